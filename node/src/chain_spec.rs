@@ -1,6 +1,6 @@
 use node_template_runtime::{
-	AccountId, AssetsConfig, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature,
-	SudoConfig, SystemConfig, WASM_BINARY,
+	AccountId, AssetsConfig, AuraConfig, BalancesConfig, DexModuleConfig, GenesisConfig,
+	GrandpaConfig, Signature, SudoConfig, SystemConfig, WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -133,6 +133,7 @@ fn testnet_genesis(
 	_enable_println: bool,
 ) -> GenesisConfig {
 	let alice = endowed_accounts.get(0).unwrap();
+	let genesis_pool_liquidity = 100_000_000_000_000_000_000u128;
 
 	let mut accounts = endowed_accounts
 		.iter()
@@ -144,7 +145,13 @@ fn testnet_genesis(
 		.map(|acc| (2u32, acc.clone(), 1_000_000_000_000_000_000_000u128))
 		.collect::<Vec<_>>();
 
+	let mut wrapped_native_account = endowed_accounts
+		.iter()
+		.map(|acc| (0u32, acc.clone(), 1_000_000_000_000_000_000_000u128))
+		.collect::<Vec<_>>();
+
 	accounts.append(&mut second_account);
+	accounts.append(&mut wrapped_native_account);
 
 	GenesisConfig {
 		system: SystemConfig {
@@ -169,15 +176,24 @@ fn testnet_genesis(
 		assets: {
 			AssetsConfig {
 				assets: vec![
+					(0u32, alice.clone(), true, 1),
 					(1u32, alice.clone(), false, 1000),
 					(2u32, alice.clone(), false, 1000),
 				],
 				metadata: vec![
+					(0u32, b"Wrapped Native".to_vec(), b"WNATIVE".to_vec(), 12u8),
 					(1u32, b"MAGIC".to_vec(), b"MAGIC".to_vec(), 12u8),
 					(2u32, b"BEANS".to_vec(), b"BEANS".to_vec(), 12u8),
 				],
 				accounts,
 			}
 		},
+		dex_module: DexModuleConfig {
+			// MAGIC and BEANS trade against the wrapped native currency, fees can be paid in them
+			pools: vec![
+				(alice.clone(), 1u32, 0u32, genesis_pool_liquidity, genesis_pool_liquidity),
+				(alice.clone(), 2u32, 0u32, genesis_pool_liquidity, genesis_pool_liquidity),
+			],
+		},
 	}
 }
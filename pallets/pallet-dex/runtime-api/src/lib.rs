@@ -0,0 +1,39 @@
+//! Runtime API definition for pallet-dex
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use pallet_dex::{DailyPoolStats, LiquidityPositionReport, PoolStats};
+use sp_arithmetic::FixedU128;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait DexApi<AccountId, AssetId, Balance, BlockNumber>
+	where
+		AccountId: Codec,
+		AssetId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Time weighted average price of `asset_a` denominated in `asset_b`
+		/// over the last `window` seconds
+		fn get_twap(asset_a: AssetId, asset_b: AssetId, window: u64) -> Option<FixedU128>;
+
+		/// Trading statistics of the pool since its creation, amounts in the order of the assets
+		fn get_pool_stats(
+			asset_a: AssetId,
+			asset_b: AssetId,
+		) -> Option<PoolStats<Balance, BlockNumber>>;
+
+		/// Trading statistics of the pool for its most recent days with trades, oldest first
+		fn get_daily_pool_stats(asset_a: AssetId, asset_b: AssetId) -> Vec<DailyPoolStats<Balance>>;
+
+		/// Value, net deposits, fees earned and impermanent loss of the account's liquidity in
+		/// the pool, amounts in the order of the assets
+		fn get_liquidity_position(
+			who: AccountId,
+			asset_a: AssetId,
+			asset_b: AssetId,
+		) -> Option<LiquidityPositionReport<Balance>>;
+	}
+}
@@ -1,6 +1,9 @@
 use crate::*;
 use frame_support::sp_runtime::traits::AtLeast32Bit;
-use sp_arithmetic::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, IntegerSquareRoot};
+use sp_arithmetic::traits::{
+	CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, IntegerSquareRoot, SaturatedConversion,
+};
+use sp_core::U256;
 
 const MULTIPLIER: u32 = 1_000;
 
@@ -71,6 +74,17 @@ pub fn get_swap_return<
 	token_a_amount: T,
 	liquidity_amounts: (T, T),
 ) -> Result<T, Error<Config>> {
+	get_swap_return_and_fee(token_a_amount, liquidity_amounts).map(|(swap_return, _)| swap_return)
+}
+
+/// Amount of token b returned for `token_a_amount`, and the swap fee in token b kept by the pool
+pub fn get_swap_return_and_fee<
+	T: AtLeast32Bit + CheckedDiv + CheckedMul + CheckedAdd + CheckedSub,
+	Config,
+>(
+	token_a_amount: T,
+	liquidity_amounts: (T, T),
+) -> Result<(T, T), Error<Config>> {
 	let swap_fee_percentage: T = SWAP_FEE_PERCENTAGE.into();
 	let swap_fee_percentage_divisor: T = SWAP_FEE_PERCENTAGE_DIVISOR.into();
 
@@ -107,7 +121,65 @@ pub fn get_swap_return<
 		.checked_div(&swap_fee_percentage_divisor)
 		.ok_or(Error::<Config>::MathOverflow)?;
 
-	Ok(returned_token_b_amount_minus_fee)
+	let swap_fee = total_b_decrease
+		.checked_sub(&returned_token_b_amount_minus_fee)
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	Ok((returned_token_b_amount_minus_fee, swap_fee))
+}
+
+/// Smallest amount of token a to swap in order to receive at least `token_b_amount`
+pub fn get_swap_input<
+	T: AtLeast32Bit + CheckedDiv + CheckedMul + CheckedAdd + CheckedSub,
+	Config,
+>(
+	token_b_amount: T,
+	liquidity_amounts: (T, T),
+) -> Result<T, Error<Config>> {
+	let swap_fee_percentage: T = SWAP_FEE_PERCENTAGE.into();
+	let swap_fee_percentage_divisor: T = SWAP_FEE_PERCENTAGE_DIVISOR.into();
+
+	let liquidity_a_amount: T =
+		if liquidity_amounts.0 == 0u32.into() { 1u32.into() } else { liquidity_amounts.0 };
+	let liquidity_b_amount: T =
+		if liquidity_amounts.1 == 0u32.into() { 1u32.into() } else { liquidity_amounts.1 };
+
+	let returned_fee_percentage_multiplier: T = swap_fee_percentage_divisor
+		.checked_sub(&swap_fee_percentage)
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	// Round up the amount that must leave the pool before the fee is taken
+	let total_b_decrease = token_b_amount
+		.checked_mul(&swap_fee_percentage_divisor)
+		.ok_or(Error::<Config>::MathOverflow)?
+		.checked_add(&returned_fee_percentage_multiplier)
+		.ok_or(Error::<Config>::MathOverflow)?
+		.checked_sub(&1u32.into())
+		.ok_or(Error::<Config>::MathOverflow)?
+		.checked_div(&returned_fee_percentage_multiplier)
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	let constant_product = liquidity_a_amount
+		.checked_mul(&liquidity_b_amount)
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	let max_new_token_b_liquidity = liquidity_b_amount
+		.checked_sub(&total_b_decrease)
+		.ok_or(Error::<Config>::MathOverflow)?
+		.checked_add(&1u32.into())
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	let min_new_token_a_liquidity = constant_product
+		.checked_div(&max_new_token_b_liquidity)
+		.ok_or(Error::<Config>::MathOverflow)?
+		.checked_add(&1u32.into())
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	let token_a_amount = min_new_token_a_liquidity
+		.checked_sub(&liquidity_a_amount)
+		.ok_or(Error::<Config>::MathOverflow)?;
+
+	Ok(token_a_amount)
 }
 
 pub fn get_redeemed_token_balance<T: AtLeast32Bit + CheckedDiv + CheckedMul>(
@@ -136,3 +208,197 @@ pub fn get_redeemed_token_balance<T: AtLeast32Bit + CheckedDiv + CheckedMul>(
 		None => None,
 	}
 }
+
+/// Whether the constant product of `new_liquidity` is at least that of `old_liquidity`
+pub fn is_constant_product_maintained<T: AtLeast32Bit + Copy>(
+	old_liquidity: (T, T),
+	new_liquidity: (T, T),
+) -> bool {
+	let constant_product = |liquidity: (T, T)| {
+		U256::from(liquidity.0.saturated_into::<u128>()) *
+			U256::from(liquidity.1.saturated_into::<u128>())
+	};
+	constant_product(new_liquidity) >= constant_product(old_liquidity)
+}
+
+/// Square root of the pool's constant product per LP token. Liquidity is provided and redeemed
+/// at the reserve ratio, so only fees grow it.
+pub fn get_liquidity_per_lp_token<T: AtLeast32Bit>(
+	liquidity_amounts: (T, T),
+	total_lp_token_supply: T,
+) -> Option<FixedU128> {
+	let constant_product = U256::from(liquidity_amounts.0.saturated_into::<u128>()) *
+		U256::from(liquidity_amounts.1.saturated_into::<u128>());
+	FixedU128::checked_from_rational(
+		u256_to_balance::<u128>(constant_product.integer_sqrt()),
+		total_lp_token_supply.saturated_into::<u128>(),
+	)
+}
+
+/// Share of the value of `held_amounts` lost by holding `liquidity_value` in the pool instead,
+/// both valued at the price of the pool's `liquidity_amounts`
+pub fn get_impermanent_loss<T: AtLeast32Bit>(
+	held_amounts: (T, T),
+	liquidity_value: (T, T),
+	liquidity_amounts: (T, T),
+) -> Permill {
+	if liquidity_amounts.0 == 0u32.into() {
+		return Permill::zero()
+	}
+	// Value in token b
+	let value_of = |amounts: (T, T)| {
+		U256::from(amounts.0.saturated_into::<u128>()) *
+			U256::from(liquidity_amounts.1.saturated_into::<u128>()) /
+			U256::from(liquidity_amounts.0.saturated_into::<u128>()) +
+			U256::from(amounts.1.saturated_into::<u128>())
+	};
+	let held_value = value_of(held_amounts);
+	let liquidity_value = value_of(liquidity_value);
+	if liquidity_value >= held_value {
+		return Permill::zero()
+	}
+	Permill::from_rational(
+		u256_to_balance::<u128>(held_value - liquidity_value),
+		u256_to_balance::<u128>(held_value),
+	)
+}
+
+fn u256_to_balance<T: AtLeast32Bit>(value: U256) -> T {
+	let value = if value > U256::from(u128::MAX) { u128::MAX } else { value.low_u128() };
+	value.saturated_into()
+}
+
+/// Price of token b, denominated in token a, of an infinitesimal swap of token a for token b,
+/// including the swap fee
+pub fn get_marginal_swap_price<T: AtLeast32Bit>(liquidity_amounts: (T, T)) -> Option<FixedU128> {
+	let returned_fee_percentage_multiplier = SWAP_FEE_PERCENTAGE_DIVISOR - SWAP_FEE_PERCENTAGE;
+
+	FixedU128::checked_from_rational(
+		liquidity_amounts
+			.0
+			.saturated_into::<u128>()
+			.checked_mul(SWAP_FEE_PERCENTAGE_DIVISOR.into())?,
+		liquidity_amounts
+			.1
+			.saturated_into::<u128>()
+			.checked_mul(returned_fee_percentage_multiplier.into())?,
+	)
+}
+
+/// Amount of token a to swap in to raise the marginal swap price of token b to `price`
+pub fn get_swap_input_to_price<T: AtLeast32Bit>(price: FixedU128, liquidity_amounts: (T, T)) -> T {
+	let returned_fee_percentage_multiplier = SWAP_FEE_PERCENTAGE_DIVISOR - SWAP_FEE_PERCENTAGE;
+	let liquidity_a_amount = U256::from(liquidity_amounts.0.saturated_into::<u128>());
+	let liquidity_b_amount = U256::from(liquidity_amounts.1.saturated_into::<u128>());
+
+	// The marginal price after swapping in x is (a + x)^2 / (a * b * fee multiplier)
+	let new_token_a_liquidity_squared = U256::from(price.into_inner())
+		.saturating_mul(liquidity_a_amount)
+		.saturating_mul(liquidity_b_amount)
+		.saturating_mul(returned_fee_percentage_multiplier.into()) /
+		U256::from(SWAP_FEE_PERCENTAGE_DIVISOR) /
+		U256::from(FixedU128::DIV);
+
+	u256_to_balance(
+		new_token_a_liquidity_squared.integer_sqrt().saturating_sub(liquidity_a_amount),
+	)
+}
+
+/// Value of `token_a_amount` in token b at the ratio of the pool reserves, without fee or
+/// price impact. Zero for an empty pool.
+pub fn get_amount_at_reserve_ratio<T: AtLeast32Bit>(
+	token_a_amount: T,
+	liquidity_amounts: (T, T),
+) -> T {
+	let liquidity_a_amount = U256::from(liquidity_amounts.0.saturated_into::<u128>());
+	if liquidity_a_amount.is_zero() {
+		return 0u32.into()
+	}
+
+	u256_to_balance(
+		U256::from(token_a_amount.saturated_into::<u128>())
+			.saturating_mul(U256::from(liquidity_amounts.1.saturated_into::<u128>())) /
+			liquidity_a_amount,
+	)
+}
+
+/// Amount of a batch's larger side input, `token_a_amount`, to swap through the pool so that
+/// both sides of the batch trade at the same price. The rest is matched directly against
+/// the opposing side's input, `token_b_amount`.
+pub fn get_batch_pool_input<
+	T: AtLeast32Bit + CheckedDiv + CheckedMul + CheckedAdd + CheckedSub,
+	Config,
+>(
+	token_a_amount: T,
+	token_b_amount: T,
+	liquidity_amounts: (T, T),
+) -> Result<T, Error<Config>> {
+	if token_b_amount == 0u32.into() {
+		return Ok(token_a_amount)
+	}
+
+	let token_a = U256::from(token_a_amount.saturated_into::<u128>());
+	let token_b = U256::from(token_b_amount.saturated_into::<u128>());
+	let matched_value = token_a.saturating_mul(token_b);
+
+	// The a side receives b's input plus the pool return, the b side receives the unswapped a.
+	// Equal prices means (b + return(x)) * (a - x) == a * b, the largest such x is searched.
+	let mut low: u128 = 0;
+	let mut high: u128 = token_a_amount.saturated_into::<u128>();
+	while high - low > 1 {
+		let mid = low + (high - low) / 2;
+		let swap_return = get_swap_return::<T, Config>(mid.saturated_into(), liquidity_amounts)?;
+		let received_value = (token_b + U256::from(swap_return.saturated_into::<u128>()))
+			.saturating_mul(token_a - U256::from(mid));
+		if received_value >= matched_value {
+			low = mid;
+		} else {
+			high = mid;
+		}
+	}
+
+	Ok(low.saturated_into())
+}
+
+/// Least acceptable return for swapping `token_a_amount` of token a: what the swap would return
+/// at the current marginal price, after the swap fee, reduced by `max_slippage`
+pub fn get_min_swap_return<T: AtLeast32Bit>(
+	token_a_amount: T,
+	liquidity_amounts: (T, T),
+	max_slippage: Permill,
+) -> Option<T> {
+	let returned_fee_percentage_multiplier = SWAP_FEE_PERCENTAGE_DIVISOR - SWAP_FEE_PERCENTAGE;
+	let liquidity_a_amount = U256::from(liquidity_amounts.0.saturated_into::<u128>());
+	if liquidity_a_amount.is_zero() {
+		return None
+	}
+
+	let marginal_return = U256::from(token_a_amount.saturated_into::<u128>())
+		.checked_mul(U256::from(liquidity_amounts.1.saturated_into::<u128>()))?
+		.checked_mul(returned_fee_percentage_multiplier.into())? /
+		liquidity_a_amount /
+		U256::from(SWAP_FEE_PERCENTAGE_DIVISOR);
+	let marginal_return: T = u256_to_balance(marginal_return);
+
+	Some(max_slippage.left_from_one().mul_floor(marginal_return))
+}
+
+/// How much of an order selling `order_amount` of token b at `price` (in token a) can be bought
+/// with `available` of token a, and what it costs. The cost is rounded up in favour of the seller.
+pub fn get_order_fill<T: AtLeast32Bit>(
+	available: T,
+	order_amount: T,
+	price: FixedU128,
+) -> Option<(T, T)> {
+	let accuracy = U256::from(FixedU128::DIV);
+	let price = U256::from(price.into_inner());
+	if price.is_zero() {
+		return None
+	}
+
+	let affordable_amount = U256::from(available.saturated_into::<u128>()) * accuracy / price;
+	let fill_amount = affordable_amount.min(U256::from(order_amount.saturated_into::<u128>()));
+	let cost = (fill_amount * price + accuracy - 1) / accuracy;
+
+	Some((u256_to_balance(fill_amount), u256_to_balance(cost)))
+}
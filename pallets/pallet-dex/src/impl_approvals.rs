@@ -0,0 +1,87 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	pub fn check_allowance(
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		ensure!(
+			T::Assets::allowance(asset, owner, delegate) >= amount,
+			Error::<T>::InsufficientAllowance
+		);
+		Ok(())
+	}
+
+	/// Move funds from `owner` into the pallet account, spending the approval `owner` gave
+	/// `delegate`
+	pub fn escrow_approved_tokens(
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let escrow = Self::account_id();
+		Self::ensure_pallet_account_alive(&escrow);
+		T::Assets::transfer_from(asset, owner, delegate, &escrow, amount)?;
+		Ok(())
+	}
+
+	/// Spend `amount` of the approval `owner` gave `delegate`. The funds pass through escrow
+	/// and are returned to `owner`, to be used on their behalf.
+	pub fn spend_approval(
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		Self::escrow_approved_tokens(owner, delegate, asset, amount)?;
+		Self::release_escrowed_tokens(owner, asset, amount)
+	}
+
+	/// Swap `owner`'s asset_pair.0 on their behalf, sending the asset_pair.1 received to
+	/// `recipient`. The swap is made by `owner`, with exactly the amount of the approval spent.
+	/// A swap tripping the pool's circuit breaker is reverted with the approval and returns
+	/// `None`.
+	pub fn process_swap_from(
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+		recipient: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		asset_a_amount: BalanceOf<T>,
+		keep_alive: bool,
+	) -> Result<Option<BalanceOf<T>>, DispatchError> {
+		Self::with_circuit_breaker(asset_pair, || {
+			Self::spend_approval(owner, delegate, asset_pair.0, asset_a_amount)?;
+			Self::process_routed_swap(owner, recipient, asset_pair, asset_a_amount, keep_alive)
+		})
+	}
+
+	/// Deposit `owner`'s tokens into the pool on their behalf, minting the LP tokens to `owner`.
+	/// The deposit is made by `owner`, with exactly the amounts of the approvals spent.
+	/// With `keep_alive` set, `owner`'s asset accounts are kept alive.
+	#[transactional]
+	pub fn process_liquidity_pool_deposit_from(
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+		current_token_amount: BalanceOf<T>,
+		keep_alive: bool,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		Self::spend_approval(owner, delegate, asset_pair.0, asset_amounts.0)?;
+		Self::spend_approval(owner, delegate, asset_pair.1, asset_amounts.1)?;
+		Self::process_liquidity_pool_deposit(
+			owner,
+			owner,
+			asset_pair,
+			asset_amounts,
+			current_token_amount,
+			keep_alive,
+		)
+	}
+}
@@ -0,0 +1,234 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Weight of clearing a full batch of a single pool
+	pub fn batch_auction_clearing_weight() -> Weight {
+		let swap_weight = 10_000 + T::DbWeight::get().reads_writes(2, 2);
+		(20_000 + T::DbWeight::get().reads_writes(8, 7))
+			.saturating_add(swap_weight.saturating_mul(T::MaxBatchSwapsPerPool::get().into()))
+	}
+
+	/// Weight reserved at the start of the block for clearing every batch auction pool's batch
+	/// at its end, whether or not swaps are submitted to it
+	pub fn batch_auctions_clearing_weight() -> Weight {
+		let pool_count: u64 = BatchAuctionPools::<T>::count().into();
+		T::DbWeight::get()
+			.reads(1)
+			.saturating_add(Self::batch_auction_clearing_weight().saturating_mul(pool_count))
+	}
+
+	pub fn check_direct_swaps_allowed(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		ensure!(!Self::is_batch_auction_pool(&pool_id), Error::<T>::PoolInBatchAuctionMode);
+		Ok(())
+	}
+
+	#[transactional]
+	pub fn collect_batch_swap(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount_in: BalanceOf<T>,
+		min_amount_out: BalanceOf<T>,
+	) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		ensure!(Self::is_batch_auction_pool(&pool_id), Error::<T>::PoolNotInBatchAuctionMode);
+		ensure!(amount_in > 0u32.into(), Error::<T>::InvalidOrderParameters);
+
+		PendingBatchSwaps::<T>::try_mutate(&pool_id, |swaps| {
+			swaps
+				.try_push(BatchSwap {
+					who: sender.clone(),
+					asset_in: asset_pair.0,
+					asset_out: asset_pair.1,
+					amount_in,
+					min_amount_out,
+				})
+				.map_err(|_| Error::<T>::TooManyBatchSwaps)
+		})?;
+
+		Self::escrow_tokens(sender, asset_pair.0, amount_in)?;
+
+		Self::deposit_event(Event::BatchSwapSubmitted {
+			pool_id,
+			who: sender.clone(),
+			asset_in: asset_pair.0,
+			amount_in,
+			min_amount_out,
+		});
+
+		Ok(())
+	}
+
+	/// Clear the batches collected for every pool in this block
+	pub fn clear_batch_auctions() {
+		for (pool_id, swaps) in PendingBatchSwaps::<T>::drain() {
+			let swaps = swaps.into_inner();
+			if Self::clear_batch_auction(&pool_id, swaps.clone()).is_err() {
+				// Nothing of a failed batch was executed, return all of it
+				for swap in swaps.iter() {
+					Self::refund_batch_swap(&pool_id, swap);
+				}
+			}
+		}
+	}
+
+	fn refund_batch_swap(
+		pool_id: &T::AccountId,
+		swap: &BatchSwap<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+	) {
+		let _ = Self::release_escrowed_tokens(&swap.who, swap.asset_in, swap.amount_in);
+		Self::deposit_event(Event::BatchSwapRefunded {
+			pool_id: pool_id.clone(),
+			who: swap.who.clone(),
+			amount_in: swap.amount_in,
+		});
+	}
+
+	/// Total input of the batch's swaps selling asset_pair.0 and of those selling asset_pair.1
+	fn get_batch_amounts_in(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		swaps: &[BatchSwap<T::AccountId, AssetIdOf<T>, BalanceOf<T>>],
+	) -> (BalanceOf<T>, BalanceOf<T>) {
+		swaps.iter().fold((0u32.into(), 0u32.into()), |amounts_in, swap| {
+			if swap.asset_in == asset_pair.0 {
+				(amounts_in.0.saturating_add(swap.amount_in), amounts_in.1)
+			} else {
+				(amounts_in.0, amounts_in.1.saturating_add(swap.amount_in))
+			}
+		})
+	}
+
+	/// Clear the batch's inputs against each other and the pool at a single price.
+	/// Returns what the sellers of asset_pair.0 and of asset_pair.1 receive in total, and the
+	/// swap to execute through the pool.
+	fn get_batch_clearing(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amounts_in: (BalanceOf<T>, BalanceOf<T>),
+	) -> Result<
+		((BalanceOf<T>, BalanceOf<T>), (AssetIdOf<T>, AssetIdOf<T>), BalanceOf<T>),
+		DispatchError,
+	> {
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		ensure!(
+			pool_liquidity.0 > 0u32.into() && pool_liquidity.1 > 0u32.into(),
+			Error::<T>::NotEnoughLiquidity
+		);
+
+		// The side worth more at the pool's reserve ratio swaps its excess through the pool
+		if get_amount_at_reserve_ratio(amounts_in.0, pool_liquidity) >= amounts_in.1 {
+			let pool_input = get_batch_pool_input::<BalanceOf<T>, T>(
+				amounts_in.0,
+				amounts_in.1,
+				pool_liquidity,
+			)?;
+			let swap_return = get_swap_return::<BalanceOf<T>, T>(pool_input, pool_liquidity)?;
+			Ok((
+				(amounts_in.1.saturating_add(swap_return), amounts_in.0 - pool_input),
+				asset_pair,
+				pool_input,
+			))
+		} else {
+			let pool_liquidity = (pool_liquidity.1, pool_liquidity.0);
+			let pool_input = get_batch_pool_input::<BalanceOf<T>, T>(
+				amounts_in.1,
+				amounts_in.0,
+				pool_liquidity,
+			)?;
+			let swap_return = get_swap_return::<BalanceOf<T>, T>(pool_input, pool_liquidity)?;
+			Ok((
+				(amounts_in.1 - pool_input, amounts_in.0.saturating_add(swap_return)),
+				(asset_pair.1, asset_pair.0),
+				pool_input,
+			))
+		}
+	}
+
+	/// The swap's pro rata share of what its side of the batch receives
+	fn get_batch_swap_return(
+		swap: &BatchSwap<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amounts_in: (BalanceOf<T>, BalanceOf<T>),
+		amounts_out: (BalanceOf<T>, BalanceOf<T>),
+	) -> BalanceOf<T> {
+		if swap.asset_in == asset_pair.0 {
+			get_amount_at_reserve_ratio(swap.amount_in, (amounts_in.0, amounts_out.0))
+		} else {
+			get_amount_at_reserve_ratio(swap.amount_in, (amounts_in.1, amounts_out.1))
+		}
+	}
+
+	/// Clear a pool's batch at a single price, refunding the swaps whose minimum return it misses.
+	/// The whole batch is refunded if its swap through the pool trips the circuit breaker.
+	#[transactional]
+	fn clear_batch_auction(
+		pool_id: &T::AccountId,
+		mut swaps: Vec<BatchSwap<T::AccountId, AssetIdOf<T>, BalanceOf<T>>>,
+	) -> DispatchResult {
+		let asset_pair = match swaps.first() {
+			Some(swap) => Self::sort_asset_pair((swap.asset_in, swap.asset_out)),
+			None => return Ok(()),
+		};
+		Self::settle_long_term_orders(asset_pair)?;
+
+		// Every refund changes the clearing price, so repeat until all minimums are met
+		let (amounts_in, amounts_out, pool_swap_pair, pool_input) = loop {
+			if swaps.is_empty() {
+				return Ok(())
+			}
+			let amounts_in = Self::get_batch_amounts_in(asset_pair, &swaps);
+			let (amounts_out, pool_swap_pair, pool_input) =
+				Self::get_batch_clearing(asset_pair, amounts_in)?;
+
+			let (filled, unfilled): (Vec<_>, Vec<_>) = swaps.into_iter().partition(|swap| {
+				Self::get_batch_swap_return(swap, asset_pair, amounts_in, amounts_out) >=
+					swap.min_amount_out
+			});
+			swaps = filled;
+			if unfilled.is_empty() {
+				break (amounts_in, amounts_out, pool_swap_pair, pool_input)
+			}
+			for swap in unfilled.iter() {
+				Self::refund_batch_swap(pool_id, swap);
+			}
+		};
+
+		if pool_input > 0u32.into() {
+			let escrow = Self::account_id();
+			let swapped = Self::with_circuit_breaker(pool_swap_pair, || {
+				Self::process_swap(&escrow, &escrow, pool_swap_pair, pool_input, false)
+			})?;
+			if swapped.is_none() {
+				for swap in swaps.iter() {
+					Self::refund_batch_swap(pool_id, swap);
+				}
+				return Ok(())
+			}
+		}
+
+		for swap in swaps.iter() {
+			let amount_out = Self::get_batch_swap_return(swap, asset_pair, amounts_in, amounts_out);
+			if amount_out > 0u32.into() {
+				Self::release_escrowed_tokens(&swap.who, swap.asset_out, amount_out)?;
+			}
+			Self::deposit_event(Event::BatchSwapFilled {
+				pool_id: pool_id.clone(),
+				who: swap.who.clone(),
+				amount_in: swap.amount_in,
+				amount_out,
+			});
+		}
+
+		Self::deposit_event(Event::BatchAuctionCleared {
+			pool_id: pool_id.clone(),
+			asset_pair,
+			amounts_in,
+			amounts_out,
+			reserves: Self::get_pool_liquidity(asset_pair)?,
+		});
+
+		Ok(())
+	}
+}
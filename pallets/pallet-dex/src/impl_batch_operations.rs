@@ -0,0 +1,111 @@
+use super::*;
+
+impl<T: Config> Pallet<T> {
+	/// Weight of a `batch` call, the sum of the weights of its operations
+	pub fn batch_weight(operations: &[DexOperation<AssetIdOf<T>, BalanceOf<T>>]) -> Weight {
+		operations.iter().fold(10_000, |weight, operation| {
+			weight.saturating_add(Self::dex_operation_weight(operation))
+		})
+	}
+
+	/// Weight of a batched operation, from the storage its code path accesses
+	fn dex_operation_weight(operation: &DexOperation<AssetIdOf<T>, BalanceOf<T>>) -> Weight {
+		match operation {
+			// Balance checks, trading mode, pool status and a circuit breaker trip
+			DexOperation::Swap { .. } => (10_000 + T::DbWeight::get().reads_writes(5, 1))
+				.saturating_add(Self::routed_swap_weight()),
+			DexOperation::AddLiquidity { .. } => Self::liquidity_deposit_weight(),
+			DexOperation::RemoveLiquidity { .. } => Self::lp_redemption_weight(),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Execute `operations` for `sender` in order. An atomic batch fails with its first failing
+	/// operation, otherwise failing operations are skipped. A swap tripping its pool's circuit
+	/// breaker fails an atomic batch, reverting the trip with it, and is skipped otherwise.
+	#[transactional]
+	pub fn execute_batch(
+		sender: &T::AccountId,
+		operations: Vec<DexOperation<AssetIdOf<T>, BalanceOf<T>>>,
+		atomic: bool,
+	) -> DispatchResult {
+		let mut failed = vec![];
+		for (index, operation) in operations.iter().enumerate() {
+			match Self::execute_dex_operation(sender, operation) {
+				Ok(true) => {},
+				Ok(false) if atomic => return Err(Error::<T>::PriceMovementLimitExceeded.into()),
+				Ok(false) => failed.push(index as u32),
+				Err(error) if atomic => return Err(error),
+				Err(_) => failed.push(index as u32),
+			}
+		}
+
+		Self::deposit_event(Event::BatchExecuted {
+			who: sender.clone(),
+			operations: operations.len() as u32,
+			failed,
+		});
+
+		Ok(())
+	}
+
+	/// Execute a single batched operation, reverting its changes if it fails.
+	/// Returns whether it was executed, a swap tripping its pool's circuit breaker is not.
+	#[transactional]
+	pub fn execute_dex_operation(
+		sender: &T::AccountId,
+		operation: &DexOperation<AssetIdOf<T>, BalanceOf<T>>,
+	) -> Result<bool, DispatchError> {
+		match *operation {
+			DexOperation::Swap { asset_in, asset_out, amount_in, min_amount_out } => {
+				let asset_pair = (asset_in, asset_out);
+				Self::check_deposit_is_valid(sender, asset_pair, (amount_in, 0u32.into()), false)?;
+				Self::check_direct_swaps_allowed(asset_pair)?;
+				Self::check_swaps_allowed(asset_pair)?;
+
+				let swapped = Self::process_swap_with_circuit_breaker(
+					sender,
+					sender,
+					asset_pair,
+					amount_in,
+					min_amount_out,
+					false,
+				)?;
+				if swapped.is_none() {
+					return Ok(false)
+				}
+			},
+			DexOperation::AddLiquidity {
+				asset_a,
+				asset_b,
+				amount_a,
+				max_amount_b,
+				min_lp_tokens,
+			} => {
+				<Self as LiquidityHandler<_, _, _>>::add_liquidity(
+					sender,
+					asset_a,
+					asset_b,
+					amount_a,
+					max_amount_b,
+					min_lp_tokens,
+				)?;
+			},
+			DexOperation::RemoveLiquidity { asset_a, asset_b, lp_token_amount, min_amounts } => {
+				<Self as LiquidityHandler<_, _, _>>::remove_liquidity(
+					sender,
+					asset_a,
+					asset_b,
+					lp_token_amount,
+					min_amounts,
+				)?;
+			},
+		}
+
+		Ok(true)
+	}
+}
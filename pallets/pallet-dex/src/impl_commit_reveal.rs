@@ -0,0 +1,115 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Weight of expiring a single swap commitment
+	pub fn commitment_expiry_weight() -> Weight {
+		10_000 + T::DbWeight::get().reads_writes(2, 2)
+	}
+
+	/// Hash a swap is committed to by `commit_swap`, the salt keeps the parameters from being
+	/// guessed before they are revealed
+	pub fn swap_commitment_hash(
+		owner: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		asset_a_amount: BalanceOf<T>,
+		min_amount_out: BalanceOf<T>,
+		salt: &[u8; 32],
+	) -> T::Hash {
+		T::Hashing::hash_of(&(owner, asset_pair, asset_a_amount, min_amount_out, salt))
+	}
+
+	#[transactional]
+	pub fn create_swap_commitment(sender: &T::AccountId, commitment: T::Hash) -> DispatchResult {
+		ensure!(
+			!SwapCommitments::<T>::contains_key(&commitment),
+			Error::<T>::DuplicateCommitment
+		);
+
+		let now = frame_system::Pallet::<T>::block_number();
+		let reveal_from = now + T::MinRevealDelay::get();
+		let reveal_until = now + T::RevealWindow::get();
+		// Reveals must come in a later block than the commitment
+		ensure!(
+			now < reveal_from && reveal_from <= reveal_until,
+			Error::<T>::InvalidOrderParameters
+		);
+
+		// The commitment is cleaned up once its reveal window has closed
+		CommitmentExpiries::<T>::try_mutate(reveal_until + 1u32.into(), |expiries| {
+			expiries.try_push(commitment).map_err(|_| Error::<T>::TooManyCommitments)
+		})?;
+
+		let deposit = T::CommitmentDeposit::get();
+		T::Balances::reserve(sender, deposit)?;
+
+		SwapCommitments::<T>::insert(
+			&commitment,
+			SwapCommitment { owner: sender.clone(), deposit, reveal_from, reveal_until },
+		);
+
+		Self::deposit_event(Event::SwapCommitted {
+			commitment,
+			owner: sender.clone(),
+			reveal_from,
+			reveal_until,
+		});
+
+		Ok(())
+	}
+
+	/// Remove a commitment being revealed and return its deposit
+	#[transactional]
+	pub fn remove_swap_commitment(sender: &T::AccountId, commitment: T::Hash) -> DispatchResult {
+		let swap_commitment =
+			Self::swap_commitments(&commitment).ok_or(Error::<T>::CommitmentNotFound)?;
+		// The owner is part of the hash, a different sender cannot have revealed a match
+		ensure!(swap_commitment.owner == *sender, Error::<T>::CommitmentNotFound);
+
+		let now = frame_system::Pallet::<T>::block_number();
+		ensure!(now >= swap_commitment.reveal_from, Error::<T>::RevealTooEarly);
+		ensure!(now <= swap_commitment.reveal_until, Error::<T>::RevealTooLate);
+
+		CommitmentExpiries::<T>::mutate_exists(
+			swap_commitment.reveal_until + 1u32.into(),
+			|maybe_expiries| {
+				if let Some(expiries) = maybe_expiries {
+					expiries.retain(|hash| *hash != commitment);
+					if expiries.is_empty() {
+						*maybe_expiries = None;
+					}
+				}
+			},
+		);
+		SwapCommitments::<T>::remove(&commitment);
+
+		T::Balances::unreserve(sender, swap_commitment.deposit);
+
+		Ok(())
+	}
+
+	/// Forfeit the deposits of the commitments whose reveal window closed before block `now`.
+	/// Returns the weight consumed.
+	pub fn expire_swap_commitments(now: T::BlockNumber) -> Weight {
+		let expired = CommitmentExpiries::<T>::take(now);
+		let mut consumed_weight = T::DbWeight::get().reads_writes(1, 1);
+
+		for commitment in expired {
+			consumed_weight = consumed_weight.saturating_add(Self::commitment_expiry_weight());
+			if let Some(swap_commitment) = SwapCommitments::<T>::take(&commitment) {
+				// The slashed deposit is burned
+				let (_, unslashed) =
+					T::Balances::slash_reserved(&swap_commitment.owner, swap_commitment.deposit);
+				Self::deposit_event(Event::SwapCommitmentExpired {
+					commitment,
+					owner: swap_commitment.owner,
+					forfeited: swap_commitment.deposit.saturating_sub(unslashed),
+				});
+			}
+		}
+
+		consumed_weight
+	}
+}
@@ -0,0 +1,269 @@
+use super::*;
+
+impl PriceTrigger {
+	pub fn is_valid(&self) -> bool {
+		match *self {
+			PriceTrigger::Below(price) | PriceTrigger::Above(price) => price.into_inner() > 0,
+			PriceTrigger::OutsideRange { lower, upper } => lower.into_inner() > 0 && lower < upper,
+		}
+	}
+
+	pub fn is_met(&self, price: FixedU128) -> bool {
+		match *self {
+			PriceTrigger::Below(threshold) => price < threshold,
+			PriceTrigger::Above(threshold) => price > threshold,
+			PriceTrigger::OutsideRange { lower, upper } => price < lower || price > upper,
+		}
+	}
+}
+
+impl<AssetId: Copy, Balance> ConditionalAction<AssetId, Balance> {
+	/// Pair whose price triggers the action, the price is of the first asset in the second
+	pub fn asset_pair(&self) -> (AssetId, AssetId) {
+		match *self {
+			ConditionalAction::Swap { asset_in, asset_out, .. } => (asset_in, asset_out),
+			ConditionalAction::RedeemLiquidity { asset_a, asset_b, .. } => (asset_a, asset_b),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Weight of checking whether a single conditional order is triggered
+	pub fn conditional_order_check_weight() -> Weight {
+		T::DbWeight::get().reads(4)
+	}
+
+	/// Weight of executing a single triggered conditional order
+	pub fn conditional_order_execution_weight() -> Weight {
+		10_000 + T::DbWeight::get().reads_writes(7, 9)
+	}
+
+	pub fn check_conditional_order_is_valid(
+		sender: &T::AccountId,
+		action: &ConditionalAction<AssetIdOf<T>, BalanceOf<T>>,
+	) -> DispatchResult {
+		match *action {
+			ConditionalAction::Swap { asset_in, asset_out, amount_in, .. } => {
+				Self::check_deposit_is_valid(
+					sender,
+					(asset_in, asset_out),
+					(amount_in, 0u32.into()),
+					false,
+				)?;
+				Self::check_swaps_allowed((asset_in, asset_out))
+			},
+			ConditionalAction::RedeemLiquidity { asset_a, asset_b, lp_token_amount, .. } => {
+				let lp_token_id = Self::get_lp_token_id(&Self::get_pool_id((asset_a, asset_b)));
+				Self::check_lp_redemption_is_valid(
+					sender,
+					lp_token_id,
+					lp_token_amount,
+					(asset_a, asset_b),
+				)
+			},
+		}
+	}
+
+	/// Asset and amount held in escrow for the action
+	fn conditional_action_input(
+		action: &ConditionalAction<AssetIdOf<T>, BalanceOf<T>>,
+	) -> (AssetIdOf<T>, BalanceOf<T>) {
+		match *action {
+			ConditionalAction::Swap { asset_in, amount_in, .. } => (asset_in, amount_in),
+			ConditionalAction::RedeemLiquidity { asset_a, asset_b, lp_token_amount, .. } =>
+				(Self::get_lp_token_id(&Self::get_pool_id((asset_a, asset_b))), lp_token_amount),
+		}
+	}
+
+	#[transactional]
+	pub fn create_conditional_order(
+		sender: &T::AccountId,
+		action: ConditionalAction<AssetIdOf<T>, BalanceOf<T>>,
+		trigger: PriceTrigger,
+		price_source: PriceSource,
+	) -> Result<OrderId, DispatchError> {
+		let (input_asset, input_amount) = Self::conditional_action_input(&action);
+		ensure!(
+			input_amount > 0u32.into() &&
+				trigger.is_valid() &&
+				price_source != PriceSource::Twap { window: 0 },
+			Error::<T>::InvalidOrderParameters
+		);
+
+		let order_id = NextOrderId::<T>::get();
+		let pool_id = Self::get_pool_id(action.asset_pair());
+
+		Self::escrow_tokens(sender, input_asset, input_amount)?;
+
+		ConditionalOrders::<T>::insert(
+			order_id,
+			ConditionalOrder { owner: sender.clone(), action, trigger, price_source },
+		);
+		PoolConditionalOrders::<T>::insert(&pool_id, order_id, ());
+		NextOrderId::<T>::put(order_id.saturating_add(1));
+
+		Self::deposit_event(Event::ConditionalOrderPlaced {
+			order_id,
+			owner: sender.clone(),
+			trigger,
+		});
+
+		Ok(order_id)
+	}
+
+	#[transactional]
+	pub fn remove_conditional_order(sender: &T::AccountId, order_id: OrderId) -> DispatchResult {
+		let order = Self::conditional_orders(order_id).ok_or(Error::<T>::OrderNotFound)?;
+		ensure!(order.owner == *sender, Error::<T>::NotOrderOwner);
+
+		ConditionalOrders::<T>::remove(order_id);
+		PoolConditionalOrders::<T>::remove(Self::get_pool_id(order.action.asset_pair()), order_id);
+
+		let (input_asset, input_amount) = Self::conditional_action_input(&order.action);
+		Self::release_escrowed_tokens(sender, input_asset, input_amount)?;
+
+		Self::deposit_event(Event::ConditionalOrderCancelled { order_id, owner: sender.clone() });
+
+		Ok(())
+	}
+
+	/// Price the order is evaluated at, if it currently meets the order's trigger
+	pub fn conditional_order_trigger_price(
+		order: &ConditionalOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+	) -> Option<FixedU128> {
+		let asset_pair = order.action.asset_pair();
+		let price = match order.price_source {
+			PriceSource::Spot => Self::get_spot_price(asset_pair),
+			PriceSource::Twap { window } => Self::get_twap(asset_pair, window),
+		}?;
+		order.trigger.is_met(price).then(|| price)
+	}
+
+	/// Execute the order's action from escrow and pass the proceeds on to the owner.
+	/// Fails without side effects if the proceeds fall short of the action's minimums.
+	/// The order stays open if its swap trips the pool's circuit breaker.
+	#[transactional]
+	pub fn try_execute_conditional_order(
+		order_id: OrderId,
+		order: &ConditionalOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+		price: FixedU128,
+	) -> DispatchResult {
+		let escrow = Self::account_id();
+
+		match order.action {
+			ConditionalAction::Swap { asset_in, asset_out, amount_in, min_amount_out } => {
+				let asset_pair = (asset_in, asset_out);
+				let swap_return = match Self::with_circuit_breaker(asset_pair, || {
+					let swap_return =
+						Self::process_swap(&escrow, &escrow, asset_pair, amount_in, false)?;
+					ensure!(swap_return >= min_amount_out, Error::<T>::SlippageExceeded);
+					Ok(swap_return)
+				})? {
+					Some(swap_return) => swap_return,
+					None => return Ok(()),
+				};
+				Self::release_escrowed_tokens(&order.owner, asset_out, swap_return)?;
+
+				Self::deposit_event(Event::ConditionalSwapExecuted {
+					order_id,
+					owner: order.owner.clone(),
+					price,
+					amount_in,
+					amount_out: swap_return,
+				});
+			},
+			ConditionalAction::RedeemLiquidity {
+				asset_a,
+				asset_b,
+				lp_token_amount,
+				min_amounts,
+			} => {
+				let pool_id = Self::get_pool_id((asset_a, asset_b));
+				let lp_token_id = Self::get_lp_token_id(&pool_id);
+
+				// The owner redeems, so the withdrawal is taken off their liquidity position
+				Self::release_escrowed_tokens(&order.owner, lp_token_id, lp_token_amount)?;
+				let redeemed_token_amounts = Self::handle_lp_token_redemption(
+					&order.owner,
+					&order.owner,
+					pool_id,
+					lp_token_id,
+					lp_token_amount,
+					(asset_a, asset_b),
+				)?;
+				ensure!(
+					redeemed_token_amounts.0 >= min_amounts.0 &&
+						redeemed_token_amounts.1 >= min_amounts.1,
+					Error::<T>::SlippageExceeded
+				);
+
+				Self::deposit_event(Event::ConditionalRedemptionExecuted {
+					order_id,
+					owner: order.owner.clone(),
+					price,
+					lp_token_amount,
+					redeemed_token_amounts,
+				});
+			},
+		}
+
+		ConditionalOrders::<T>::remove(order_id);
+		PoolConditionalOrders::<T>::remove(Self::get_pool_id(order.action.asset_pair()), order_id);
+
+		Ok(())
+	}
+
+	/// Check open conditional orders, continuing from where the previous block stopped, and
+	/// execute those that are triggered. At most `MaxConditionalExecutionsPerBlock` orders are
+	/// executed and no more than `remaining_weight` is consumed. Returns the weight consumed.
+	pub fn execute_conditional_orders(remaining_weight: Weight) -> Weight {
+		let check_weight = Self::conditional_order_check_weight();
+		let execution_weight = Self::conditional_order_execution_weight();
+		let mut consumed_weight = T::DbWeight::get().reads_writes(1, 1);
+		if consumed_weight > remaining_weight {
+			return 0
+		}
+
+		let orders = match ConditionalOrderCursor::<T>::get() {
+			Some(order_id) =>
+				ConditionalOrders::<T>::iter_from(ConditionalOrders::<T>::hashed_key_for(order_id)),
+			None => ConditionalOrders::<T>::iter(),
+		};
+
+		// Find the triggered orders first, the map must not change while it is iterated
+		let mut triggered = vec![];
+		let mut last_checked = None;
+		let mut all_checked = true;
+		for (order_id, order) in orders {
+			// Every check must leave room for the execution it could lead to
+			if triggered.len() as u32 >= T::MaxConditionalExecutionsPerBlock::get() ||
+				consumed_weight.saturating_add(check_weight + execution_weight) > remaining_weight
+			{
+				all_checked = false;
+				break
+			}
+			consumed_weight = consumed_weight.saturating_add(check_weight);
+			last_checked = Some(order_id);
+
+			if let Some(price) = Self::conditional_order_trigger_price(&order) {
+				consumed_weight = consumed_weight.saturating_add(execution_weight);
+				triggered.push((order_id, order, price));
+			}
+		}
+
+		// Orders failing their minimums stay open and are retried later
+		for (order_id, order, price) in triggered {
+			let _ = Self::try_execute_conditional_order(order_id, &order, price);
+		}
+
+		match last_checked {
+			Some(order_id) if !all_checked => ConditionalOrderCursor::<T>::put(order_id),
+			_ => ConditionalOrderCursor::<T>::kill(),
+		}
+
+		consumed_weight
+	}
+}
@@ -4,36 +4,85 @@ impl<T: Config> Pallet<T>
 where
 	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
 {
+	pub fn is_asset_allowed(asset_id: AssetIdOf<T>) -> bool {
+		match Self::asset_list_mode() {
+			AssetListMode::DenyList => !Self::is_asset_listed(asset_id),
+			AssetListMode::AllowList => Self::is_asset_listed(asset_id),
+		}
+	}
+
+	pub fn check_pool_assets_allowed(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		ensure!(
+			Self::is_asset_allowed(asset_pair.0) && Self::is_asset_allowed(asset_pair.1),
+			Error::<T>::AssetNotAllowed
+		);
+		Ok(())
+	}
+
+	/// Reserve the pool creation deposit from the pool's creator
+	fn reserve_pool_creation_deposit(
+		sender: &T::AccountId,
+		pool_id: &T::AccountId,
+	) -> DispatchResult {
+		let deposit = T::PoolCreationDeposit::get();
+		T::Balances::reserve(sender, deposit)?;
+		PoolCreationDeposits::<T>::insert(pool_id, (sender.clone(), deposit));
+		Ok(())
+	}
+
+	/// Return the pool creation deposit to the pool's creator
+	pub fn release_pool_creation_deposit(pool_id: &T::AccountId) {
+		if let Some((creator, deposit)) = PoolCreationDeposits::<T>::take(pool_id) {
+			T::Balances::unreserve(&creator, deposit);
+		}
+	}
 
 	// TODO: get rid of unwraps
 	pub fn send_lp_tokens_to_pool_creator(
 		sender: &T::AccountId,
 		pool_id: &T::AccountId,
 		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
-	) -> Result<(), DispatchError> {
+	) -> Result<(AssetIdOf<T>, BalanceOf<T>), DispatchError> {
 		let lp_tokens_amount =
 			get_lp_tokens_for_new_pool(asset_amounts.0, asset_amounts.1).unwrap();
 		let asset_id: AssetIdOf<T> = Self::get_lp_token_id(pool_id);
 		T::Assets::create(asset_id, Self::account_id(), true, 1u32.into())?;
 		T::Assets::mint_into(asset_id, sender, lp_tokens_amount)?;
-		Ok(())
+		Ok((asset_id, lp_tokens_amount))
 	}
 
+	#[transactional]
 	pub fn create_new_pool(
 		sender: &T::AccountId,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
 		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+		keep_alive: bool,
 	) -> Result<(), DispatchError> {
 		// Initialize the new pool
-		let pool_id = Self::initialize_pool(asset_pair);
+		let pool_id = Self::initialize_pool(sender, asset_pair)?;
+		Self::reserve_pool_creation_deposit(sender, &pool_id)?;
+		Pools::<T>::insert(&pool_id, Self::sort_asset_pair(asset_pair));
+
+		// Start tracking the pool price
+		Self::update_price_oracle(asset_pair);
 
 		// Transfer the tokens to the new pool
-		Self::transfer_tokens_to_pool(&sender, &pool_id, asset_pair, asset_amounts)?;
+		Self::transfer_tokens_to_pool(&sender, &pool_id, asset_pair, asset_amounts, keep_alive)?;
 
 		// Send the lp tokens in exchange to the pool creator
-		Self::send_lp_tokens_to_pool_creator(&sender, &pool_id, asset_amounts)?;
+		let (lp_token_id, lp_tokens_amount) =
+			Self::send_lp_tokens_to_pool_creator(&sender, &pool_id, asset_amounts)?;
+		Self::record_liquidity_deposit(sender, asset_pair, asset_amounts, lp_tokens_amount);
 
-        Self::deposit_event(Event::NewPoolCreated { pool_id });
+		Self::deposit_event(Event::NewPoolCreated {
+			pool_id,
+			creator: sender.clone(),
+			asset_pair,
+			asset_amounts,
+			lp_token_id,
+			earned_lp_tokens: lp_tokens_amount,
+			reserves: Self::get_pool_liquidity(asset_pair)?,
+		});
 		Ok(())
 	}
 }
@@ -0,0 +1,217 @@
+use super::*;
+
+/// Number of consecutive blocks searched for a free execution slot
+const DCA_SCHEDULING_ATTEMPTS: u32 = 10;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Weight of executing a single period of a DCA schedule
+	pub fn dca_execution_weight() -> Weight {
+		10_000 + T::DbWeight::get().reads_writes(7, 8)
+	}
+
+	#[transactional]
+	pub fn create_dca_schedule(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount_per_period: BalanceOf<T>,
+		period: T::BlockNumber,
+		count: u32,
+		max_slippage: Permill,
+	) -> Result<ScheduleId, DispatchError> {
+		ensure!(
+			amount_per_period > 0u32.into() && period > 0u32.into() && count > 0,
+			Error::<T>::InvalidScheduleParameters
+		);
+		let total_amount =
+			amount_per_period.checked_mul(&count.into()).ok_or(Error::<T>::MathOverflow)?;
+
+		let schedule_id = NextScheduleId::<T>::get();
+		let now = frame_system::Pallet::<T>::block_number();
+		let next_execution = Self::enqueue_dca_execution(schedule_id, now + period)?;
+
+		Self::escrow_tokens(sender, asset_pair.0, total_amount)?;
+
+		DcaSchedules::<T>::insert(
+			schedule_id,
+			DcaSchedule {
+				owner: sender.clone(),
+				asset_in: asset_pair.0,
+				asset_out: asset_pair.1,
+				amount_per_period,
+				period,
+				remaining_periods: count,
+				max_slippage,
+				escrowed_amount: total_amount,
+				next_execution,
+			},
+		);
+		PoolDcaSchedules::<T>::insert(Self::get_pool_id(asset_pair), schedule_id, ());
+		NextScheduleId::<T>::put(schedule_id.saturating_add(1));
+
+		Self::deposit_event(Event::DcaScheduled {
+			schedule_id,
+			owner: sender.clone(),
+			asset_pair,
+			amount_per_period,
+			period,
+			count,
+		});
+
+		Ok(schedule_id)
+	}
+
+	#[transactional]
+	pub fn remove_dca_schedule(sender: &T::AccountId, schedule_id: ScheduleId) -> DispatchResult {
+		let schedule = Self::dca_schedules(schedule_id).ok_or(Error::<T>::ScheduleNotFound)?;
+		ensure!(schedule.owner == *sender, Error::<T>::NotScheduleOwner);
+
+		DcaExecutionQueue::<T>::mutate_exists(schedule.next_execution, |maybe_queue| {
+			if let Some(queue) = maybe_queue {
+				queue.retain(|id| *id != schedule_id);
+				if queue.is_empty() {
+					*maybe_queue = None;
+				}
+			}
+		});
+		DcaSchedules::<T>::remove(schedule_id);
+		PoolDcaSchedules::<T>::remove(
+			Self::get_pool_id((schedule.asset_in, schedule.asset_out)),
+			schedule_id,
+		);
+
+		Self::release_escrowed_tokens(sender, schedule.asset_in, schedule.escrowed_amount)?;
+
+		Self::deposit_event(Event::DcaCancelled {
+			schedule_id,
+			owner: sender.clone(),
+			refunded: schedule.escrowed_amount,
+		});
+
+		Ok(())
+	}
+
+	/// Queue the schedule at the first block from `earliest` with a free execution slot.
+	/// Returns the block it was queued at.
+	fn enqueue_dca_execution(
+		schedule_id: ScheduleId,
+		earliest: T::BlockNumber,
+	) -> Result<T::BlockNumber, DispatchError> {
+		let mut block = earliest;
+		for _ in 0..DCA_SCHEDULING_ATTEMPTS {
+			if DcaExecutionQueue::<T>::try_mutate(block, |queue| queue.try_push(schedule_id))
+				.is_ok()
+			{
+				return Ok(block)
+			}
+			block = block + 1u32.into();
+		}
+		Err(Error::<T>::TooManyScheduledSwaps.into())
+	}
+
+	/// Swap one period of the schedule from escrow, passing the return on to the owner.
+	/// Fails without side effects if the return exceeds the accepted slippage, and returns `None`
+	/// if the swap trips the pool's circuit breaker.
+	#[transactional]
+	fn try_execute_dca_swap(
+		schedule: &DcaSchedule<T::AccountId, AssetIdOf<T>, BalanceOf<T>, T::BlockNumber>,
+	) -> Result<Option<BalanceOf<T>>, DispatchError> {
+		let asset_pair = (schedule.asset_in, schedule.asset_out);
+		Self::settle_long_term_orders(asset_pair)?;
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		let min_return =
+			get_min_swap_return(schedule.amount_per_period, pool_liquidity, schedule.max_slippage)
+				.ok_or(Error::<T>::NotEnoughLiquidity)?;
+
+		let escrow = Self::account_id();
+		let swap_return = match Self::with_circuit_breaker(asset_pair, || {
+			let swap_return = Self::process_swap(
+				&escrow,
+				&escrow,
+				asset_pair,
+				schedule.amount_per_period,
+				false,
+			)?;
+			ensure!(swap_return >= min_return, Error::<T>::SlippageExceeded);
+			Ok(swap_return)
+		})? {
+			Some(swap_return) => swap_return,
+			None => return Ok(None),
+		};
+
+		Self::release_escrowed_tokens(&schedule.owner, schedule.asset_out, swap_return)?;
+
+		Ok(Some(swap_return))
+	}
+
+	/// Run one period of the schedule, then queue the next one or close the schedule
+	fn execute_dca_period(
+		schedule_id: ScheduleId,
+		mut schedule: DcaSchedule<T::AccountId, AssetIdOf<T>, BalanceOf<T>, T::BlockNumber>,
+		now: T::BlockNumber,
+	) {
+		match Self::try_execute_dca_swap(&schedule) {
+			Ok(Some(amount_out)) => {
+				schedule.escrowed_amount =
+					schedule.escrowed_amount.saturating_sub(schedule.amount_per_period);
+				Self::deposit_event(Event::DcaExecuted {
+					schedule_id,
+					owner: schedule.owner.clone(),
+					amount_in: schedule.amount_per_period,
+					amount_out,
+				});
+			},
+			Ok(None) | Err(_) => Self::deposit_event(Event::DcaExecutionSkipped {
+				schedule_id,
+				owner: schedule.owner.clone(),
+			}),
+		}
+		schedule.remaining_periods = schedule.remaining_periods.saturating_sub(1);
+
+		if schedule.remaining_periods > 0 {
+			if let Ok(next_execution) =
+				Self::enqueue_dca_execution(schedule_id, now + schedule.period)
+			{
+				schedule.next_execution = next_execution;
+				DcaSchedules::<T>::insert(schedule_id, schedule);
+				return
+			}
+		}
+
+		// Completed, or no slot left to continue in: return what is still held
+		DcaSchedules::<T>::remove(schedule_id);
+		PoolDcaSchedules::<T>::remove(
+			Self::get_pool_id((schedule.asset_in, schedule.asset_out)),
+			schedule_id,
+		);
+		if schedule.escrowed_amount > 0u32.into() {
+			let _ = Self::release_escrowed_tokens(
+				&schedule.owner,
+				schedule.asset_in,
+				schedule.escrowed_amount,
+			);
+		}
+		Self::deposit_event(Event::DcaCompleted {
+			schedule_id,
+			owner: schedule.owner,
+			refunded: schedule.escrowed_amount,
+		});
+	}
+
+	/// Execute every DCA schedule due at block `now`. Returns the weight consumed.
+	pub fn execute_dca_schedules(now: T::BlockNumber) -> Weight {
+		let due = DcaExecutionQueue::<T>::take(now);
+		let mut consumed_weight = T::DbWeight::get().reads_writes(1, 1);
+
+		for schedule_id in due {
+			consumed_weight = consumed_weight.saturating_add(Self::dca_execution_weight());
+			if let Some(schedule) = Self::dca_schedules(schedule_id) {
+				Self::execute_dca_period(schedule_id, schedule, now);
+			}
+		}
+
+		consumed_weight
+	}
+}
@@ -0,0 +1,245 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// LP tokens of the pool held by the pool account itself. Nobody can redeem them, they are
+	/// locked in the pool for good.
+	pub fn locked_lp_tokens(pool_id: &T::AccountId) -> BalanceOf<T> {
+		T::Assets::balance(Self::get_lp_token_id(pool_id), pool_id)
+	}
+
+	pub fn check_pool_can_be_destroyed(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		ensure!(asset_pair.0 != asset_pair.1, Error::<T>::ProvidedInvalidAssetIds);
+		let pool_id = Self::get_pool_id(asset_pair);
+		let lp_token_id = Self::get_lp_token_id(&pool_id);
+
+		// The pool exists for as long as its LP token does
+		ensure!(T::Assets::get_destroy_witness(&lp_token_id).is_some(), Error::<T>::PoolNotFound);
+		// Only locked LP tokens may be left, any other LP tokens still have a claim on the pool
+		ensure!(
+			T::Assets::total_issuance(lp_token_id) == Self::locked_lp_tokens(&pool_id),
+			Error::<T>::PoolNotEmpty
+		);
+
+		// Swaps collected for the pool's batch auction are cleared at the end of the block
+		ensure!(Self::pending_batch_swaps(&pool_id).is_empty(), Error::<T>::PoolNotEmpty);
+
+		// Open long-term orders keep selling into the pool until they end or are cancelled
+		let long_term_orders_open = Self::long_term_order_pools(&pool_id)
+			.map_or(false, |pool| {
+				pool.sell_rates.0 > 0u32.into() || pool.sell_rates.1 > 0u32.into()
+			});
+		ensure!(!long_term_orders_open, Error::<T>::PoolNotEmpty);
+
+		Ok(())
+	}
+
+	/// Weight of releasing a single order, DCA schedule, farming stake or liquidity position of
+	/// a pool being torn down
+	pub fn pool_teardown_item_weight() -> Weight {
+		10_000 + T::DbWeight::get().reads_writes(5, 6)
+	}
+
+	/// Weight of destroying a pool once nothing depends on it anymore
+	pub fn pool_teardown_weight() -> Weight {
+		20_000 + T::DbWeight::get().reads_writes(12, 20)
+	}
+
+	/// Queue a pool without liquidity for teardown
+	pub fn schedule_pool_teardown(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		Self::check_pool_can_be_destroyed(asset_pair)?;
+		let pool_id = Self::get_pool_id(asset_pair);
+		if PoolTeardowns::<T>::contains_key(&pool_id) {
+			return Ok(())
+		}
+
+		let asset_pair = Self::sort_asset_pair(asset_pair);
+		PoolTeardowns::<T>::insert(&pool_id, asset_pair);
+		Self::deposit_event(Event::PoolTeardownScheduled { pool_id, asset_pair });
+
+		Ok(())
+	}
+
+	/// Release the next limit order, conditional order, DCA schedule, farming stake or liquidity
+	/// position of the pool, refunding what is escrowed for it and paying out farming rewards.
+	/// Returns whether there was anything left to release.
+	#[transactional]
+	fn release_next_pool_item(
+		pool_id: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Result<bool, DispatchError> {
+		for asset_id in [asset_pair.0, asset_pair.1] {
+			if let Some((_, order_id)) = Self::pool_limit_orders(pool_id, asset_id).first() {
+				match Self::limit_orders(order_id) {
+					Some(order) => Self::remove_limit_order(&order.owner, *order_id)?,
+					None =>
+						PoolLimitOrders::<T>::mutate_exists(pool_id, asset_id, |maybe_orders| {
+							if let Some(orders) = maybe_orders {
+								orders.retain(|(_, id)| id != order_id);
+								if orders.is_empty() {
+									*maybe_orders = None;
+								}
+							}
+						}),
+				}
+				return Ok(true)
+			}
+		}
+
+		if let Some(order_id) = PoolConditionalOrders::<T>::iter_key_prefix(pool_id).next() {
+			match Self::conditional_orders(order_id) {
+				Some(order) => Self::remove_conditional_order(&order.owner, order_id)?,
+				None => PoolConditionalOrders::<T>::remove(pool_id, order_id),
+			}
+			return Ok(true)
+		}
+
+		if let Some(schedule_id) = PoolDcaSchedules::<T>::iter_key_prefix(pool_id).next() {
+			match Self::dca_schedules(schedule_id) {
+				Some(schedule) => Self::remove_dca_schedule(&schedule.owner, schedule_id)?,
+				None => PoolDcaSchedules::<T>::remove(pool_id, schedule_id),
+			}
+			return Ok(true)
+		}
+
+		// Without LP tokens outside the pool nothing is staked, only unclaimed rewards are left
+		if let Some(staker) = FarmingStakes::<T>::iter_key_prefix(pool_id).next() {
+			let _ = Self::process_farming_rewards_claim(&staker, asset_pair);
+			FarmingStakes::<T>::remove(pool_id, &staker);
+			return Ok(true)
+		}
+
+		if let Some(provider) = LiquidityPositions::<T>::iter_key_prefix(pool_id).next() {
+			LiquidityPositions::<T>::remove(pool_id, &provider);
+			return Ok(true)
+		}
+
+		Ok(false)
+	}
+
+	/// Tear down the queued pools for as long as `remaining_weight` allows, one item at a time.
+	/// A pool which can no longer be destroyed, e.g. because liquidity was provided to it in the
+	/// meantime, or whose teardown fails, is taken off the queue. Returns the weight consumed.
+	pub fn process_pool_teardowns(remaining_weight: Weight) -> Weight {
+		let check_weight = T::DbWeight::get().reads(6);
+		let item_weight = Self::pool_teardown_item_weight();
+		let teardown_weight = Self::pool_teardown_weight();
+		let mut consumed_weight = T::DbWeight::get().reads(1);
+		if consumed_weight > remaining_weight {
+			return 0
+		}
+
+		// Every step must leave room for destroying the pool, which could be the next one
+		while consumed_weight.saturating_add(check_weight + teardown_weight) <= remaining_weight {
+			let (pool_id, asset_pair) = match PoolTeardowns::<T>::iter().next() {
+				Some(teardown) => teardown,
+				None => break,
+			};
+			consumed_weight = consumed_weight.saturating_add(check_weight);
+			if Self::check_pool_can_be_destroyed(asset_pair).is_err() {
+				PoolTeardowns::<T>::remove(&pool_id);
+				continue
+			}
+
+			loop {
+				if consumed_weight.saturating_add(teardown_weight) > remaining_weight {
+					return consumed_weight
+				}
+				match Self::release_next_pool_item(&pool_id, asset_pair) {
+					Ok(true) => consumed_weight = consumed_weight.saturating_add(item_weight),
+					Ok(false) => {
+						consumed_weight = consumed_weight.saturating_add(teardown_weight);
+						let _ = Self::destroy_empty_pool(&pool_id, asset_pair);
+						PoolTeardowns::<T>::remove(&pool_id);
+						break
+					},
+					Err(_) => {
+						consumed_weight = consumed_weight.saturating_add(item_weight);
+						PoolTeardowns::<T>::remove(&pool_id);
+						break
+					},
+				}
+			}
+		}
+
+		consumed_weight
+	}
+
+	/// Empty the pool account and reap it. Whatever is left in it has no claim on it anymore,
+	/// it goes to the pool's creator along with the existential deposit, or is burned if the
+	/// creator is unknown.
+	fn reap_pool_account(
+		pool_id: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> DispatchResult {
+		let creator = Self::pool_creation_deposits(pool_id).map(|(creator, _)| creator);
+
+		for asset_id in [asset_pair.0, asset_pair.1] {
+			let remainder = T::Assets::balance(asset_id, pool_id);
+			if remainder == 0u32.into() {
+				continue
+			}
+			match creator {
+				Some(ref creator) => {
+					T::Assets::transfer(asset_id, pool_id, creator, remainder, false)?;
+				},
+				None => {
+					T::Assets::burn_from(asset_id, pool_id, remainder)?;
+				},
+			}
+		}
+
+		let native_remainder = T::Balances::free_balance(pool_id);
+		match creator {
+			Some(creator) => T::Balances::transfer(
+				pool_id,
+				&creator,
+				native_remainder,
+				ExistenceRequirement::AllowDeath,
+			)?,
+			None => {
+				let _ = T::Balances::slash(pool_id, native_remainder);
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Destroy a pool without liquidity beyond locked LP tokens, once its orders, schedules,
+	/// farming stakes and liquidity positions are released: destroy its LP token, reap its
+	/// account, return its creation deposit and remove its state, so that the pair can be
+	/// created again
+	#[transactional]
+	fn destroy_empty_pool(
+		pool_id: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> DispatchResult {
+		let lp_token_id = Self::get_lp_token_id(pool_id);
+
+		// Destroying the LP token burns the locked LP tokens along with it
+		let witness =
+			T::Assets::get_destroy_witness(&lp_token_id).ok_or(Error::<T>::PoolNotFound)?;
+		T::Assets::destroy(lp_token_id, witness, None)?;
+
+		Self::reap_pool_account(pool_id, asset_pair)?;
+		Self::release_pool_creation_deposit(pool_id);
+
+		Pools::<T>::remove(pool_id);
+		PriceObservations::<T>::remove(pool_id);
+		BatchAuctionPools::<T>::remove(pool_id);
+		LongTermOrderPools::<T>::remove(pool_id);
+		LongTermOrderExpiries::<T>::remove(pool_id);
+		PoolStatuses::<T>::remove(pool_id);
+		PoolPriceLimits::<T>::remove(pool_id);
+		BlockPriceStates::<T>::remove(pool_id);
+		PoolStatistics::<T>::remove(pool_id);
+		DailyPoolStatistics::<T>::remove(pool_id);
+		FarmingPools::<T>::remove(pool_id);
+
+		Self::deposit_event(Event::PoolDestroyed { pool_id: pool_id.clone(), asset_pair });
+
+		Ok(())
+	}
+}
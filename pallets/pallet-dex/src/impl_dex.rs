@@ -21,17 +21,75 @@ where
 		asset_balance >= amount
 	}
 
+	/// Check `who` is able to transfer `amount` of `asset` away, keeping their account alive
+	/// if `keep_alive` is set
+	pub fn check_can_withdraw(
+		who: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+		keep_alive: bool,
+	) -> DispatchResult {
+		if amount == 0u32.into() {
+			return Ok(())
+		}
+
+		match T::Assets::can_withdraw(asset, who, amount) {
+			WithdrawConsequence::Success => {},
+			// The dust left below the minimum balance goes along with the withdrawal
+			WithdrawConsequence::ReducedToZero(_) if !keep_alive => {},
+			WithdrawConsequence::ReducedToZero(_) | WithdrawConsequence::WouldDie =>
+				return Err(Error::<T>::WouldKillAccount.into()),
+			WithdrawConsequence::Frozen => return Err(Error::<T>::BalanceFrozen.into()),
+			_ => return Err(Error::<T>::NotEnoughTokensForTransaction.into()),
+		}
+
+		// Whatever else holds the balance in place, e.g. a freeze, keeps it from being withdrawn
+		ensure!(
+			amount <= T::Assets::reducible_balance(asset, who, keep_alive),
+			Error::<T>::BalanceFrozen
+		);
+
+		Ok(())
+	}
+
 	pub fn has_enough_of_both_tokens(
 		sender: &T::AccountId,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
 		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+		keep_alive: bool,
 	) -> DispatchResult {
-		if Self::has_enough_tokens(asset_pair.0, asset_amounts.0, sender) &&
-			Self::has_enough_tokens(asset_pair.1, asset_amounts.1, sender)
-		{
-			return Ok(())
+		Self::check_can_withdraw(sender, asset_pair.0, asset_amounts.0, keep_alive)?;
+		Self::check_can_withdraw(sender, asset_pair.1, asset_amounts.1, keep_alive)
+	}
+
+	/// Move funds from `who` into the pallet account, which holds them on their behalf
+	pub fn escrow_tokens(
+		who: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let escrow = Self::account_id();
+		Self::ensure_pallet_account_alive(&escrow);
+		T::Assets::transfer(asset, who, &escrow, amount, false)?;
+		Ok(())
+	}
+
+	/// Keep an account of the pallet alive before it receives assets. It holds no native
+	/// balance, a provider reference keeps it alive instead.
+	pub fn ensure_pallet_account_alive(account: &T::AccountId) {
+		if !frame_system::Pallet::<T>::account_exists(account) {
+			frame_system::Pallet::<T>::inc_providers(account);
 		}
-		Err(Error::<T>::NotEnoughTokensForTransaction.into())
+	}
+
+	/// Send funds held by the pallet account to `who`
+	pub fn release_escrowed_tokens(
+		who: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		T::Assets::transfer(asset, &Self::account_id(), who, amount, false)?;
+		Ok(())
 	}
 
 	pub fn get_pool_id(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> T::AccountId {
@@ -41,10 +99,18 @@ where
 		Self::sub_account_id(&hashed_assets)
 	}
 
-	pub fn initialize_pool(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> T::AccountId {
+	/// Fund the pool account up to the existential deposit, paid for by the pool's creator
+	pub fn initialize_pool(
+		creator: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Result<T::AccountId, DispatchError> {
 		let pool_id = Self::get_pool_id(asset_pair);
-		T::Balances::make_free_balance_be(&pool_id, T::Balances::minimum_balance());
-		pool_id
+		let shortfall =
+			T::Balances::minimum_balance().saturating_sub(T::Balances::free_balance(&pool_id));
+		if shortfall > 0u32.into() {
+			T::Balances::transfer(creator, &pool_id, shortfall, ExistenceRequirement::KeepAlive)?;
+		}
+		Ok(pool_id)
 	}
 
 	pub fn transfer_tokens_to_pool(
@@ -52,9 +118,10 @@ where
 		pool_id: &T::AccountId,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
 		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+		keep_alive: bool,
 	) -> Result<(), DispatchError> {
-		T::Assets::transfer(asset_pair.0, &sender, &pool_id, asset_amounts.0, false)?;
-		T::Assets::transfer(asset_pair.1, &sender, &pool_id, asset_amounts.1, false)?;
+		T::Assets::transfer(asset_pair.0, &sender, &pool_id, asset_amounts.0, keep_alive)?;
+		T::Assets::transfer(asset_pair.1, &sender, &pool_id, asset_amounts.1, keep_alive)?;
 		Ok(())
 	}
 
@@ -70,12 +137,13 @@ where
 		sender: &T::AccountId,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
 		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+		keep_alive: bool,
 	) -> Result<(), DispatchError> {
 		// Ensure that the assets are valid.
 		ensure!(asset_pair.0 != asset_pair.1, Error::<T>::ProvidedInvalidAssetIds);
 
 		// check if sender has enough tokens to stake
-		Self::has_enough_of_both_tokens(&sender, asset_pair, asset_amounts)?;
+		Self::has_enough_of_both_tokens(&sender, asset_pair, asset_amounts, keep_alive)?;
 
 		Ok(())
 	}
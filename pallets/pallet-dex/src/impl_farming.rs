@@ -0,0 +1,282 @@
+use super::*;
+
+const FARMING_REWARDS_ACCOUNT: &[u8; 16] = b"dex/farm/rewards";
+
+impl<Balance: AtLeast32Bit + Copy, BlockNumber: AtLeast32Bit + Copy>
+	FarmingPool<Balance, BlockNumber>
+{
+	/// Accumulate the rewards emitted to the stakers since the last reward block up to `now`
+	pub fn accumulate_rewards(&mut self, now: BlockNumber) {
+		if now <= self.last_reward_block {
+			return
+		}
+		if self.total_staked > 0u32.into() {
+			let blocks: u128 = (now - self.last_reward_block).saturated_into();
+			let rewards = self.reward_per_block.saturated_into::<u128>().saturating_mul(blocks);
+			self.reward_per_share = self.reward_per_share.saturating_add(
+				FixedU128::saturating_from_rational(
+					rewards,
+					self.total_staked.saturated_into::<u128>(),
+				),
+			);
+		}
+		self.last_reward_block = now;
+	}
+}
+
+impl<Balance: AtLeast32Bit + Copy> FarmingStake<Balance> {
+	/// Credit the rewards earned since they were last accrued, up to the pool's
+	/// `reward_per_share`
+	pub fn accrue_rewards(&mut self, reward_per_share: FixedU128) {
+		let earned = reward_per_share
+			.saturating_sub(self.reward_per_share_paid)
+			.saturating_mul_int(self.staked.saturated_into::<u128>());
+		self.pending_rewards = self.pending_rewards.saturating_add(earned.saturated_into());
+		self.reward_per_share_paid = reward_per_share;
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Account liquidity mining rewards are paid from
+	pub fn farming_rewards_account_id() -> T::AccountId {
+		Self::sub_account_id(FARMING_REWARDS_ACCOUNT)
+	}
+
+	/// LP tokens of the pool `who` holds, including those staked
+	pub fn lp_tokens_owned(pool_id: &T::AccountId, who: &T::AccountId) -> BalanceOf<T> {
+		T::Assets::balance(Self::get_lp_token_id(pool_id), who)
+			.saturating_add(Self::farming_stake(pool_id, who).staked)
+	}
+
+	pub fn check_farming_pool_exists(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		ensure!(asset_pair.0 != asset_pair.1, Error::<T>::ProvidedInvalidAssetIds);
+		ensure!(Pools::<T>::contains_key(Self::get_pool_id(asset_pair)), Error::<T>::PoolNotFound);
+		Ok(())
+	}
+
+	pub fn check_lp_stake_is_valid(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		lp_token_amount: BalanceOf<T>,
+	) -> DispatchResult {
+		Self::check_farming_pool_exists(asset_pair)?;
+		let lp_token_id = Self::get_lp_token_id(&Self::get_pool_id(asset_pair));
+
+		Self::check_can_withdraw_lp_tokens(sender, lp_token_id, lp_token_amount)?;
+
+		Ok(())
+	}
+
+	pub fn check_lp_unstake_is_valid(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		lp_token_amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let stake = Self::farming_stake(Self::get_pool_id(asset_pair), sender);
+		ensure!(stake.staked >= lp_token_amount, Error::<T>::NotEnoughStakedLpTokens);
+		Ok(())
+	}
+
+	pub fn check_can_compound_farming_rewards(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> DispatchResult {
+		Self::check_farming_pool_exists(asset_pair)?;
+		let reward_asset = T::FarmingRewardAsset::get();
+		ensure!(
+			asset_pair.0 == reward_asset || asset_pair.1 == reward_asset,
+			Error::<T>::RewardAssetNotInPool
+		);
+		Ok(())
+	}
+
+	/// The pool's liquidity mining state, with the rewards accumulated up to the current block
+	fn accumulated_farming_pool(
+		pool_id: &T::AccountId,
+	) -> FarmingPool<BalanceOf<T>, T::BlockNumber> {
+		let mut pool = Self::farming_pool(pool_id);
+		pool.accumulate_rewards(frame_system::Pallet::<T>::block_number());
+		pool
+	}
+
+	/// `who`'s stake in the pool, with the rewards accrued up to the pool's reward per share
+	fn accrued_farming_stake(
+		pool: &FarmingPool<BalanceOf<T>, T::BlockNumber>,
+		pool_id: &T::AccountId,
+		who: &T::AccountId,
+	) -> FarmingStake<BalanceOf<T>> {
+		let mut stake = Self::farming_stake(pool_id, who);
+		stake.accrue_rewards(pool.reward_per_share);
+		stake
+	}
+
+	fn store_farming_stake(
+		pool_id: &T::AccountId,
+		who: &T::AccountId,
+		stake: FarmingStake<BalanceOf<T>>,
+	) {
+		if stake.staked == 0u32.into() && stake.pending_rewards == 0u32.into() {
+			FarmingStakes::<T>::remove(pool_id, who);
+		} else {
+			FarmingStakes::<T>::insert(pool_id, who, stake);
+		}
+	}
+
+	/// Set the pool's reward rate, from the current block on
+	pub fn set_farming_reward_rate_for(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		reward_per_block: BalanceOf<T>,
+	) {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let mut pool = Self::accumulated_farming_pool(&pool_id);
+		pool.reward_per_block = reward_per_block;
+		FarmingPools::<T>::insert(&pool_id, pool);
+
+		Self::deposit_event(Event::FarmingRewardRateSet { pool_id, reward_per_block });
+	}
+
+	#[transactional]
+	pub fn process_farming_rewards_funding(
+		sender: &T::AccountId,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let rewards_account = Self::farming_rewards_account_id();
+		Self::ensure_pallet_account_alive(&rewards_account);
+		T::Assets::transfer(T::FarmingRewardAsset::get(), sender, &rewards_account, amount, false)?;
+
+		Self::deposit_event(Event::FarmingRewardsFunded { who: sender.clone(), amount });
+
+		Ok(())
+	}
+
+	/// Move `sender`'s LP tokens into escrow and add them to their stake in the pool
+	#[transactional]
+	pub fn process_lp_token_stake(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		lp_token_amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let mut pool = Self::accumulated_farming_pool(&pool_id);
+		let mut stake = Self::accrued_farming_stake(&pool, &pool_id, sender);
+
+		Self::escrow_tokens(sender, Self::get_lp_token_id(&pool_id), lp_token_amount)?;
+		stake.staked = stake.staked.saturating_add(lp_token_amount);
+		pool.total_staked = pool.total_staked.saturating_add(lp_token_amount);
+
+		FarmingPools::<T>::insert(&pool_id, pool);
+		Self::store_farming_stake(&pool_id, sender, stake);
+
+		Self::deposit_event(Event::LpTokensStaked {
+			pool_id,
+			who: sender.clone(),
+			amount: lp_token_amount,
+		});
+
+		Ok(())
+	}
+
+	/// Return staked LP tokens to `sender`, keeping the rewards they earned claimable
+	#[transactional]
+	pub fn process_lp_token_unstake(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		lp_token_amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let mut pool = Self::accumulated_farming_pool(&pool_id);
+		let mut stake = Self::accrued_farming_stake(&pool, &pool_id, sender);
+
+		Self::release_escrowed_tokens(sender, Self::get_lp_token_id(&pool_id), lp_token_amount)?;
+		stake.staked = stake.staked.saturating_sub(lp_token_amount);
+		pool.total_staked = pool.total_staked.saturating_sub(lp_token_amount);
+
+		FarmingPools::<T>::insert(&pool_id, pool);
+		Self::store_farming_stake(&pool_id, sender, stake);
+
+		Self::deposit_event(Event::LpTokensUnstaked {
+			pool_id,
+			who: sender.clone(),
+			amount: lp_token_amount,
+		});
+
+		Ok(())
+	}
+
+	/// Pay out the rewards `sender` earned in the pool, returning the amount paid
+	#[transactional]
+	pub fn process_farming_rewards_claim(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Result<BalanceOf<T>, DispatchError> {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let pool = Self::accumulated_farming_pool(&pool_id);
+		let mut stake = Self::accrued_farming_stake(&pool, &pool_id, sender);
+
+		let rewards = stake.pending_rewards;
+		ensure!(rewards > 0u32.into(), Error::<T>::NoFarmingRewards);
+		T::Assets::transfer(
+			T::FarmingRewardAsset::get(),
+			&Self::farming_rewards_account_id(),
+			sender,
+			rewards,
+			false,
+		)
+		.map_err(|_| Error::<T>::NotEnoughFarmingRewardFunds)?;
+		stake.pending_rewards = 0u32.into();
+
+		FarmingPools::<T>::insert(&pool_id, pool);
+		Self::store_farming_stake(&pool_id, sender, stake);
+
+		Self::deposit_event(Event::FarmingRewardsClaimed {
+			pool_id,
+			who: sender.clone(),
+			amount: rewards,
+		});
+
+		Ok(rewards)
+	}
+
+	/// Claim `sender`'s rewards in the pool, swap half of them for at least `min_amount_out` of
+	/// the pool's other asset, deposit the other half with it and stake the LP tokens received
+	#[transactional]
+	pub fn process_farming_rewards_compound(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		min_amount_out: BalanceOf<T>,
+		min_lp_tokens: BalanceOf<T>,
+	) -> DispatchResult {
+		let rewards = Self::process_farming_rewards_claim(sender, asset_pair)?;
+		let reward_asset = T::FarmingRewardAsset::get();
+		let other_asset = if asset_pair.0 == reward_asset { asset_pair.1 } else { asset_pair.0 };
+
+		let swapped_rewards = rewards / 2u32.into();
+		let other_asset_amount = <Self as SwapHandler<_, _, _>>::swap(
+			sender,
+			reward_asset,
+			other_asset,
+			swapped_rewards,
+			min_amount_out,
+		)?;
+		let (_, lp_tokens) = <Self as LiquidityHandler<_, _, _>>::add_liquidity(
+			sender,
+			reward_asset,
+			other_asset,
+			rewards - swapped_rewards,
+			other_asset_amount,
+			min_lp_tokens,
+		)?;
+		Self::process_lp_token_stake(sender, asset_pair, lp_tokens)?;
+
+		Self::deposit_event(Event::FarmingRewardsCompounded {
+			pool_id: Self::get_pool_id(asset_pair),
+			who: sender.clone(),
+			rewards,
+			lp_tokens,
+		});
+
+		Ok(())
+	}
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	pub fn check_flash_loan_is_valid(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount: BalanceOf<T>,
+		call: &<T as Config>::Call,
+	) -> Result<(), DispatchError> {
+		// Ensure that the assets are valid.
+		ensure!(asset_pair.0 != asset_pair.1, Error::<T>::ProvidedInvalidAssetIds);
+
+		// Ensure the pool is trading
+		Self::check_swaps_allowed(asset_pair)?;
+
+		// Ensure the borrower only does what they are allowed to
+		ensure!(T::FlashLoanCallFilter::contains(call), Error::<T>::CallNotAllowed);
+
+		// Ensure the pool can lend the amount
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		ensure!(amount <= pool_liquidity.0, Error::<T>::NotEnoughLiquidity);
+
+		Ok(())
+	}
+
+	#[transactional]
+	pub fn process_flash_loan(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount: BalanceOf<T>,
+		call: <T as Config>::Call,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		// Get pool data, after the virtual trades of long-term orders
+		Self::settle_long_term_orders(asset_pair)?;
+		let pool_id = Self::get_pool_id(asset_pair);
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		let fee = T::FlashLoanFee::get().mul_ceil(amount);
+
+		// Record the price before the reserves change
+		Self::update_price_oracle(asset_pair);
+
+		// Lend the tokens
+		T::Assets::transfer(asset_pair.0, &pool_id, sender, amount, false)?;
+
+		// Let the borrower use them
+		call.dispatch(frame_system::RawOrigin::Signed(sender.clone()).into())
+			.map_err(|e| e.error)?;
+
+		// Take back the loan plus the fee
+		let repayment = amount.checked_add(&fee).ok_or(Error::<T>::MathOverflow)?;
+		T::Assets::transfer(asset_pair.0, sender, &pool_id, repayment, false)
+			.map_err(|_| Error::<T>::FlashLoanNotRepaid)?;
+
+		// The pool must be at least as valuable as before, plus the fee
+		let required_liquidity = (
+			pool_liquidity.0.checked_add(&fee).ok_or(Error::<T>::MathOverflow)?,
+			pool_liquidity.1,
+		);
+		let reserves = Self::get_pool_liquidity(asset_pair)?;
+		ensure!(
+			is_constant_product_maintained(required_liquidity, reserves),
+			Error::<T>::FlashLoanNotRepaid
+		);
+
+		Self::deposit_event(Event::FlashLoanRepaid {
+			pool_id,
+			borrower: sender.clone(),
+			asset_pair,
+			amount,
+			fee,
+			reserves,
+		});
+
+		Ok(fee)
+	}
+}
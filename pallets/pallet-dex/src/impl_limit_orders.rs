@@ -0,0 +1,209 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Weight of attempting to fill a single limit order
+	pub fn limit_order_fill_weight() -> Weight {
+		10_000 + T::DbWeight::get().reads_writes(6, 6)
+	}
+
+	#[transactional]
+	pub fn create_limit_order(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount_in: BalanceOf<T>,
+		limit_price: FixedU128,
+	) -> Result<OrderId, DispatchError> {
+		ensure!(
+			amount_in > 0u32.into() && limit_price.into_inner() > 0,
+			Error::<T>::InvalidOrderParameters
+		);
+
+		let pool_id = Self::get_pool_id(asset_pair);
+		let order_id = NextOrderId::<T>::get();
+
+		// Keep the pool's orders sorted by ascending limit price
+		PoolLimitOrders::<T>::try_mutate(&pool_id, asset_pair.0, |orders| {
+			let index = orders.iter().position(|(price, _)| *price > limit_price);
+			orders
+				.try_insert(index.unwrap_or(orders.len()), (limit_price, order_id))
+				.map_err(|_| Error::<T>::TooManyOrders)
+		})?;
+
+		Self::escrow_tokens(sender, asset_pair.0, amount_in)?;
+
+		LimitOrders::<T>::insert(
+			order_id,
+			LimitOrder {
+				owner: sender.clone(),
+				asset_in: asset_pair.0,
+				asset_out: asset_pair.1,
+				amount_in,
+				limit_price,
+			},
+		);
+		NextOrderId::<T>::put(order_id.saturating_add(1));
+
+		Self::deposit_event(Event::LimitOrderPlaced {
+			order_id,
+			owner: sender.clone(),
+			asset_pair,
+			amount_in,
+			limit_price,
+		});
+
+		Ok(order_id)
+	}
+
+	#[transactional]
+	pub fn remove_limit_order(sender: &T::AccountId, order_id: OrderId) -> DispatchResult {
+		let order = Self::limit_orders(order_id).ok_or(Error::<T>::OrderNotFound)?;
+		ensure!(order.owner == *sender, Error::<T>::NotOrderOwner);
+
+		let pool_id = Self::get_pool_id((order.asset_in, order.asset_out));
+		PoolLimitOrders::<T>::mutate_exists(&pool_id, order.asset_in, |maybe_orders| {
+			if let Some(orders) = maybe_orders {
+				orders.retain(|(_, id)| *id != order_id);
+				if orders.is_empty() {
+					*maybe_orders = None;
+				}
+			}
+		});
+		LimitOrders::<T>::remove(order_id);
+
+		Self::release_escrowed_tokens(sender, order.asset_in, order.amount_in)?;
+
+		Self::deposit_event(Event::LimitOrderCancelled { order_id, owner: sender.clone() });
+
+		Ok(())
+	}
+
+	/// Fill the order if the pool returns at least its limit price. The order stays open if the
+	/// swap trips the pool's circuit breaker. Returns whether the order was filled.
+	#[transactional]
+	pub fn try_fill_limit_order(
+		order_id: OrderId,
+		order: &LimitOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+	) -> Result<bool, DispatchError> {
+		let asset_pair = (order.asset_in, order.asset_out);
+		Self::settle_long_term_orders(asset_pair)?;
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		let swap_return = get_swap_return::<BalanceOf<T>, T>(order.amount_in, pool_liquidity)?;
+
+		let min_return = order
+			.limit_price
+			.checked_mul_int(order.amount_in.saturated_into::<u128>())
+			.ok_or(Error::<T>::MathOverflow)?;
+		if swap_return.saturated_into::<u128>() < min_return {
+			return Ok(false)
+		}
+
+		// Swap from escrow and pass the return on to the owner
+		let escrow = Self::account_id();
+		let swap_return = match Self::with_circuit_breaker(asset_pair, || {
+			Self::process_swap(&escrow, &escrow, asset_pair, order.amount_in, false)
+		})? {
+			Some(swap_return) => swap_return,
+			None => return Ok(false),
+		};
+		Self::release_escrowed_tokens(&order.owner, order.asset_out, swap_return)?;
+
+		LimitOrders::<T>::remove(order_id);
+
+		Self::deposit_event(Event::LimitOrderFilled {
+			order_id,
+			owner: order.owner.clone(),
+			amount_in: order.amount_in,
+			amount_out: swap_return,
+		});
+
+		Ok(true)
+	}
+
+	/// Fill every open limit order whose limit price the pools now offer, continuing from the
+	/// order book the previous block stopped at, for as long as `remaining_weight` allows.
+	/// Orders on pools in batch auction mode are left to rest. Returns the weight consumed.
+	pub fn match_limit_orders(remaining_weight: Weight) -> Weight {
+		let fill_weight = Self::limit_order_fill_weight();
+		let mut consumed_weight = T::DbWeight::get().reads_writes(1, 1);
+		if consumed_weight > remaining_weight {
+			return 0
+		}
+
+		// Every order book visited costs at least one fill attempt
+		let max_order_books =
+			(remaining_weight.saturating_sub(consumed_weight) / fill_weight) as usize;
+		let order_books = match LimitOrderBookCursor::<T>::get() {
+			Some((pool_id, asset_in)) => PoolLimitOrders::<T>::iter_keys_from(
+				PoolLimitOrders::<T>::hashed_key_for(&pool_id, asset_in),
+			),
+			None => PoolLimitOrders::<T>::iter_keys(),
+		};
+		let order_books: Vec<(T::AccountId, AssetIdOf<T>)> =
+			order_books.take(max_order_books).collect();
+
+		let mut last_visited = None;
+		let mut all_visited = order_books.len() < max_order_books;
+		for (pool_id, asset_in) in order_books {
+			if consumed_weight.saturating_add(fill_weight) > remaining_weight {
+				all_visited = false;
+				break
+			}
+			last_visited = Some((pool_id.clone(), asset_in));
+
+			// Batch auction pools only trade in their batches
+			if Self::is_batch_auction_pool(&pool_id) {
+				consumed_weight = consumed_weight.saturating_add(T::DbWeight::get().reads(1));
+				continue
+			}
+
+			let mut orders = Self::pool_limit_orders(&pool_id, asset_in);
+			let mut filled = vec![];
+
+			for (limit_price, order_id) in orders.iter() {
+				if consumed_weight.saturating_add(fill_weight) > remaining_weight {
+					break
+				}
+				consumed_weight = consumed_weight.saturating_add(fill_weight);
+
+				let order = match Self::limit_orders(order_id) {
+					Some(order) => order,
+					None => {
+						filled.push(*order_id);
+						continue
+					},
+				};
+
+				// Orders are sorted by limit price, once the spot price is below the limit
+				// none of the remaining orders can be filled
+				let spot_price = Self::get_spot_price((order.asset_in, order.asset_out));
+				if spot_price.map_or(true, |spot_price| spot_price < *limit_price) {
+					break
+				}
+
+				if let Ok(true) = Self::try_fill_limit_order(*order_id, &order) {
+					filled.push(*order_id);
+				}
+			}
+
+			if !filled.is_empty() {
+				orders.retain(|(_, id)| !filled.contains(id));
+				if orders.is_empty() {
+					PoolLimitOrders::<T>::remove(&pool_id, asset_in);
+				} else {
+					PoolLimitOrders::<T>::insert(&pool_id, asset_in, orders);
+				}
+			}
+		}
+
+		if all_visited {
+			LimitOrderBookCursor::<T>::kill();
+		} else if let Some(order_book) = last_visited {
+			LimitOrderBookCursor::<T>::put(order_book);
+		}
+
+		consumed_weight
+	}
+}
@@ -0,0 +1,176 @@
+use super::*;
+
+impl<Balance: AtLeast32Bit + Copy, BlockNumber> LiquidityPosition<Balance, BlockNumber> {
+	/// Keep only `lp_tokens` of the tracked LP tokens, along with their share of the deposits
+	pub fn reduced_to(self, lp_tokens: Balance) -> Self {
+		if lp_tokens >= self.lp_tokens {
+			return self
+		}
+		let share = FixedU128::saturating_from_rational(
+			lp_tokens.saturated_into::<u128>(),
+			self.lp_tokens.saturated_into::<u128>(),
+		);
+		let share_of = |amount: Balance| {
+			share.saturating_mul_int(amount.saturated_into::<u128>()).saturated_into::<Balance>()
+		};
+		LiquidityPosition {
+			lp_tokens,
+			net_deposits: (share_of(self.net_deposits.0), share_of(self.net_deposits.1)),
+			..self
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Weight of updating a liquidity position after a deposit or withdrawal
+	pub fn liquidity_position_update_weight() -> Weight {
+		T::DbWeight::get().reads_writes(5, 1)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// The account's position in the pool, without the LP tokens it no longer owns.
+	/// Tokens transferred away take their share of the deposits with them, staked tokens are
+	/// still owned.
+	fn held_liquidity_position(
+		pool_id: &T::AccountId,
+		who: &T::AccountId,
+		lp_tokens_owned: BalanceOf<T>,
+	) -> Option<LiquidityPosition<BalanceOf<T>, T::BlockNumber>> {
+		Self::liquidity_position(pool_id, who)
+			.map(|position| position.reduced_to(lp_tokens_owned))
+			.filter(|position| position.lp_tokens > 0u32.into())
+	}
+
+	/// Add a deposit of `asset_amounts` for `lp_tokens_minted` to `who`'s position.
+	/// Must be called after the LP tokens are minted.
+	pub fn record_liquidity_deposit(
+		who: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+		lp_tokens_minted: BalanceOf<T>,
+	) {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let lp_token_id = Self::get_lp_token_id(&pool_id);
+		let position = Self::held_liquidity_position(
+			&pool_id,
+			who,
+			Self::lp_tokens_owned(&pool_id, who).saturating_sub(lp_tokens_minted),
+		)
+		.unwrap_or_default();
+
+		let asset_amounts = if asset_pair.0 <= asset_pair.1 {
+			asset_amounts
+		} else {
+			(asset_amounts.1, asset_amounts.0)
+		};
+		let liquidity_per_lp_token = Self::get_pool_liquidity(Self::sort_asset_pair(asset_pair))
+			.ok()
+			.and_then(|reserves| {
+				get_liquidity_per_lp_token(reserves, T::Assets::total_issuance(lp_token_id))
+			})
+			.unwrap_or_default();
+
+		// The entry liquidity per LP token is averaged over all tracked LP tokens
+		let lp_tokens = position.lp_tokens.saturating_add(lp_tokens_minted);
+		let weight_of = |amount: BalanceOf<T>| {
+			FixedU128::saturating_from_rational(
+				amount.saturated_into::<u128>(),
+				lp_tokens.saturated_into::<u128>(),
+			)
+		};
+		let entry_liquidity_per_lp_token = position
+			.entry_liquidity_per_lp_token
+			.saturating_mul(weight_of(position.lp_tokens))
+			.saturating_add(liquidity_per_lp_token.saturating_mul(weight_of(lp_tokens_minted)));
+
+		LiquidityPositions::<T>::insert(
+			&pool_id,
+			who,
+			LiquidityPosition {
+				lp_tokens,
+				net_deposits: (
+					position.net_deposits.0.saturating_add(asset_amounts.0),
+					position.net_deposits.1.saturating_add(asset_amounts.1),
+				),
+				entry_liquidity_per_lp_token,
+				last_deposit_block: frame_system::Pallet::<T>::block_number(),
+			},
+		);
+	}
+
+	/// Remove `lp_tokens_burned` from `who`'s position along with their share of the deposits.
+	/// Must be called after the LP tokens are burned.
+	pub fn record_liquidity_withdrawal(
+		who: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		lp_tokens_burned: BalanceOf<T>,
+	) {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let position = Self::held_liquidity_position(
+			&pool_id,
+			who,
+			Self::lp_tokens_owned(&pool_id, who).saturating_add(lp_tokens_burned),
+		);
+
+		match position {
+			Some(position) if position.lp_tokens > lp_tokens_burned => {
+				let lp_tokens = position.lp_tokens - lp_tokens_burned;
+				LiquidityPositions::<T>::insert(&pool_id, who, position.reduced_to(lp_tokens));
+			},
+			_ => LiquidityPositions::<T>::remove(&pool_id, who),
+		}
+	}
+
+	/// Value, net deposits, fees earned and impermanent loss of `who`'s position in the pool,
+	/// amounts in the order of `asset_pair`. LP tokens received by transfer are not included.
+	pub fn get_liquidity_position(
+		who: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Option<LiquidityPositionReport<BalanceOf<T>>> {
+		let pool_id = Self::get_pool_id(asset_pair);
+		if !Pools::<T>::contains_key(&pool_id) {
+			return None
+		}
+		let lp_token_id = Self::get_lp_token_id(&pool_id);
+		let position =
+			Self::held_liquidity_position(&pool_id, who, Self::lp_tokens_owned(&pool_id, who))?;
+
+		let reserves = Self::get_pool_liquidity(Self::sort_asset_pair(asset_pair)).ok()?;
+		let total_lp_token_supply = T::Assets::total_issuance(lp_token_id);
+		let value =
+			get_redeemed_token_balance(position.lp_tokens, total_lp_token_supply, reserves)?;
+
+		// Fees grow the liquidity per LP token, the value it grew by since the deposits is theirs
+		let liquidity_per_lp_token = get_liquidity_per_lp_token(reserves, total_lp_token_supply)?;
+		let value_share_without_fees = FixedU128::checked_from_rational(
+			position.entry_liquidity_per_lp_token.into_inner(),
+			liquidity_per_lp_token.into_inner(),
+		)
+		.unwrap_or_default()
+		.min(FixedU128::saturating_from_integer(1u32));
+		let without_fees = |amount: BalanceOf<T>| {
+			value_share_without_fees
+				.saturating_mul_int(amount.saturated_into::<u128>())
+				.saturated_into::<BalanceOf<T>>()
+		};
+		let value_without_fees = (without_fees(value.0), without_fees(value.1));
+		let fees_earned = (value.0 - value_without_fees.0, value.1 - value_without_fees.1);
+		let impermanent_loss =
+			get_impermanent_loss(position.net_deposits, value_without_fees, reserves);
+
+		let orient = |amounts: (BalanceOf<T>, BalanceOf<T>)| {
+			if asset_pair.0 <= asset_pair.1 { amounts } else { (amounts.1, amounts.0) }
+		};
+		Some(LiquidityPositionReport {
+			lp_tokens: position.lp_tokens,
+			value: orient(value),
+			net_deposits: orient(position.net_deposits),
+			fees_earned: orient(fees_earned),
+			impermanent_loss,
+		})
+	}
+}
@@ -0,0 +1,328 @@
+use super::*;
+
+impl<Balance, BlockNumber> LongTermOrderPool<Balance, BlockNumber> {
+	pub fn sell_rate_mut(&mut self, is_asset_a: bool) -> &mut Balance {
+		if is_asset_a {
+			&mut self.sell_rates.0
+		} else {
+			&mut self.sell_rates.1
+		}
+	}
+
+	pub fn proceeds_per_rate(&self, is_asset_a: bool) -> FixedU128 {
+		if is_asset_a {
+			self.proceeds_per_rate.0
+		} else {
+			self.proceeds_per_rate.1
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Weight of settling a pool's long-term orders, each selling out in a segment of its own
+	pub fn long_term_order_settlement_weight() -> Weight {
+		let segment_weight = (10_000 + T::DbWeight::get().reads_writes(14, 7))
+			.saturating_add(Self::swap_stats_weight());
+		(10_000 + T::DbWeight::get().reads_writes(4, 2))
+			.saturating_add(Self::price_oracle_update_weight())
+			.saturating_add(segment_weight.saturating_mul(T::MaxOrdersPerPool::get().into()))
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	#[transactional]
+	pub fn create_long_term_order(
+		sender: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount_in: BalanceOf<T>,
+		duration: T::BlockNumber,
+	) -> Result<OrderId, DispatchError> {
+		let duration_amount: BalanceOf<T> = duration.saturated_into::<u128>().saturated_into();
+		ensure!(duration_amount > 0u32.into(), Error::<T>::InvalidOrderParameters);
+		let sell_rate = amount_in / duration_amount;
+		ensure!(sell_rate > 0u32.into(), Error::<T>::InvalidOrderParameters);
+
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		ensure!(
+			pool_liquidity.0 > 0u32.into() && pool_liquidity.1 > 0u32.into(),
+			Error::<T>::NotEnoughLiquidity
+		);
+
+		// Bring the pool's order flow up to date before joining it
+		Self::settle_long_term_orders(asset_pair)?;
+
+		let pool_id = Self::get_pool_id(asset_pair);
+		let now = frame_system::Pallet::<T>::block_number();
+		let end_block = now + duration;
+		let order_id = NextOrderId::<T>::get();
+
+		// Keep the pool's orders sorted by ascending end block
+		LongTermOrderExpiries::<T>::try_mutate(&pool_id, |expiries| {
+			let index = expiries.iter().position(|(block, _)| *block > end_block);
+			expiries
+				.try_insert(index.unwrap_or(expiries.len()), (end_block, order_id))
+				.map_err(|_| Error::<T>::TooManyOrders)
+		})?;
+
+		Self::escrow_tokens(sender, asset_pair.0, sell_rate * duration_amount)?;
+
+		let is_asset_a = Self::sort_asset_pair(asset_pair).0 == asset_pair.0;
+		let mut pool = Self::long_term_order_pools(&pool_id)
+			.unwrap_or(LongTermOrderPool { last_settled_block: now, ..Default::default() });
+		let pool_sell_rate = pool.sell_rate_mut(is_asset_a);
+		*pool_sell_rate = pool_sell_rate.saturating_add(sell_rate);
+
+		LongTermOrders::<T>::insert(
+			order_id,
+			LongTermOrder {
+				owner: sender.clone(),
+				asset_in: asset_pair.0,
+				asset_out: asset_pair.1,
+				sell_rate,
+				end_block,
+				proceeds_per_rate_start: pool.proceeds_per_rate(is_asset_a),
+			},
+		);
+		LongTermOrderPools::<T>::insert(&pool_id, pool);
+		NextOrderId::<T>::put(order_id.saturating_add(1));
+
+		Self::deposit_event(Event::LongTermOrderPlaced {
+			order_id,
+			owner: sender.clone(),
+			asset_pair,
+			sell_rate,
+			end_block,
+		});
+
+		Ok(order_id)
+	}
+
+	#[transactional]
+	pub fn remove_long_term_order(sender: &T::AccountId, order_id: OrderId) -> DispatchResult {
+		let order = Self::long_term_orders(order_id).ok_or(Error::<T>::OrderNotFound)?;
+		ensure!(order.owner == *sender, Error::<T>::NotOrderOwner);
+
+		let asset_pair = (order.asset_in, order.asset_out);
+		Self::settle_long_term_orders(asset_pair)?;
+
+		// The order may have sold out and been paid during settlement
+		if !LongTermOrders::<T>::contains_key(order_id) {
+			return Ok(())
+		}
+
+		let pool_id = Self::get_pool_id(asset_pair);
+		let mut pool = Self::long_term_order_pools(&pool_id).ok_or(Error::<T>::OrderNotFound)?;
+		let is_asset_a = Self::sort_asset_pair(asset_pair).0 == asset_pair.0;
+
+		// Blocks after the current one have not been sold yet
+		let now = frame_system::Pallet::<T>::block_number();
+		let unsold_blocks: BalanceOf<T> =
+			order.end_block.saturating_sub(now).saturated_into::<u128>().saturated_into();
+		let refunded = order.sell_rate.saturating_mul(unsold_blocks);
+		let amount_out = Self::long_term_order_proceeds(&order, &pool, is_asset_a);
+
+		let pool_sell_rate = pool.sell_rate_mut(is_asset_a);
+		*pool_sell_rate = pool_sell_rate.saturating_sub(order.sell_rate);
+		let mut expiries = Self::long_term_order_expiries(&pool_id);
+		expiries.retain(|(_, id)| *id != order_id);
+		Self::store_long_term_order_pool(&pool_id, pool, expiries);
+		LongTermOrders::<T>::remove(order_id);
+
+		if refunded > 0u32.into() {
+			Self::release_escrowed_tokens(sender, order.asset_in, refunded)?;
+		}
+		if amount_out > 0u32.into() {
+			Self::release_escrowed_tokens(sender, order.asset_out, amount_out)?;
+		}
+
+		Self::deposit_event(Event::LongTermOrderCancelled {
+			order_id,
+			owner: sender.clone(),
+			refunded,
+			amount_out,
+		});
+
+		Ok(())
+	}
+
+	/// Proceeds the order has earned so far
+	fn long_term_order_proceeds(
+		order: &LongTermOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>, T::BlockNumber>,
+		pool: &LongTermOrderPool<BalanceOf<T>, T::BlockNumber>,
+		is_asset_a: bool,
+	) -> BalanceOf<T> {
+		pool.proceeds_per_rate(is_asset_a)
+			.saturating_sub(order.proceeds_per_rate_start)
+			.saturating_mul_int(order.sell_rate.saturated_into::<u128>())
+			.saturated_into()
+	}
+
+	fn store_long_term_order_pool(
+		pool_id: &T::AccountId,
+		pool: LongTermOrderPool<BalanceOf<T>, T::BlockNumber>,
+		expiries: BoundedVec<(T::BlockNumber, OrderId), T::MaxOrdersPerPool>,
+	) {
+		if expiries.is_empty() {
+			LongTermOrderPools::<T>::remove(pool_id);
+			LongTermOrderExpiries::<T>::remove(pool_id);
+		} else {
+			LongTermOrderPools::<T>::insert(pool_id, pool);
+			LongTermOrderExpiries::<T>::insert(pool_id, expiries);
+		}
+	}
+
+	/// Execute the virtual trades of the pool's long-term orders for every block since the last
+	/// settlement, and pay out the orders that have sold out.
+	/// Must be called before the pool reserves are read for any change to them.
+	#[transactional]
+	pub fn settle_long_term_orders(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let now = frame_system::Pallet::<T>::block_number();
+		let mut pool = match Self::long_term_order_pools(&pool_id) {
+			Some(pool) if pool.last_settled_block < now => pool,
+			_ => return Ok(()),
+		};
+		let asset_pair = Self::sort_asset_pair(asset_pair);
+		let mut expiries = Self::long_term_order_expiries(&pool_id);
+
+		// Record the price before the reserves change
+		Self::update_price_oracle(asset_pair);
+
+		// Sell rates only change when orders sell out, so trade in segments between those blocks
+		while pool.last_settled_block < now {
+			let segment_end = expiries.first().map_or(now, |(end_block, _)| (*end_block).min(now));
+			let blocks = segment_end.saturating_sub(pool.last_settled_block);
+			Self::execute_virtual_trades(&pool_id, asset_pair, &mut pool, blocks)?;
+			pool.last_settled_block = segment_end;
+
+			while let Some((end_block, order_id)) = expiries.first().copied() {
+				if end_block > segment_end {
+					break
+				}
+				expiries.remove(0);
+				Self::complete_long_term_order(order_id, asset_pair, &mut pool)?;
+			}
+		}
+
+		Self::store_long_term_order_pool(&pool_id, pool, expiries);
+
+		Self::deposit_event(Event::LongTermOrdersSettled {
+			pool_id,
+			asset_pair,
+			reserves: Self::get_pool_liquidity(asset_pair)?,
+		});
+
+		Ok(())
+	}
+
+	/// Trade `blocks` worth of both directions' sell rates. Opposing flows are matched with each
+	/// other at the pool's reserve ratio, only the excess is swapped through the pool.
+	fn execute_virtual_trades(
+		pool_id: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		pool: &mut LongTermOrderPool<BalanceOf<T>, T::BlockNumber>,
+		blocks: T::BlockNumber,
+	) -> DispatchResult {
+		let blocks: BalanceOf<T> = blocks.saturated_into::<u128>().saturated_into();
+		let amounts_in =
+			(pool.sell_rates.0.saturating_mul(blocks), pool.sell_rates.1.saturating_mul(blocks));
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		if pool_liquidity.0 == 0u32.into() || pool_liquidity.1 == 0u32.into() {
+			return Ok(())
+		}
+
+		let value_a = get_amount_at_reserve_ratio(amounts_in.0, pool_liquidity);
+		let proceeds = if value_a <= amounts_in.1 {
+			// Sellers of asset_pair.1 take all of asset_pair.0 and swap the rest
+			let excess = amounts_in.1 - value_a;
+			let swap_return =
+				Self::execute_virtual_swap(pool_id, (asset_pair.1, asset_pair.0), excess)?;
+			(value_a, amounts_in.0.saturating_add(swap_return))
+		} else {
+			// Sellers of asset_pair.0 take all of asset_pair.1 and swap the rest
+			let value_b =
+				get_amount_at_reserve_ratio(amounts_in.1, (pool_liquidity.1, pool_liquidity.0));
+			let excess = amounts_in.0.saturating_sub(value_b);
+			let swap_return = Self::execute_virtual_swap(pool_id, asset_pair, excess)?;
+			(amounts_in.1.saturating_add(swap_return), value_b)
+		};
+
+		pool.proceeds_per_rate = (
+			Self::accumulate_proceeds(pool.proceeds_per_rate.0, proceeds.0, pool.sell_rates.0)?,
+			Self::accumulate_proceeds(pool.proceeds_per_rate.1, proceeds.1, pool.sell_rates.1)?,
+		);
+
+		Ok(())
+	}
+
+	/// Add the proceeds of one direction to its cumulative proceeds per unit of sell rate
+	fn accumulate_proceeds(
+		proceeds_per_rate: FixedU128,
+		proceeds: BalanceOf<T>,
+		sell_rate: BalanceOf<T>,
+	) -> Result<FixedU128, DispatchError> {
+		if sell_rate == 0u32.into() {
+			return Ok(proceeds_per_rate)
+		}
+		let added = FixedU128::checked_from_rational(
+			proceeds.saturated_into::<u128>(),
+			sell_rate.saturated_into::<u128>(),
+		)
+		.ok_or(Error::<T>::MathOverflow)?;
+		Ok(proceeds_per_rate.saturating_add(added))
+	}
+
+	/// Swap escrowed long-term order funds through the pool
+	fn execute_virtual_swap(
+		pool_id: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount_in: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		if amount_in == 0u32.into() {
+			return Ok(0u32.into())
+		}
+
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+		let (swap_return, fee) =
+			get_swap_return_and_fee::<BalanceOf<T>, T>(amount_in, pool_liquidity)?;
+
+		let escrow = Self::account_id();
+		T::Assets::transfer(asset_pair.0, &escrow, pool_id, amount_in, false)?;
+		T::Assets::transfer(asset_pair.1, pool_id, &escrow, swap_return, false)?;
+		Self::record_swap_stats(asset_pair, amount_in, fee);
+
+		Ok(swap_return)
+	}
+
+	/// Pay out a sold out order and remove its sell rate from the pool
+	fn complete_long_term_order(
+		order_id: OrderId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		pool: &mut LongTermOrderPool<BalanceOf<T>, T::BlockNumber>,
+	) -> DispatchResult {
+		let order = match LongTermOrders::<T>::take(order_id) {
+			Some(order) => order,
+			None => return Ok(()),
+		};
+		let is_asset_a = asset_pair.0 == order.asset_in;
+
+		let amount_out = Self::long_term_order_proceeds(&order, pool, is_asset_a);
+		let pool_sell_rate = pool.sell_rate_mut(is_asset_a);
+		*pool_sell_rate = pool_sell_rate.saturating_sub(order.sell_rate);
+
+		if amount_out > 0u32.into() {
+			Self::release_escrowed_tokens(&order.owner, order.asset_out, amount_out)?;
+		}
+
+		Self::deposit_event(Event::LongTermOrderCompleted {
+			order_id,
+			owner: order.owner,
+			amount_out,
+		});
+
+		Ok(())
+	}
+}
@@ -1,9 +1,34 @@
 use super::*;
 
+impl<T: Config> Pallet<T> {
+	/// Weight of redeeming LP tokens, including scheduling the teardown of an emptied pool
+	pub fn lp_redemption_weight() -> Weight {
+		(10_000 + T::DbWeight::get().reads_writes(23, 7))
+			.saturating_add(Self::long_term_order_settlement_weight())
+			.saturating_add(Self::price_oracle_update_weight())
+			.saturating_add(Self::liquidity_position_update_weight())
+	}
+}
+
 impl<T: Config> Pallet<T>
 where
 	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
 {
+	/// Check `who` is able to give up `amount` of the LP token
+	pub fn check_can_withdraw_lp_tokens(
+		who: &T::AccountId,
+		lp_token_id: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		Self::check_can_withdraw(who, lp_token_id, amount, false).map_err(|error| {
+			if error == Error::<T>::NotEnoughTokensForTransaction.into() {
+				Error::<T>::NotEnoughLPTokens.into()
+			} else {
+				error
+			}
+		})
+	}
+
 	pub fn check_lp_redemption_is_valid(
 		sender: &T::AccountId,
 		lp_token_id: AssetIdOf<T>,
@@ -14,41 +39,57 @@ where
 		ensure!(asset_pair.0 != asset_pair.1, Error::<T>::ProvidedInvalidAssetIds);
 
 		// check if sender has enough lp tokens
-		ensure!(
-			Self::has_enough_tokens(lp_token_id, lp_token_amount, sender),
-			Error::<T>::NotEnoughLPTokens
-		);
+		Self::check_can_withdraw_lp_tokens(sender, lp_token_id, lp_token_amount)?;
 
 		Ok(())
 	}
 
+	/// Burn `sender`'s LP tokens, sending the liquidity they redeem to `recipient`
+	#[transactional]
 	pub fn handle_lp_token_redemption(
 		sender: &T::AccountId,
+		recipient: &T::AccountId,
 		pool_id: T::AccountId,
 		lp_token_id: AssetIdOf<T>,
 		lp_token_amount: BalanceOf<T>,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
-	) -> Result<(), DispatchError> {
-		// Get pool data
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		// Get pool data, after the virtual trades of long-term orders
+		Self::settle_long_term_orders(asset_pair)?;
 		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
 		let total_lp_token_supply = T::Assets::total_issuance(lp_token_id);
 		let redeemed_token_amounts =
 			get_redeemed_token_balance(lp_token_amount, total_lp_token_supply, pool_liquidity)
 				.unwrap();
 
-		// Send the user their assets
-		T::Assets::transfer(asset_pair.0, &pool_id, &sender, redeemed_token_amounts.0, false)?;
-		T::Assets::transfer(asset_pair.1, &pool_id, &sender, redeemed_token_amounts.1, false)?;
+		// Record the price before the reserves change
+		Self::update_price_oracle(asset_pair);
+
+		// Send the user their assets, the last redemption empties the pool's asset accounts
+		T::Assets::transfer(asset_pair.0, &pool_id, recipient, redeemed_token_amounts.0, false)?;
+		T::Assets::transfer(asset_pair.1, &pool_id, recipient, redeemed_token_amounts.1, false)?;
 
 		// Burn the LP tokens
 		T::Assets::burn_from(lp_token_id, &sender, lp_token_amount)?;
+		Self::record_liquidity_withdrawal(sender, asset_pair, lp_token_amount);
 
-        Self::deposit_event(Event::LiquidityRedeemed {
-			pool_id,
+		Self::deposit_event(Event::LiquidityRedeemed {
+			pool_id: pool_id.clone(),
+			who: sender.clone(),
+			recipient: recipient.clone(),
 			asset_pair,
+			lp_token_id,
+			lp_token_amount,
 			redeemed_token_amounts,
+			reserves: Self::get_pool_liquidity(asset_pair)?,
 		});
 
-		Ok(())
+		// Queue the pool for teardown once only locked LP tokens are left, if nothing else
+		// depends on it
+		if T::Assets::total_issuance(lp_token_id) == Self::locked_lp_tokens(&pool_id) {
+			let _ = Self::schedule_pool_teardown(asset_pair);
+		}
+
+		Ok(redeemed_token_amounts)
 	}
 }
@@ -0,0 +1,191 @@
+use super::*;
+
+impl<T: Config> Pallet<T> {
+	/// Weight of matching a single resting limit order
+	pub fn limit_order_match_weight() -> Weight {
+		(10_000 + T::DbWeight::get().reads_writes(14, 9))
+			.saturating_add(Self::price_oracle_update_weight())
+	}
+
+	/// Weight of a routed swap, going through the pool before each of the pool's limit orders it
+	/// matches and once more for the rest
+	pub fn routed_swap_weight() -> Weight {
+		let order_weight = T::DbWeight::get()
+			.reads(3)
+			.saturating_add(Self::pool_swap_weight())
+			.saturating_add(Self::limit_order_match_weight());
+		(10_000 + T::DbWeight::get().reads_writes(4, 1))
+			.saturating_add(Self::long_term_order_settlement_weight())
+			.saturating_add(Self::pool_swap_weight())
+			.saturating_add(Self::price_limits_check_weight())
+			.saturating_add(order_weight.saturating_mul(T::MaxOrdersPerPool::get().into()))
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Swap asset_pair.0 for asset_pair.1, taking from resting limit orders selling asset_pair.1
+	/// whenever they are cheaper than the pool. The proceeds are sent to `recipient`.
+	/// With `keep_alive` set, `sender`'s asset_pair.0 account is kept alive.
+	/// Returns the total amount received.
+	#[transactional]
+	pub fn process_routed_swap(
+		sender: &T::AccountId,
+		recipient: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		asset_a_amount: BalanceOf<T>,
+		keep_alive: bool,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		// Resting orders are only matched while the pool trades
+		Self::check_swaps_allowed(asset_pair)?;
+		Self::settle_long_term_orders(asset_pair)?;
+		let price_before = Self::get_limit_price(asset_pair);
+		let pool_id = Self::get_pool_id(asset_pair);
+		let mut remaining_amount = asset_a_amount;
+		let mut total_return: BalanceOf<T> = 0u32.into();
+
+		// Orders selling asset_pair.1, cheapest first
+		let mut orders = Self::pool_limit_orders(&pool_id, asset_pair.1);
+		let mut filled = vec![];
+
+		for (limit_price, order_id) in orders.iter() {
+			if remaining_amount == 0u32.into() {
+				break
+			}
+
+			let order = match Self::limit_orders(order_id) {
+				Some(order) => order,
+				None => {
+					filled.push(*order_id);
+					continue
+				},
+			};
+
+			// Use the pool for as long as it is cheaper than the order
+			let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
+			let pool_price = get_marginal_swap_price(pool_liquidity).unwrap_or_default();
+			if pool_price < *limit_price {
+				let pool_amount =
+					get_swap_input_to_price(*limit_price, pool_liquidity).min(remaining_amount);
+				if pool_amount > 0u32.into() {
+					let swap_return = Self::process_swap(
+						sender,
+						recipient,
+						asset_pair,
+						pool_amount,
+						keep_alive,
+					)?;
+					total_return = total_return.saturating_add(swap_return);
+					remaining_amount = remaining_amount.saturating_sub(pool_amount);
+				}
+			}
+
+			// Then take from the order at its limit price
+			let (spent, received, remaining_order_amount) = Self::match_limit_order(
+				sender,
+				recipient,
+				*order_id,
+				&order,
+				remaining_amount,
+				keep_alive,
+			)?;
+			if spent == 0u32.into() {
+				// Not even a single unit of the order is affordable, the pool takes the rest
+				break
+			}
+			total_return = total_return.saturating_add(received);
+			remaining_amount = remaining_amount.saturating_sub(spent);
+			if remaining_order_amount == 0u32.into() {
+				filled.push(*order_id);
+			}
+		}
+
+		if !filled.is_empty() {
+			orders.retain(|(_, id)| !filled.contains(id));
+			if orders.is_empty() {
+				PoolLimitOrders::<T>::remove(&pool_id, asset_pair.1);
+			} else {
+				PoolLimitOrders::<T>::insert(&pool_id, asset_pair.1, orders);
+			}
+		}
+
+		// Whatever is left goes through the pool
+		if remaining_amount > 0u32.into() {
+			let swap_return =
+				Self::process_swap(sender, recipient, asset_pair, remaining_amount, keep_alive)?;
+			total_return = total_return.saturating_add(swap_return);
+		}
+
+		// The pool parts of the swap are limited together
+		Self::check_price_limits(asset_pair, price_before)?;
+
+		Ok(total_return)
+	}
+
+	/// Buy as much of the order as `available` allows, at the order's limit price, for
+	/// `recipient`. Maker and taker fees are paid to the pool. Returns the amount the taker spent,
+	/// the amount received and the amount left in the order.
+	pub fn match_limit_order(
+		taker: &T::AccountId,
+		recipient: &T::AccountId,
+		order_id: OrderId,
+		order: &LimitOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+		available: BalanceOf<T>,
+		keep_alive: bool,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let (amount_sold, amount_paid) =
+			get_order_fill(available, order.amount_in, order.limit_price)
+				.ok_or(Error::<T>::MathOverflow)?;
+		if amount_sold == 0u32.into() {
+			return Ok((0u32.into(), 0u32.into(), order.amount_in))
+		}
+
+		let asset_pair = (order.asset_out, order.asset_in);
+		let pool_id = Self::get_pool_id(asset_pair);
+		let maker_fee = T::MakerFee::get().mul_floor(amount_paid);
+		let taker_fee = T::TakerFee::get().mul_floor(amount_sold);
+
+		// Fees change the pool reserves
+		Self::update_price_oracle(asset_pair);
+
+		// The taker pays the maker, the maker fee goes to the pool
+		let maker_amount = amount_paid - maker_fee;
+		T::Assets::transfer(order.asset_out, taker, &order.owner, maker_amount, keep_alive)?;
+		if maker_fee > 0u32.into() {
+			T::Assets::transfer(order.asset_out, taker, &pool_id, maker_fee, keep_alive)?;
+		}
+
+		// The escrowed funds go to the recipient, the taker fee goes to the pool
+		let amount_received = amount_sold - taker_fee;
+		Self::release_escrowed_tokens(recipient, order.asset_in, amount_received)?;
+		if taker_fee > 0u32.into() {
+			T::Assets::transfer(order.asset_in, &Self::account_id(), &pool_id, taker_fee, false)?;
+		}
+
+		let remaining_order_amount = order.amount_in - amount_sold;
+		if remaining_order_amount == 0u32.into() {
+			LimitOrders::<T>::remove(order_id);
+		} else {
+			LimitOrders::<T>::insert(
+				order_id,
+				LimitOrder { amount_in: remaining_order_amount, ..order.clone() },
+			);
+		}
+
+		Self::deposit_event(Event::LimitOrderMatched {
+			order_id,
+			maker: order.owner.clone(),
+			taker: taker.clone(),
+			amount_sold,
+			amount_paid,
+			maker_fee,
+			taker_fee,
+			pool_id,
+			reserves: Self::get_pool_liquidity(asset_pair)?,
+		});
+
+		Ok((amount_paid, amount_received, remaining_order_amount))
+	}
+}
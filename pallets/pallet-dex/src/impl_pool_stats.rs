@@ -0,0 +1,107 @@
+use super::*;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+impl<Balance, BlockNumber> PoolStats<Balance, BlockNumber> {
+	/// Statistics with the amounts in the reverse asset order
+	pub fn reversed(self) -> Self {
+		PoolStats {
+			volumes: (self.volumes.1, self.volumes.0),
+			fees: (self.fees.1, self.fees.0),
+			..self
+		}
+	}
+}
+
+impl<Balance> DailyPoolStats<Balance> {
+	/// Statistics with the amounts in the reverse asset order
+	pub fn reversed(self) -> Self {
+		DailyPoolStats {
+			volumes: (self.volumes.1, self.volumes.0),
+			fees: (self.fees.1, self.fees.0),
+			..self
+		}
+	}
+}
+
+/// Add `amount` to the side of `amounts` of the lower asset id if `is_asset_a`
+fn add_to_side<Balance: AtLeast32Bit + Copy>(
+	amounts: &mut (Balance, Balance),
+	is_asset_a: bool,
+	amount: Balance,
+) {
+	let side = if is_asset_a { &mut amounts.0 } else { &mut amounts.1 };
+	*side = side.saturating_add(amount);
+}
+
+impl<T: Config> Pallet<T> {
+	/// Weight of recording a swap in the pool's all time and daily statistics
+	pub fn swap_stats_weight() -> Weight {
+		T::DbWeight::get().reads_writes(3, 2)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Record a swap of `amount_in` of asset_pair.0 through the pool, which kept `fee` of
+	/// asset_pair.1
+	pub fn record_swap_stats(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		amount_in: BalanceOf<T>,
+		fee: BalanceOf<T>,
+	) {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let is_asset_a = asset_pair.0 <= asset_pair.1;
+
+		PoolStatistics::<T>::mutate(&pool_id, |stats| {
+			add_to_side(&mut stats.volumes, is_asset_a, amount_in);
+			add_to_side(&mut stats.fees, !is_asset_a, fee);
+			stats.trade_count = stats.trade_count.saturating_add(1);
+			stats.last_trade_block = frame_system::Pallet::<T>::block_number();
+		});
+
+		let today = Self::now_in_secs() / SECONDS_PER_DAY;
+		DailyPoolStatistics::<T>::mutate(&pool_id, |days| {
+			if days.last().map_or(true, |last| last.day < today) {
+				if !days.is_empty() && days.len() as u32 >= T::MaxStatsDays::get() {
+					days.remove(0);
+				}
+				// Can only fail if MaxStatsDays is zero
+				if days.try_push(DailyPoolStats { day: today, ..Default::default() }).is_err() {
+					return
+				}
+			}
+			if let Some(stats) = days.last_mut() {
+				add_to_side(&mut stats.volumes, is_asset_a, amount_in);
+				add_to_side(&mut stats.fees, !is_asset_a, fee);
+				stats.trade_count = stats.trade_count.saturating_add(1);
+			}
+		});
+	}
+
+	/// Trading statistics of the pool, amounts in the order of `asset_pair`.
+	/// `None` if the pool does not exist.
+	pub fn get_pool_stats(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Option<PoolStats<BalanceOf<T>, T::BlockNumber>> {
+		let pool_id = Self::get_pool_id(asset_pair);
+		if !Pools::<T>::contains_key(&pool_id) {
+			return None
+		}
+		let stats = Self::pool_stats(&pool_id);
+		Some(if asset_pair.0 <= asset_pair.1 { stats } else { stats.reversed() })
+	}
+
+	/// Daily trading statistics of the pool, oldest first, amounts in the order of `asset_pair`
+	pub fn get_daily_pool_stats(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Vec<DailyPoolStats<BalanceOf<T>>> {
+		let is_sorted = asset_pair.0 <= asset_pair.1;
+		Self::daily_pool_stats(Self::get_pool_id(asset_pair))
+			.into_iter()
+			.map(|stats| if is_sorted { stats } else { stats.reversed() })
+			.collect()
+	}
+}
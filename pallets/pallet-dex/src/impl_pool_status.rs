@@ -0,0 +1,82 @@
+use super::*;
+
+impl PoolStatus {
+	pub fn allows_swaps(&self) -> bool {
+		*self == PoolStatus::Active
+	}
+
+	pub fn allows_liquidity_provision(&self) -> bool {
+		matches!(*self, PoolStatus::Active | PoolStatus::SwapsPaused)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	pub fn check_swaps_allowed(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		let status = Self::pool_status(Self::get_pool_id(asset_pair));
+		ensure!(status.allows_swaps(), Error::<T>::PoolSwapsPaused);
+		Ok(())
+	}
+
+	pub fn check_liquidity_provision_allowed(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> DispatchResult {
+		let status = Self::pool_status(Self::get_pool_id(asset_pair));
+		ensure!(status.allows_liquidity_provision(), Error::<T>::PoolPaused);
+		Ok(())
+	}
+
+	pub fn set_pool_status_for(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		status: PoolStatus,
+	) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		// Delisting is final, the pool's reserves are being returned to its liquidity providers
+		ensure!(Self::pool_status(&pool_id) != PoolStatus::Delisted, Error::<T>::PoolDelisted);
+
+		PoolStatuses::<T>::insert(&pool_id, status);
+		Self::deposit_event(Event::PoolStatusSet { pool_id, status });
+
+		Ok(())
+	}
+
+	/// Redeem all LP tokens the holders have in the delisted pool on their behalf
+	#[transactional]
+	pub fn force_return_liquidity_to(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		holders: &[T::AccountId],
+	) -> DispatchResult {
+		let pool_id = Self::get_pool_id(asset_pair);
+		ensure!(Self::pool_status(&pool_id) == PoolStatus::Delisted, Error::<T>::PoolNotDelisted);
+		let lp_token_id = Self::get_lp_token_id(&pool_id);
+		let escrow = Self::account_id();
+
+		for holder in holders.iter() {
+			// LP tokens held in escrow belong to conditional orders, their owners redeem them
+			let lp_token_amount = T::Assets::balance(lp_token_id, holder);
+			if *holder == escrow || lp_token_amount == 0u32.into() {
+				continue
+			}
+
+			let redeemed_token_amounts = Self::handle_lp_token_redemption(
+				holder,
+				holder,
+				pool_id.clone(),
+				lp_token_id,
+				lp_token_amount,
+				asset_pair,
+			)?;
+
+			Self::deposit_event(Event::LiquidityForceReturned {
+				pool_id: pool_id.clone(),
+				who: holder.clone(),
+				lp_token_amount,
+				redeemed_token_amounts,
+			});
+		}
+
+		Ok(())
+	}
+}
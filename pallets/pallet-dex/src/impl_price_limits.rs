@@ -0,0 +1,179 @@
+use super::*;
+use frame_support::storage::{with_transaction, TransactionOutcome};
+
+impl<T: Config> Pallet<T> {
+	/// Weight of checking a swap against the pool's price limits, with a TWAP reference
+	pub fn price_limits_check_weight() -> Weight {
+		T::DbWeight::get().reads(7)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Price the limits are measured on, that of the pool's lower asset id in its higher one
+	pub fn get_limit_price(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> Option<FixedU128> {
+		Self::get_spot_price(Self::sort_asset_pair(asset_pair))
+	}
+
+	/// Whether `price` differs from `reference` by more than `limit`
+	fn exceeds_price_limit(price: FixedU128, reference: FixedU128, limit: Permill) -> bool {
+		let difference = if price > reference { price - reference } else { reference - price };
+		match FixedU128::checked_from_rational(difference.into_inner(), reference.into_inner()) {
+			Some(change) => change > FixedU128::from(limit),
+			// Any move away from a zero price is unbounded
+			None => difference.into_inner() > 0,
+		}
+	}
+
+	/// Record the pool's price before its reserves first change in the block.
+	/// Only pools with price limits are tracked.
+	pub fn record_opening_price(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) {
+		let pool_id = Self::get_pool_id(asset_pair);
+		if !PoolPriceLimits::<T>::contains_key(&pool_id) {
+			return
+		}
+
+		let now = frame_system::Pallet::<T>::block_number();
+		BlockPriceStates::<T>::mutate(&pool_id, |maybe_state| {
+			if maybe_state.as_ref().map_or(true, |state| state.block < now) {
+				*maybe_state = Self::get_limit_price(asset_pair).map(|opening_price| {
+					BlockPriceState { block: now, opening_price, circuit_breaker_tripped: false }
+				});
+			}
+		});
+	}
+
+	/// Price the pool's movement within the current block is measured against
+	fn get_reference_price(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		reference: PriceReference,
+	) -> Option<FixedU128> {
+		match reference {
+			PriceReference::OpeningPrice => {
+				let now = frame_system::Pallet::<T>::block_number();
+				Self::block_price_states(Self::get_pool_id(asset_pair))
+					.filter(|state| state.block == now)
+					.map(|state| state.opening_price)
+			},
+			PriceReference::Twap { window } =>
+				Self::get_twap(Self::sort_asset_pair(asset_pair), window),
+		}
+	}
+
+	pub fn check_circuit_breaker(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> DispatchResult {
+		let now = frame_system::Pallet::<T>::block_number();
+		let tripped = Self::block_price_states(Self::get_pool_id(asset_pair))
+			.map_or(false, |state| state.block == now && state.circuit_breaker_tripped);
+		ensure!(!tripped, Error::<T>::CircuitBreakerTripped);
+		Ok(())
+	}
+
+	/// Check a swap which moved the pool's price from `price_before` kept within the pool's
+	/// price impact and per-block price movement limits
+	pub fn check_price_limits(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		price_before: Option<FixedU128>,
+	) -> DispatchResult {
+		let limits = match Self::pool_price_limits(Self::get_pool_id(asset_pair)) {
+			Some(limits) => limits,
+			None => return Ok(()),
+		};
+		let price = match Self::get_limit_price(asset_pair) {
+			Some(price) => price,
+			None => return Ok(()),
+		};
+
+		if let (Some(max_price_impact), Some(price_before)) =
+			(limits.max_price_impact, price_before)
+		{
+			ensure!(
+				!Self::exceeds_price_limit(price, price_before, max_price_impact),
+				Error::<T>::PriceImpactTooHigh
+			);
+		}
+
+		// Without a reference price, e.g. a TWAP lacking history, the movement is not limited
+		if let (Some(max_price_movement), Some(reference_price)) = (
+			limits.max_price_movement,
+			Self::get_reference_price(asset_pair, limits.reference),
+		) {
+			ensure!(
+				!Self::exceeds_price_limit(price, reference_price, max_price_movement),
+				Error::<T>::PriceMovementLimitExceeded
+			);
+		}
+
+		Ok(())
+	}
+
+	/// Halt the pool's swaps for the rest of the block
+	pub fn trip_circuit_breaker(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) {
+		let pool_id = Self::get_pool_id(asset_pair);
+		let now = frame_system::Pallet::<T>::block_number();
+		let price = Self::get_limit_price(asset_pair).unwrap_or_default();
+
+		BlockPriceStates::<T>::mutate(&pool_id, |maybe_state| match maybe_state {
+			Some(state) if state.block == now => state.circuit_breaker_tripped = true,
+			_ =>
+				*maybe_state = Some(BlockPriceState {
+					block: now,
+					opening_price: price,
+					circuit_breaker_tripped: true,
+				}),
+		});
+
+		Self::deposit_event(Event::CircuitBreakerTripped { pool_id, price });
+	}
+
+	/// Run `swap`, a swap through the pool of `asset_pair`, reverting it if it fails. A swap
+	/// moving the price beyond the pool's per-block limit trips the pool's circuit breaker and
+	/// returns `None`. The trip is recorded once the swap is reverted, so it persists unless the
+	/// caller reverts its own changes.
+	pub fn with_circuit_breaker(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		swap: impl FnOnce() -> Result<BalanceOf<T>, DispatchError>,
+	) -> Result<Option<BalanceOf<T>>, DispatchError> {
+		let result = with_transaction(|| {
+			let result = swap();
+			if result.is_ok() {
+				TransactionOutcome::Commit(result)
+			} else {
+				TransactionOutcome::Rollback(result)
+			}
+		});
+
+		match result {
+			Ok(amount_out) => Ok(Some(amount_out)),
+			Err(error) if error == DispatchError::from(Error::<T>::PriceMovementLimitExceeded) => {
+				Self::trip_circuit_breaker(asset_pair);
+				Ok(None)
+			},
+			Err(error) => Err(error),
+		}
+	}
+
+	/// Swap through the pool and its order book for at least `min_amount_out`, returning the
+	/// amount received. A swap tripping the pool's circuit breaker is reverted and returns `None`.
+	pub fn process_swap_with_circuit_breaker(
+		sender: &T::AccountId,
+		recipient: &T::AccountId,
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+		asset_a_amount: BalanceOf<T>,
+		min_amount_out: BalanceOf<T>,
+		keep_alive: bool,
+	) -> Result<Option<BalanceOf<T>>, DispatchError> {
+		Self::with_circuit_breaker(asset_pair, || {
+			let amount_out = Self::process_routed_swap(
+				sender,
+				recipient,
+				asset_pair,
+				asset_a_amount,
+				keep_alive,
+			)?;
+			ensure!(amount_out >= min_amount_out, Error::<T>::SlippageExceeded);
+			Ok(amount_out)
+		})
+	}
+}
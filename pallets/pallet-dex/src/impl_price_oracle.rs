@@ -0,0 +1,144 @@
+use super::*;
+
+impl PriceObservation {
+	/// Roll the accumulators forward to `now`, assuming `prices` held since the last observation
+	pub fn accumulate(&self, now: u64, prices: (FixedU128, FixedU128)) -> PriceObservation {
+		let elapsed: u128 = now.saturating_sub(self.timestamp).into();
+		PriceObservation {
+			timestamp: now,
+			price_a_cumulative: self
+				.price_a_cumulative
+				.wrapping_add(prices.0.into_inner().wrapping_mul(elapsed)),
+			price_b_cumulative: self
+				.price_b_cumulative
+				.wrapping_add(prices.1.into_inner().wrapping_mul(elapsed)),
+		}
+	}
+
+	pub fn price_cumulative(&self, is_asset_a: bool) -> u128 {
+		if is_asset_a {
+			self.price_a_cumulative
+		} else {
+			self.price_b_cumulative
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Weight of recording a price observation, along with the block's opening price
+	pub fn price_oracle_update_weight() -> Weight {
+		T::DbWeight::get().reads_writes(6, 2)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	pub fn now_in_secs() -> u64 {
+		T::UnixTime::now().as_secs()
+	}
+
+	pub fn sort_asset_pair(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> (AssetIdOf<T>, AssetIdOf<T>) {
+		if asset_pair.0 <= asset_pair.1 {
+			asset_pair
+		} else {
+			(asset_pair.1, asset_pair.0)
+		}
+	}
+
+	/// Price of asset_pair.0 denominated in asset_pair.1, based on the current pool reserves
+	pub fn get_spot_price(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> Option<FixedU128> {
+		let pool_liquidity = Self::get_pool_liquidity(asset_pair).ok()?;
+		FixedU128::checked_from_rational(
+			pool_liquidity.1.saturated_into::<u128>(),
+			pool_liquidity.0.saturated_into::<u128>(),
+		)
+	}
+
+	/// Current spot prices of the sorted asset pair, zero for an empty pool
+	fn get_sorted_spot_prices(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> (FixedU128, FixedU128) {
+		let (asset_a, asset_b) = Self::sort_asset_pair(asset_pair);
+		(
+			Self::get_spot_price((asset_a, asset_b)).unwrap_or_default(),
+			Self::get_spot_price((asset_b, asset_a)).unwrap_or_default(),
+		)
+	}
+
+	/// Record the prices the pool held since the last observation.
+	/// Must be called before any change to the pool reserves.
+	pub fn update_price_oracle(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) {
+		Self::record_opening_price(asset_pair);
+
+		let pool_id = Self::get_pool_id(asset_pair);
+		let now = Self::now_in_secs();
+
+		PriceObservations::<T>::mutate(&pool_id, |observations| {
+			let observation = match observations.last() {
+				// Prices can only be accumulated once per timestamp
+				Some(last) if last.timestamp >= now => return,
+				Some(last) => last.accumulate(now, Self::get_sorted_spot_prices(asset_pair)),
+				None => PriceObservation { timestamp: now, ..Default::default() },
+			};
+
+			if !observations.is_empty() &&
+				observations.len() as u32 >= T::MaxPriceObservations::get()
+			{
+				observations.remove(0);
+			}
+			// Can only fail if MaxPriceObservations is zero
+			let _ = observations.try_push(observation);
+		});
+	}
+
+	/// Time weighted average price of asset_pair.0 denominated in asset_pair.1
+	/// over the last `window` seconds
+	pub fn get_twap(asset_pair: (AssetIdOf<T>, AssetIdOf<T>), window: u64) -> Option<FixedU128> {
+		if window == 0 || asset_pair.0 == asset_pair.1 {
+			return None
+		}
+
+		let pool_id = Self::get_pool_id(asset_pair);
+		let is_asset_a = Self::sort_asset_pair(asset_pair).0 == asset_pair.0;
+		let observations = Self::price_observations(&pool_id);
+
+		let now = Self::now_in_secs();
+		let window_start = now.checked_sub(window)?;
+
+		// Extrapolate the latest observation to now using the current reserves
+		let latest = observations.last()?;
+		let latest = if latest.timestamp < now {
+			latest.accumulate(now, Self::get_sorted_spot_prices(asset_pair))
+		} else {
+			*latest
+		};
+
+		let start_index = observations.iter().rposition(|o| o.timestamp <= window_start)?;
+		let start = observations[start_index];
+		let next = observations.get(start_index + 1).copied().unwrap_or(latest);
+
+		// Prices are constant between two observations, so interpolate to the window start
+		let start_cumulative = if start.timestamp == window_start {
+			start.price_cumulative(is_asset_a)
+		} else {
+			let interval: u128 = next.timestamp.saturating_sub(start.timestamp).into();
+			let price = next
+				.price_cumulative(is_asset_a)
+				.wrapping_sub(start.price_cumulative(is_asset_a))
+				.checked_div(interval)?;
+			let elapsed: u128 = (window_start - start.timestamp).into();
+			start.price_cumulative(is_asset_a).wrapping_add(price.wrapping_mul(elapsed))
+		};
+
+		let average_price = latest
+			.price_cumulative(is_asset_a)
+			.wrapping_sub(start_cumulative)
+			.checked_div(window.into())?;
+
+		Some(FixedU128::from_inner(average_price))
+	}
+}
@@ -1,5 +1,15 @@
 use super::*;
 
+impl<T: Config> Pallet<T> {
+	/// Weight of adding liquidity to an existing pool
+	pub fn liquidity_deposit_weight() -> Weight {
+		(10_000 + T::DbWeight::get().reads_writes(16, 6))
+			.saturating_add(Self::long_term_order_settlement_weight())
+			.saturating_add(Self::price_oracle_update_weight())
+			.saturating_add(Self::liquidity_position_update_weight())
+	}
+}
+
 impl<T: Config> Pallet<T>
 where
 	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
@@ -24,32 +34,45 @@ where
 		Ok((lp_token_id, lp_tokens_amount))
 	}
 
+	/// Deposit `sender`'s tokens into the pool, minting the LP tokens to `recipient`.
+	/// With `keep_alive` set, `sender`'s asset accounts are kept alive.
+	#[transactional]
 	pub fn process_liquidity_pool_deposit(
 		sender: &T::AccountId,
+		recipient: &T::AccountId,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
 		asset_amounts: (BalanceOf<T>, BalanceOf<T>),
 		current_token_amount: BalanceOf<T>,
-	) -> Result<(), DispatchError> {
+		keep_alive: bool,
+	) -> Result<BalanceOf<T>, DispatchError> {
 		// Initialize the new pool
 		let pool_id = Self::get_pool_id(asset_pair);
 
+		// Record the price before the reserves change
+		Self::update_price_oracle(asset_pair);
+
 		// Transfer the tokens to the new pool
-		Self::transfer_tokens_to_pool(&sender, &pool_id, asset_pair, asset_amounts)?;
+		Self::transfer_tokens_to_pool(&sender, &pool_id, asset_pair, asset_amounts, keep_alive)?;
 
 		// Send the lp tokens in exchange to the pool creator
 		let (lp_token_id, lp_tokens_amount) = Self::send_lp_tokens_to_pool_contributor(
-			&sender,
+			recipient,
 			&pool_id,
 			asset_amounts.0,
 			current_token_amount,
 		)?;
+		Self::record_liquidity_deposit(recipient, asset_pair, asset_amounts, lp_tokens_amount);
 
 		Self::deposit_event(Event::LiquidityProvided {
 			pool_id,
+			who: recipient.clone(),
+			asset_pair,
+			asset_amounts,
 			lp_token_id,
 			earned_lp_tokens: lp_tokens_amount,
+			reserves: Self::get_pool_liquidity(asset_pair)?,
 		});
 
-		Ok(())
+		Ok(lp_tokens_amount)
 	}
 }
@@ -1,31 +1,71 @@
 use super::*;
 
+impl<T: Config> Pallet<T> {
+	/// Weight of a swap through the pool, once its long-term orders are settled
+	pub fn pool_swap_weight() -> Weight {
+		(10_000 + T::DbWeight::get().reads_writes(14, 4))
+			.saturating_add(Self::price_oracle_update_weight())
+			.saturating_add(Self::swap_stats_weight())
+			.saturating_add(Self::price_limits_check_weight())
+	}
+}
+
 impl<T: Config> Pallet<T>
 where
 	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
 {
+	/// Swap `sender`'s asset_pair.0 through the pool, sending the asset_pair.1 received to
+	/// `recipient`. With `keep_alive` set, `sender`'s asset_pair.0 account is kept alive.
+	#[transactional]
 	pub fn process_swap(
 		sender: &T::AccountId,
+		recipient: &T::AccountId,
 		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
 		asset_a_amount: BalanceOf<T>,
-	) -> Result<(), DispatchError> {
-		// Get swap data
+		keep_alive: bool,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		Self::check_swaps_allowed(asset_pair)?;
+		Self::check_circuit_breaker(asset_pair)?;
+
+		// Get swap data, after the virtual trades of long-term orders
+		Self::settle_long_term_orders(asset_pair)?;
+		let price_before = Self::get_limit_price(asset_pair);
 		let pool_id = Self::get_pool_id(asset_pair);
 		let pool_liquidity = Self::get_pool_liquidity(asset_pair)?;
-		let swap_return = get_swap_return::<BalanceOf<T>, T>(asset_a_amount, pool_liquidity)?;
+		let (swap_return, fee) =
+			get_swap_return_and_fee::<BalanceOf<T>, T>(asset_a_amount, pool_liquidity)?;
+
+		// Record the price before the reserves change
+		Self::update_price_oracle(asset_pair);
 
 		// Send tokens into pool
-		T::Assets::transfer(asset_pair.0, &sender, &pool_id, asset_a_amount, false)?;
+		T::Assets::transfer(asset_pair.0, &sender, &pool_id, asset_a_amount, keep_alive)?;
 
 		// Send tokens to users
-		T::Assets::transfer(asset_pair.1, &pool_id, &sender, swap_return, false)?;
+		T::Assets::transfer(asset_pair.1, &pool_id, recipient, swap_return, false)?;
+
+		// The swap fee stays in the pool, a swap never lowers its constant product
+		let reserves = Self::get_pool_liquidity(asset_pair)?;
+		ensure!(
+			is_constant_product_maintained(pool_liquidity, reserves),
+			Error::<T>::ConstantProductDecreased
+		);
+		Self::record_swap_stats(asset_pair, asset_a_amount, fee);
 
-        Self::deposit_event(Event::TokensSwapped {
+		Self::deposit_event(Event::TokensSwapped {
 			pool_id,
-            asset_received: asset_pair.1,
-			swap_return,
+			who: sender.clone(),
+			recipient: recipient.clone(),
+			asset_in: asset_pair.0,
+			asset_out: asset_pair.1,
+			amount_in: asset_a_amount,
+			amount_out: swap_return,
+			fee,
+			reserves,
 		});
 
-		Ok(())
+		Self::check_price_limits(asset_pair, price_before)?;
+
+		Ok(swap_return)
 	}
 }
@@ -0,0 +1,76 @@
+use super::*;
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	/// Check the invariants of every registered pool:
+	/// - the pool's account and LP token are the ones derived from its asset pair
+	/// - a pool with LP tokens outstanding holds reserves of both of its assets
+	/// - the LP supply is backed by the reserves, its square never exceeds their product.
+	///   A pool starts at the square root of the product, which swap fees only ever grow.
+	/// - the LP tokens staked for liquidity mining add up and are held in escrow
+	/// - every conditional order and DCA schedule is indexed under its pool
+	///
+	/// That a swap never lowers the pool's constant product cannot be told from the state alone,
+	/// `process_swap` rejects any swap that would.
+	pub fn do_try_state() -> Result<(), &'static str> {
+		for (pool_id, asset_pair) in Pools::<T>::iter() {
+			ensure!(asset_pair == Self::sort_asset_pair(asset_pair), "Pool asset pair not sorted");
+			ensure!(Self::get_pool_id(asset_pair) == pool_id, "Pool account mismatch");
+
+			let lp_token_id = Self::get_lp_token_id(&pool_id);
+			ensure!(T::Assets::get_destroy_witness(&lp_token_id).is_some(), "Pool has no LP token");
+
+			// Pools left without liquidity are torn down once nothing else depends on them
+			let lp_token_supply = T::Assets::total_issuance(lp_token_id);
+			if lp_token_supply == 0u32.into() {
+				continue
+			}
+
+			let reserves =
+				Self::get_pool_liquidity(asset_pair).map_err(|_| "Pool reserves unavailable")?;
+			ensure!(
+				reserves.0 > 0u32.into() && reserves.1 > 0u32.into(),
+				"LP tokens outstanding on an empty pool"
+			);
+			ensure!(
+				is_constant_product_maintained((lp_token_supply, lp_token_supply), reserves),
+				"LP supply not backed by the pool's reserves"
+			);
+		}
+
+		for pool_id in PoolCreationDeposits::<T>::iter_keys() {
+			ensure!(Pools::<T>::contains_key(&pool_id), "Deposit held for an unregistered pool");
+		}
+
+		// Staked LP tokens are held in escrow
+		for (pool_id, farming_pool) in FarmingPools::<T>::iter() {
+			let staked = FarmingStakes::<T>::iter_prefix_values(&pool_id)
+				.fold(BalanceOf::<T>::default(), |total, stake| total.saturating_add(stake.staked));
+			ensure!(staked == farming_pool.total_staked, "Farming stakes do not add up");
+			ensure!(
+				T::Assets::balance(Self::get_lp_token_id(&pool_id), &Self::account_id()) >= staked,
+				"Staked LP tokens not held in escrow"
+			);
+		}
+
+		// Pool teardowns find the orders and schedules trading on the pool through its index
+		for (order_id, order) in ConditionalOrders::<T>::iter() {
+			let pool_id = Self::get_pool_id(order.action.asset_pair());
+			ensure!(
+				PoolConditionalOrders::<T>::contains_key(&pool_id, order_id),
+				"Conditional order not indexed by its pool"
+			);
+		}
+		for (schedule_id, schedule) in DcaSchedules::<T>::iter() {
+			let pool_id = Self::get_pool_id((schedule.asset_in, schedule.asset_out));
+			ensure!(
+				PoolDcaSchedules::<T>::contains_key(&pool_id, schedule_id),
+				"DCA schedule not indexed by its pool"
+			);
+		}
+
+		Ok(())
+	}
+}
@@ -2,18 +2,28 @@
 
 use crate::dex_math::*;
 use frame_support::{
-	dispatch::{Codec, Decode},
+	dispatch::{Codec, Decode, Dispatchable, GetDispatchInfo, PostDispatchInfo},
 	pallet_prelude::*,
-	sp_runtime::traits::{AccountIdConversion, AtLeast32Bit},
-	traits::tokens::{
-		currency::Currency,
-		fungibles::{Create, Inspect, Mutate, Transfer},
+	sp_runtime::{
+		traits::{AccountIdConversion, AtLeast32Bit, CheckedAdd, CheckedMul, Hash, Saturating},
+		FixedPointNumber, FixedU128, PerThing, Permill, SaturatedConversion,
 	},
-	Hashable, PalletId,
+	traits::{
+		tokens::{
+			currency::{Currency, ReservableCurrency},
+			fungibles::{
+				approvals::{Inspect as ApprovalInspect, Mutate as ApprovalMutate},
+				Create, Destroy, Inspect, Mutate, Transfer,
+			},
+			WithdrawConsequence,
+		},
+		Contains, ExistenceRequirement, UnixTime,
+	},
+	transactional, Hashable, PalletId,
 };
 use frame_system::pallet_prelude::*;
 pub use pallet::*;
-use scale_info::prelude::vec;
+use scale_info::prelude::{boxed::Box, vec, vec::Vec};
 
 #[cfg(test)]
 mod mock;
@@ -25,14 +35,342 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 mod dex_math;
+mod impl_approvals;
+mod impl_batch_auction;
+mod impl_batch_operations;
+mod impl_commit_reveal;
+mod impl_conditional_orders;
 mod impl_create_pool;
+mod impl_dca;
+mod impl_destroy_pool;
 mod impl_dex;
+mod impl_farming;
+mod impl_flash_loan;
+mod impl_limit_orders;
+mod impl_liquidity_positions;
+mod impl_long_term_orders;
 mod impl_lp_redemption;
+mod impl_order_routing;
+mod impl_pool_stats;
+mod impl_pool_status;
+mod impl_price_limits;
+mod impl_price_oracle;
 mod impl_provide_liquidity;
 mod impl_swap;
+#[cfg(any(feature = "try-runtime", test))]
+mod impl_try_state;
+mod traits;
+
+pub use traits::{DexPriceProvider, LiquidityHandler, SwapHandler};
 
 type AssetIdOf<T: Config> = <T::Assets as Inspect<T::AccountId>>::AssetId;
 type BalanceOf<T: Config> = <T::Assets as Inspect<T::AccountId>>::Balance;
+type NativeBalanceOf<T: Config> = <T::Balances as Currency<T::AccountId>>::Balance;
+
+/// Snapshot of a pool's cumulative prices, used to derive time weighted average prices
+///
+/// Prices are stored as the inner value of a `FixedU128` multiplied by the number of seconds
+/// they were in effect. The accumulators are allowed to wrap, only differences between two
+/// observations are meaningful.
+#[derive(
+	Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub struct PriceObservation {
+	/// Unix timestamp (in seconds) at which the observation was taken
+	pub timestamp: u64,
+	/// Cumulative price of the lower asset id, denominated in the higher asset id
+	pub price_a_cumulative: u128,
+	/// Cumulative price of the higher asset id, denominated in the lower asset id
+	pub price_b_cumulative: u128,
+}
+
+/// Identifier of an order held by the pallet
+pub type OrderId = u64;
+
+/// Order to swap `amount_in` of `asset_in` for `asset_out` once the pool offers `limit_price`
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct LimitOrder<AccountId, AssetId, Balance> {
+	/// Account the order was placed by, and which receives the swap return
+	pub owner: AccountId,
+	pub asset_in: AssetId,
+	pub asset_out: AssetId,
+	/// Amount of asset_in held in escrow until the order is filled or cancelled
+	pub amount_in: Balance,
+	/// Minimum average price of asset_in, denominated in asset_out, the order is filled at
+	pub limit_price: FixedU128,
+}
+
+/// Swap collected during a block and cleared with the pool's other swaps in `on_finalize`
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct BatchSwap<AccountId, AssetId, Balance> {
+	/// Account the swap was submitted by, and which receives the return
+	pub who: AccountId,
+	pub asset_in: AssetId,
+	pub asset_out: AssetId,
+	/// Amount of asset_in held in escrow until the batch is cleared
+	pub amount_in: Balance,
+	/// The swap is refunded instead if the clearing price returns less than this
+	pub min_amount_out: Balance,
+}
+
+/// Order selling `sell_rate` of `asset_in` for `asset_out` in every block up to `end_block`
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct LongTermOrder<AccountId, AssetId, Balance, BlockNumber> {
+	/// Account the order was placed by, and which receives the proceeds
+	pub owner: AccountId,
+	pub asset_in: AssetId,
+	pub asset_out: AssetId,
+	pub sell_rate: Balance,
+	/// Last block in which the order sells
+	pub end_block: BlockNumber,
+	/// Proceeds per unit of sell rate of the order's direction when the order was placed
+	pub proceeds_per_rate_start: FixedU128,
+}
+
+/// Virtual order flow of a pool, for the lower and the higher asset id of its pair
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct LongTermOrderPool<Balance, BlockNumber> {
+	/// Amounts sold in every block by the open orders of each direction
+	pub sell_rates: (Balance, Balance),
+	/// Cumulative proceeds per unit of sell rate of each direction
+	pub proceeds_per_rate: (FixedU128, FixedU128),
+	/// Last block whose virtual trades have been executed against the pool
+	pub last_settled_block: BlockNumber,
+}
+
+/// Price a conditional order is triggered at
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum PriceTrigger {
+	/// The price falls below the threshold, e.g. a stop-loss
+	Below(FixedU128),
+	/// The price rises above the threshold, e.g. a take-profit
+	Above(FixedU128),
+	/// The price leaves the range between `lower` and `upper`
+	OutsideRange { lower: FixedU128, upper: FixedU128 },
+}
+
+/// Pool price a conditional order is evaluated against
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum PriceSource {
+	/// The price given by the current pool reserves
+	Spot,
+	/// The time weighted average price over the last `window` seconds
+	Twap { window: u64 },
+}
+
+/// What a conditional order does once triggered
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum ConditionalAction<AssetId, Balance> {
+	/// Swap `amount_in` of `asset_in` for at least `min_amount_out` of `asset_out`.
+	/// Triggered by the price of asset_in denominated in asset_out.
+	Swap { asset_in: AssetId, asset_out: AssetId, amount_in: Balance, min_amount_out: Balance },
+	/// Redeem `lp_token_amount` of the asset_a/asset_b pool for at least `min_amounts`.
+	/// Triggered by the price of asset_a denominated in asset_b.
+	RedeemLiquidity {
+		asset_a: AssetId,
+		asset_b: AssetId,
+		lp_token_amount: Balance,
+		min_amounts: (Balance, Balance),
+	},
+}
+
+/// Operation executed as part of a `batch` call
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum DexOperation<AssetId, Balance> {
+	/// Swap `amount_in` of `asset_in` for at least `min_amount_out` of `asset_out`
+	Swap { asset_in: AssetId, asset_out: AssetId, amount_in: Balance, min_amount_out: Balance },
+	/// Provide `amount_a` of `asset_a` and at most `max_amount_b` of `asset_b` to their pool,
+	/// for at least `min_lp_tokens`
+	AddLiquidity {
+		asset_a: AssetId,
+		asset_b: AssetId,
+		amount_a: Balance,
+		max_amount_b: Balance,
+		min_lp_tokens: Balance,
+	},
+	/// Redeem `lp_token_amount` of the asset_a/asset_b pool for at least `min_amounts`
+	RemoveLiquidity {
+		asset_a: AssetId,
+		asset_b: AssetId,
+		lp_token_amount: Balance,
+		min_amounts: (Balance, Balance),
+	},
+}
+
+/// Order executed by the pallet once its pool's price meets `trigger`
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ConditionalOrder<AccountId, AssetId, Balance> {
+	/// Account the order was placed by, and which receives the proceeds
+	pub owner: AccountId,
+	/// The action's input is held in escrow until the order is executed or cancelled
+	pub action: ConditionalAction<AssetId, Balance>,
+	pub trigger: PriceTrigger,
+	pub price_source: PriceSource,
+}
+
+/// Identifier of a recurring swap schedule
+pub type ScheduleId = u64;
+
+/// Recurring swap of `amount_per_period` of `asset_in` for `asset_out` every `period` blocks
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct DcaSchedule<AccountId, AssetId, Balance, BlockNumber> {
+	/// Account the schedule was created by, and which receives the swap returns
+	pub owner: AccountId,
+	pub asset_in: AssetId,
+	pub asset_out: AssetId,
+	pub amount_per_period: Balance,
+	pub period: BlockNumber,
+	/// Number of swaps still to be attempted
+	pub remaining_periods: u32,
+	/// Largest accepted shortfall from the return at the pool's marginal price,
+	/// periods exceeding it are skipped
+	pub max_slippage: Permill,
+	/// Amount of asset_in held in escrow for the remaining and skipped periods
+	pub escrowed_amount: Balance,
+	/// Block at which the next swap is attempted
+	pub next_execution: BlockNumber,
+}
+
+/// Trading status of a pool, set by `AdminOrigin`
+#[derive(
+	Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum PoolStatus {
+	#[default]
+	Active,
+	/// Liquidity can be provided and redeemed, but not swapped
+	SwapsPaused,
+	/// Liquidity can only be redeemed
+	Paused,
+	/// The pool is being wound down, its liquidity is returned to the providers
+	Delisted,
+}
+
+/// How the asset list decides which assets pools can be created for
+#[derive(
+	Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum AssetListMode {
+	/// Pools can be created for any asset not on the list
+	#[default]
+	DenyList,
+	/// Pools can only be created for assets on the list
+	AllowList,
+}
+
+/// Price a pool's movement within a block is measured against
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum PriceReference {
+	/// The pool's price before its reserves first changed in the block
+	OpeningPrice,
+	/// Time weighted average price over the last `window` seconds
+	Twap { window: u64 },
+}
+
+/// Limits on how far swaps can move a pool's price, unset limits are not enforced
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PriceLimits {
+	/// Largest price change a single swap can cause
+	pub max_price_impact: Option<Permill>,
+	/// Largest distance from the reference price swaps can move the price to within a block,
+	/// a swap exceeding it trips the pool's circuit breaker
+	pub max_price_movement: Option<Permill>,
+	pub reference: PriceReference,
+}
+
+/// Price state of a pool with price limits during a block
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct BlockPriceState<BlockNumber> {
+	pub block: BlockNumber,
+	/// Price of the pool's lower asset id before its reserves first changed in the block
+	pub opening_price: FixedU128,
+	/// Whether swaps are halted for the rest of the block
+	pub circuit_breaker_tripped: bool,
+}
+
+/// Swap committed to by the hash of its parameters, executed once they are revealed
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct SwapCommitment<AccountId, Balance, BlockNumber> {
+	/// Account the commitment was made by, only it can reveal the swap
+	pub owner: AccountId,
+	/// Native deposit reserved until the swap is revealed, forfeited if it never is
+	pub deposit: Balance,
+	/// First block in which the swap can be revealed
+	pub reveal_from: BlockNumber,
+	/// Last block in which the swap can be revealed
+	pub reveal_until: BlockNumber,
+}
+
+/// Cumulative trading statistics of a pool, amounts are for the lower and the higher asset id
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PoolStats<Balance, BlockNumber> {
+	/// Amounts of each asset swapped into the pool
+	pub volumes: (Balance, Balance),
+	/// Swap fees kept by the pool, in the asset the swaps paid out
+	pub fees: (Balance, Balance),
+	pub trade_count: u64,
+	pub last_trade_block: BlockNumber,
+}
+
+/// Trading statistics of a pool during a single day
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct DailyPoolStats<Balance> {
+	/// Days since the unix epoch
+	pub day: u64,
+	pub volumes: (Balance, Balance),
+	pub fees: (Balance, Balance),
+	pub trade_count: u32,
+}
+
+/// Cost basis of the LP tokens an account received for providing liquidity to a pool.
+/// Amounts are for the lower and the higher asset id.
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct LiquidityPosition<Balance, BlockNumber> {
+	/// LP tokens received for deposits and not yet redeemed or transferred away
+	pub lp_tokens: Balance,
+	/// Amounts deposited for the tracked LP tokens, net of the share of them redeemed
+	pub net_deposits: (Balance, Balance),
+	/// Average liquidity per LP token of the pool at the time of the deposits
+	pub entry_liquidity_per_lp_token: FixedU128,
+	pub last_deposit_block: BlockNumber,
+}
+
+/// Current state of a liquidity position, amounts in the order of the requested assets
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct LiquidityPositionReport<Balance> {
+	/// Tracked LP tokens still owned by the account, staked ones included
+	pub lp_tokens: Balance,
+	/// Amounts the LP tokens would redeem at the current reserves
+	pub value: (Balance, Balance),
+	pub net_deposits: (Balance, Balance),
+	/// Part of the value grown from fees since the deposits
+	pub fees_earned: (Balance, Balance),
+	/// Loss of the value without fees compared to holding the deposited amounts
+	pub impermanent_loss: Permill,
+}
+
+/// Liquidity mining state of a pool
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct FarmingPool<Balance, BlockNumber> {
+	/// Rewards emitted to the pool's stakers in every block
+	pub reward_per_block: Balance,
+	/// LP tokens staked in the pool
+	pub total_staked: Balance,
+	/// Cumulative rewards per staked LP token
+	pub reward_per_share: FixedU128,
+	/// Last block whose rewards have been accumulated
+	pub last_reward_block: BlockNumber,
+}
+
+/// LP tokens an account staked in a pool, and the rewards they earned
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct FarmingStake<Balance> {
+	pub staked: Balance,
+	/// Rewards per staked LP token of the pool when the rewards were last accrued
+	pub reward_per_share_paid: FixedU128,
+	/// Rewards accrued and not yet claimed
+	pub pending_rewards: Balance,
+}
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -43,50 +381,656 @@ pub mod pallet {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
+		/// Origin allowed to manage pool settings
+		type AdminOrigin: EnsureOrigin<Self::Origin>;
+
+		/// Origin allowed to create pools, resolving to the account providing the initial
+		/// liquidity and the creation deposit
+		type CreatePoolOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
+
 		/// Asset type for this pallet
 		type Assets: Inspect<Self::AccountId>
 			+ Transfer<Self::AccountId>
 			+ Mutate<Self::AccountId>
-			+ Create<Self::AccountId>;
+			+ Create<Self::AccountId>
+			+ Destroy<Self::AccountId>
+			+ ApprovalMutate<Self::AccountId>;
 
 		/// Balances is the Currency type for this pallet
-		type Balances: Currency<Self::AccountId>;
+		type Balances: ReservableCurrency<Self::AccountId>;
 
 		/// PalletId for this pallet - used to manage the liquidity pools
 		#[pallet::constant]
 		type PalletId: Get<PalletId>;
+
+		/// Time provider used to timestamp price observations
+		type UnixTime: UnixTime;
+
+		/// Maximum number of price observations kept per pool for TWAP calculations
+		#[pallet::constant]
+		type MaxPriceObservations: Get<u32>;
+
+		/// The overarching call type, dispatched on behalf of flash loan borrowers
+		type Call: Parameter
+			+ Dispatchable<Origin = Self::Origin, PostInfo = PostDispatchInfo>
+			+ GetDispatchInfo;
+
+		/// Calls a borrower is allowed to make while holding a flash loan
+		type FlashLoanCallFilter: Contains<<Self as Config>::Call>;
+
+		/// Fee charged on the borrowed amount of a flash loan, paid to the liquidity providers
+		#[pallet::constant]
+		type FlashLoanFee: Get<Permill>;
+
+		/// Maximum number of open limit orders per pool and direction
+		#[pallet::constant]
+		type MaxOrdersPerPool: Get<u32>;
+
+		/// Fee taken from what a resting order receives when a swap is matched against it
+		#[pallet::constant]
+		type MakerFee: Get<Permill>;
+
+		/// Fee taken from what a swap receives from the resting orders it is matched against
+		#[pallet::constant]
+		type TakerFee: Get<Permill>;
+
+		/// Maximum number of scheduled swaps executed in a single block
+		#[pallet::constant]
+		type MaxDcaExecutionsPerBlock: Get<u32>;
+
+		/// Maximum number of conditional orders executed in a single block
+		#[pallet::constant]
+		type MaxConditionalExecutionsPerBlock: Get<u32>;
+
+		/// Maximum number of swaps collected per batch auction pool and block
+		#[pallet::constant]
+		type MaxBatchSwapsPerPool: Get<u32>;
+
+		/// Native deposit reserved for every swap commitment, forfeited if it is not revealed
+		#[pallet::constant]
+		type CommitmentDeposit: Get<NativeBalanceOf<Self>>;
+
+		/// Number of blocks after its commitment before a swap can be revealed
+		#[pallet::constant]
+		type MinRevealDelay: Get<Self::BlockNumber>;
+
+		/// Number of blocks after its commitment in which a swap can still be revealed
+		#[pallet::constant]
+		type RevealWindow: Get<Self::BlockNumber>;
+
+		/// Maximum number of swap commitments expiring in a single block
+		#[pallet::constant]
+		type MaxCommitmentExpiriesPerBlock: Get<u32>;
+
+		/// Maximum number of LP holders whose liquidity is force returned in a single call
+		#[pallet::constant]
+		type MaxForcedRedemptions: Get<u32>;
+
+		/// Native deposit reserved from the creator of a pool until the pool is destroyed
+		#[pallet::constant]
+		type PoolCreationDeposit: Get<NativeBalanceOf<Self>>;
+
+		/// Maximum number of operations in a single `batch` call
+		#[pallet::constant]
+		type MaxBatchOperations: Get<u32>;
+
+		/// Number of days of trading statistics kept per pool
+		#[pallet::constant]
+		type MaxStatsDays: Get<u32>;
+
+		/// Asset liquidity mining rewards are paid in
+		#[pallet::constant]
+		type FarmingRewardAsset: Get<AssetIdOf<Self>>;
 	}
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
 	pub struct Pallet<T>(_);
 
+	/// Price observations for each pool, oldest first
+	#[pallet::storage]
+	#[pallet::getter(fn price_observations)]
+	pub type PriceObservations<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<PriceObservation, T::MaxPriceObservations>,
+		ValueQuery,
+	>;
+
+	/// Trading statistics of each pool since its creation
+	#[pallet::storage]
+	#[pallet::getter(fn pool_stats)]
+	pub type PoolStatistics<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		PoolStats<BalanceOf<T>, T::BlockNumber>,
+		ValueQuery,
+	>;
+
+	/// Trading statistics of each pool for its last `MaxStatsDays` days with trades, oldest first
+	#[pallet::storage]
+	#[pallet::getter(fn daily_pool_stats)]
+	pub type DailyPoolStatistics<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<DailyPoolStats<BalanceOf<T>>, T::MaxStatsDays>,
+		ValueQuery,
+	>;
+
+	/// Liquidity positions of each pool's providers
+	#[pallet::storage]
+	#[pallet::getter(fn liquidity_position)]
+	pub type LiquidityPositions<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		LiquidityPosition<BalanceOf<T>, T::BlockNumber>,
+	>;
+
+	/// Liquidity mining state of each pool with a reward rate or staked LP tokens
+	#[pallet::storage]
+	#[pallet::getter(fn farming_pool)]
+	pub type FarmingPools<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		FarmingPool<BalanceOf<T>, T::BlockNumber>,
+		ValueQuery,
+	>;
+
+	/// LP tokens staked in each pool and the rewards earned, by staker
+	#[pallet::storage]
+	#[pallet::getter(fn farming_stake)]
+	pub type FarmingStakes<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		FarmingStake<BalanceOf<T>>,
+		ValueQuery,
+	>;
+
+	/// Identifier given to the next order placed
+	#[pallet::storage]
+	pub type NextOrderId<T: Config> = StorageValue<_, OrderId, ValueQuery>;
+
+	/// Open limit orders
+	#[pallet::storage]
+	#[pallet::getter(fn limit_orders)]
+	pub type LimitOrders<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		OrderId,
+		LimitOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+	>;
+
+	/// Open limit order ids for each pool and input asset, sorted by ascending limit price
+	#[pallet::storage]
+	#[pallet::getter(fn pool_limit_orders)]
+	pub type PoolLimitOrders<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		AssetIdOf<T>,
+		BoundedVec<(FixedU128, OrderId), T::MaxOrdersPerPool>,
+		ValueQuery,
+	>;
+
+	/// Last order book visited for limit order fills, the next block continues after it
+	#[pallet::storage]
+	pub type LimitOrderBookCursor<T: Config> = StorageValue<_, (T::AccountId, AssetIdOf<T>)>;
+
+	/// Pools whose swaps are collected and cleared in a batch at the end of each block.
+	/// Counted so the weight of clearing them can be reserved at the start of the block.
+	#[pallet::storage]
+	#[pallet::getter(fn is_batch_auction_pool)]
+	pub type BatchAuctionPools<T: Config> =
+		CountedStorageMap<_, Blake2_128Concat, T::AccountId, bool, ValueQuery>;
+
+	/// Swaps collected in the current block for each batch auction pool
+	#[pallet::storage]
+	#[pallet::getter(fn pending_batch_swaps)]
+	pub type PendingBatchSwaps<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<BatchSwap<T::AccountId, AssetIdOf<T>, BalanceOf<T>>, T::MaxBatchSwapsPerPool>,
+		ValueQuery,
+	>;
+
+	/// Open long-term orders
+	#[pallet::storage]
+	#[pallet::getter(fn long_term_orders)]
+	pub type LongTermOrders<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		OrderId,
+		LongTermOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>, T::BlockNumber>,
+	>;
+
+	/// Virtual order flow of each pool with open long-term orders
+	#[pallet::storage]
+	#[pallet::getter(fn long_term_order_pools)]
+	pub type LongTermOrderPools<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		LongTermOrderPool<BalanceOf<T>, T::BlockNumber>,
+	>;
+
+	/// Open long-term order ids of each pool, sorted by ascending end block
+	#[pallet::storage]
+	#[pallet::getter(fn long_term_order_expiries)]
+	pub type LongTermOrderExpiries<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<(T::BlockNumber, OrderId), T::MaxOrdersPerPool>,
+		ValueQuery,
+	>;
+
+	/// Open conditional orders
+	#[pallet::storage]
+	#[pallet::getter(fn conditional_orders)]
+	pub type ConditionalOrders<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		OrderId,
+		ConditionalOrder<T::AccountId, AssetIdOf<T>, BalanceOf<T>>,
+	>;
+
+	/// Open conditional orders of each pool
+	#[pallet::storage]
+	pub type PoolConditionalOrders<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, OrderId, ()>;
+
+	/// Last conditional order checked, the next block continues after it
+	#[pallet::storage]
+	pub type ConditionalOrderCursor<T: Config> = StorageValue<_, OrderId>;
+
+	/// Identifier given to the next DCA schedule
+	#[pallet::storage]
+	pub type NextScheduleId<T: Config> = StorageValue<_, ScheduleId, ValueQuery>;
+
+	/// Active DCA schedules
+	#[pallet::storage]
+	#[pallet::getter(fn dca_schedules)]
+	pub type DcaSchedules<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		ScheduleId,
+		DcaSchedule<T::AccountId, AssetIdOf<T>, BalanceOf<T>, T::BlockNumber>,
+	>;
+
+	/// Active DCA schedules of each pool
+	#[pallet::storage]
+	pub type PoolDcaSchedules<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, ScheduleId, ()>;
+
+	/// DCA schedules due for execution at each block
+	#[pallet::storage]
+	#[pallet::getter(fn dca_execution_queue)]
+	pub type DcaExecutionQueue<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<ScheduleId, T::MaxDcaExecutionsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// Whether the asset list allows or denies the assets on it
+	#[pallet::storage]
+	#[pallet::getter(fn asset_list_mode)]
+	pub type ActiveAssetListMode<T: Config> = StorageValue<_, AssetListMode, ValueQuery>;
+
+	/// Assets allowed or denied for pool creation, depending on the asset list mode
+	#[pallet::storage]
+	#[pallet::getter(fn is_asset_listed)]
+	pub type AssetList<T: Config> = StorageMap<_, Blake2_128Concat, AssetIdOf<T>, bool, ValueQuery>;
+
+	/// Sorted asset pair of each pool, keyed by the pool account
+	#[pallet::storage]
+	#[pallet::getter(fn pools)]
+	pub type Pools<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (AssetIdOf<T>, AssetIdOf<T>)>;
+
+	/// Creator and native deposit of each pool, returned when the pool is destroyed
+	#[pallet::storage]
+	#[pallet::getter(fn pool_creation_deposits)]
+	pub type PoolCreationDeposits<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::AccountId, NativeBalanceOf<T>)>;
+
+	/// Pools without liquidity waiting to be torn down, step by step when blocks have weight
+	/// to spare
+	#[pallet::storage]
+	#[pallet::getter(fn pool_teardowns)]
+	pub type PoolTeardowns<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (AssetIdOf<T>, AssetIdOf<T>)>;
+
+	/// Trading status of each pool, pools without an entry are active
+	#[pallet::storage]
+	#[pallet::getter(fn pool_status)]
+	pub type PoolStatuses<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, PoolStatus, ValueQuery>;
+
+	/// Price limits of each pool, pools without an entry are not limited
+	#[pallet::storage]
+	#[pallet::getter(fn pool_price_limits)]
+	pub type PoolPriceLimits<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, PriceLimits>;
+
+	/// Price state of each pool with price limits, as of the last block its reserves changed in
+	#[pallet::storage]
+	#[pallet::getter(fn block_price_states)]
+	pub type BlockPriceStates<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BlockPriceState<T::BlockNumber>>;
+
+	/// Unrevealed swap commitments, by the hash of the swap's parameters
+	#[pallet::storage]
+	#[pallet::getter(fn swap_commitments)]
+	pub type SwapCommitments<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::Hash,
+		SwapCommitment<T::AccountId, NativeBalanceOf<T>, T::BlockNumber>,
+	>;
+
+	/// Swap commitments whose reveal window closes before each block
+	#[pallet::storage]
+	#[pallet::getter(fn commitment_expiries)]
+	pub type CommitmentExpiries<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<T::Hash, T::MaxCommitmentExpiriesPerBlock>,
+		ValueQuery,
+	>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Pools created at genesis: their creator, asset pair and the amounts the creator
+		/// deposits
+		pub pools: Vec<(T::AccountId, u32, u32, u128, u128)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { pools: vec![] }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T>
+	where
+		<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+	{
+		fn build(&self) {
+			for (creator, asset_a, asset_b, asset_a_amount, asset_b_amount) in self.pools.iter() {
+				Pallet::<T>::create_new_pool(
+					creator,
+					((*asset_a).into(), (*asset_b).into()),
+					((*asset_a_amount).saturated_into(), (*asset_b_amount).saturated_into()),
+					false,
+				)
+				.expect("genesis pools are created from the creator's balances");
+			}
+		}
+	}
+
 	// Pallets use events to inform users when important changes are made.
 	// https://docs.substrate.io/v3/runtime/events-and-errors
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// A new liquidity pool has been successfully created
-		NewPoolCreated { pool_id: T::AccountId },
-		/// Liquidity has been successfully added to the pool
+		/// A new liquidity pool has been successfully created.
+		/// Events changing a pool's reserves carry them as they are after the change,
+		/// in the order of the event's assets.
+		NewPoolCreated {
+			pool_id: T::AccountId,
+			creator: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			asset_amounts: (BalanceOf<T>, BalanceOf<T>),
+			lp_token_id: AssetIdOf<T>,
+			earned_lp_tokens: BalanceOf<T>,
+			reserves: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// Liquidity has been successfully added to the pool, the LP tokens minted to `who`
 		LiquidityProvided {
 			pool_id: T::AccountId,
+			who: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			asset_amounts: (BalanceOf<T>, BalanceOf<T>),
 			lp_token_id: AssetIdOf<T>,
 			earned_lp_tokens: BalanceOf<T>,
+			reserves: (BalanceOf<T>, BalanceOf<T>),
 		},
-		/// Tokens have been successfully swapped
+		/// Tokens have been successfully swapped. The fee is paid in `asset_out` and stays in
+		/// the pool.
 		TokensSwapped {
 			pool_id: T::AccountId,
-			asset_received: AssetIdOf<T>,
-			swap_return: BalanceOf<T>,
+			who: T::AccountId,
+			recipient: T::AccountId,
+			asset_in: AssetIdOf<T>,
+			asset_out: AssetIdOf<T>,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+			fee: BalanceOf<T>,
+			reserves: (BalanceOf<T>, BalanceOf<T>),
 		},
 		/// LP tokens have been successfully redeemed
 		/// to acquire assets back from the pool
 		LiquidityRedeemed {
+			pool_id: T::AccountId,
+			who: T::AccountId,
+			recipient: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			lp_token_id: AssetIdOf<T>,
+			lp_token_amount: BalanceOf<T>,
+			redeemed_token_amounts: (BalanceOf<T>, BalanceOf<T>),
+			reserves: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// A flash loan has been taken out and repaid within a single call
+		FlashLoanRepaid {
+			pool_id: T::AccountId,
+			borrower: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			amount: BalanceOf<T>,
+			fee: BalanceOf<T>,
+			reserves: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// A limit order has been placed and its input amount escrowed
+		LimitOrderPlaced {
+			order_id: OrderId,
+			owner: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			amount_in: BalanceOf<T>,
+			limit_price: FixedU128,
+		},
+		/// A limit order has been cancelled and its input amount returned
+		LimitOrderCancelled { order_id: OrderId, owner: T::AccountId },
+		/// A limit order has been filled against its pool
+		LimitOrderFilled {
+			order_id: OrderId,
+			owner: T::AccountId,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		/// A swap has been matched against a resting limit order, fully or partially
+		/// Both fees go to the pool, whose reserves are in the order the taker swapped.
+		LimitOrderMatched {
+			order_id: OrderId,
+			maker: T::AccountId,
+			taker: T::AccountId,
+			amount_sold: BalanceOf<T>,
+			amount_paid: BalanceOf<T>,
+			maker_fee: BalanceOf<T>,
+			taker_fee: BalanceOf<T>,
+			pool_id: T::AccountId,
+			reserves: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// Batch auction mode has been turned on or off for a pool
+		BatchAuctionModeSet { pool_id: T::AccountId, enabled: bool },
+		/// A swap has been collected for the pool's batch auction and its input escrowed
+		BatchSwapSubmitted {
+			pool_id: T::AccountId,
+			who: T::AccountId,
+			asset_in: AssetIdOf<T>,
+			amount_in: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+		},
+		/// A batched swap has been filled at the clearing price
+		BatchSwapFilled {
+			pool_id: T::AccountId,
+			who: T::AccountId,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		/// A batched swap whose minimum return was not met has been refunded
+		BatchSwapRefunded { pool_id: T::AccountId, who: T::AccountId, amount_in: BalanceOf<T> },
+		/// A pool's batch has been cleared. Inputs and outputs are totals per asset of the pair.
+		BatchAuctionCleared {
 			pool_id: T::AccountId,
 			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			amounts_in: (BalanceOf<T>, BalanceOf<T>),
+			amounts_out: (BalanceOf<T>, BalanceOf<T>),
+			reserves: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// A long-term order has been placed and its input escrowed
+		LongTermOrderPlaced {
+			order_id: OrderId,
+			owner: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			sell_rate: BalanceOf<T>,
+			end_block: T::BlockNumber,
+		},
+		/// A long-term order has sold its full amount and its proceeds have been paid out
+		LongTermOrderCompleted { order_id: OrderId, owner: T::AccountId, amount_out: BalanceOf<T> },
+		/// A long-term order has been cancelled, its unsold input and proceeds paid out
+		LongTermOrderCancelled {
+			order_id: OrderId,
+			owner: T::AccountId,
+			refunded: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		/// The virtual trades of a pool's long-term orders have been executed up to this block
+		LongTermOrdersSettled {
+			pool_id: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			reserves: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// A conditional order has been placed and its input escrowed
+		ConditionalOrderPlaced { order_id: OrderId, owner: T::AccountId, trigger: PriceTrigger },
+		/// A conditional order has been cancelled and its input returned
+		ConditionalOrderCancelled { order_id: OrderId, owner: T::AccountId },
+		/// A conditional swap has been triggered at `price` and executed
+		ConditionalSwapExecuted {
+			order_id: OrderId,
+			owner: T::AccountId,
+			price: FixedU128,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		/// A conditional LP redemption has been triggered at `price` and executed
+		ConditionalRedemptionExecuted {
+			order_id: OrderId,
+			owner: T::AccountId,
+			price: FixedU128,
+			lp_token_amount: BalanceOf<T>,
+			redeemed_token_amounts: (BalanceOf<T>, BalanceOf<T>),
+		},
+		/// A recurring swap has been scheduled and its total amount escrowed
+		DcaScheduled {
+			schedule_id: ScheduleId,
+			owner: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			amount_per_period: BalanceOf<T>,
+			period: T::BlockNumber,
+			count: u32,
+		},
+		/// A period of a recurring swap has been executed
+		DcaExecuted {
+			schedule_id: ScheduleId,
+			owner: T::AccountId,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		/// A period of a recurring swap has been skipped, its amount stays escrowed
+		DcaExecutionSkipped { schedule_id: ScheduleId, owner: T::AccountId },
+		/// A recurring swap has run all its periods, the amount of skipped periods is returned
+		DcaCompleted { schedule_id: ScheduleId, owner: T::AccountId, refunded: BalanceOf<T> },
+		/// A recurring swap has been cancelled and its escrowed amount returned
+		DcaCancelled { schedule_id: ScheduleId, owner: T::AccountId, refunded: BalanceOf<T> },
+		/// The asset list now allows or denies the assets on it
+		AssetListModeSet { mode: AssetListMode },
+		/// An asset has been added to or removed from the asset list
+		AssetListUpdated { asset_id: AssetIdOf<T>, listed: bool },
+		/// A pool without liquidity has been queued for teardown
+		PoolTeardownScheduled { pool_id: T::AccountId, asset_pair: (AssetIdOf<T>, AssetIdOf<T>) },
+		/// A pool without liquidity has been torn down, the pair can be created again
+		PoolDestroyed { pool_id: T::AccountId, asset_pair: (AssetIdOf<T>, AssetIdOf<T>) },
+		/// The trading status of a pool has been changed
+		PoolStatusSet { pool_id: T::AccountId, status: PoolStatus },
+		/// The liquidity of a holder of a delisted pool's LP tokens has been returned to them
+		LiquidityForceReturned {
+			pool_id: T::AccountId,
+			who: T::AccountId,
+			lp_token_amount: BalanceOf<T>,
 			redeemed_token_amounts: (BalanceOf<T>, BalanceOf<T>),
 		},
+		/// The price limits of a pool have been changed
+		PriceLimitsSet { pool_id: T::AccountId, limits: Option<PriceLimits> },
+		/// A swap would have moved the pool's price too far within the block, swaps are halted
+		/// for the rest of the block at `price`
+		CircuitBreakerTripped { pool_id: T::AccountId, price: FixedU128 },
+		/// A swap has been committed to, it can be revealed from `reveal_from` to `reveal_until`
+		SwapCommitted {
+			commitment: T::Hash,
+			owner: T::AccountId,
+			reveal_from: T::BlockNumber,
+			reveal_until: T::BlockNumber,
+		},
+		/// A committed swap has been revealed and executed
+		SwapRevealed {
+			commitment: T::Hash,
+			owner: T::AccountId,
+			asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		/// A swap commitment was not revealed in time and its deposit has been forfeited
+		SwapCommitmentExpired {
+			commitment: T::Hash,
+			owner: T::AccountId,
+			forfeited: NativeBalanceOf<T>,
+		},
+		/// A batch of operations has been executed, `failed` lists the indices of the
+		/// operations skipped in a best-effort batch
+		BatchExecuted { who: T::AccountId, operations: u32, failed: Vec<u32> },
+		/// The reward rate of a pool's liquidity mining has been set
+		FarmingRewardRateSet { pool_id: T::AccountId, reward_per_block: BalanceOf<T> },
+		/// Liquidity mining rewards have been added to the rewards account
+		FarmingRewardsFunded { who: T::AccountId, amount: BalanceOf<T> },
+		/// LP tokens have been staked for liquidity mining rewards
+		LpTokensStaked { pool_id: T::AccountId, who: T::AccountId, amount: BalanceOf<T> },
+		/// Staked LP tokens have been returned to their owner
+		LpTokensUnstaked { pool_id: T::AccountId, who: T::AccountId, amount: BalanceOf<T> },
+		/// Liquidity mining rewards have been paid out
+		FarmingRewardsClaimed { pool_id: T::AccountId, who: T::AccountId, amount: BalanceOf<T> },
+		/// Liquidity mining rewards have been deposited into the pool and the LP tokens staked
+		FarmingRewardsCompounded {
+			pool_id: T::AccountId,
+			who: T::AccountId,
+			rewards: BalanceOf<T>,
+			lp_tokens: BalanceOf<T>,
+		},
 	}
 
 	// Errors inform users that something went wrong.
@@ -100,6 +1044,118 @@ pub mod pallet {
 		MathOverflow,
 		/// The user does not have enough LP tokens for the redemption request
 		NotEnoughLPTokens,
+		/// The amounts involved moved beyond the accepted slippage limits
+		SlippageExceeded,
+		/// The pool does not hold enough liquidity for the request
+		NotEnoughLiquidity,
+		/// The call is not allowed to be dispatched while holding a flash loan
+		CallNotAllowed,
+		/// The flash loan and its fee were not returned to the pool
+		FlashLoanNotRepaid,
+		/// The order amount or price must be greater than zero
+		InvalidOrderParameters,
+		/// The pool already holds the maximum number of open orders
+		TooManyOrders,
+		/// No open order exists with the given id
+		OrderNotFound,
+		/// Only the owner of an order can cancel it
+		NotOrderOwner,
+		/// The amount, period and count of a schedule must be greater than zero
+		InvalidScheduleParameters,
+		/// No execution slot is available for the schedule in the coming blocks
+		TooManyScheduledSwaps,
+		/// No active schedule exists with the given id
+		ScheduleNotFound,
+		/// Only the owner of a schedule can cancel it
+		NotScheduleOwner,
+		/// The pool only accepts swaps through its batch auction
+		PoolInBatchAuctionMode,
+		/// The pool does not run batch auctions
+		PoolNotInBatchAuctionMode,
+		/// The pool's batch for this block is full
+		TooManyBatchSwaps,
+		/// A swap commitment with the same hash already exists
+		DuplicateCommitment,
+		/// The maximum number of swap commitments already expire in the same block
+		TooManyCommitments,
+		/// No swap commitment matches the revealed parameters
+		CommitmentNotFound,
+		/// The swap's reveal window has not opened yet
+		RevealTooEarly,
+		/// The swap's reveal window has closed
+		RevealTooLate,
+		/// The swap would change the pool's price by more than the pool allows
+		PriceImpactTooHigh,
+		/// The swap would move the pool's price too far from its reference price in this block
+		PriceMovementLimitExceeded,
+		/// The pool's swaps are halted for the rest of the block
+		CircuitBreakerTripped,
+		/// Pools cannot be created for the asset
+		AssetNotAllowed,
+		/// No pool exists for the asset pair
+		PoolNotFound,
+		/// The pool still has liquidity, open long-term orders or pending batch swaps
+		PoolNotEmpty,
+		/// The pool's swaps have been paused
+		PoolSwapsPaused,
+		/// The pool has been paused or delisted, its liquidity can only be redeemed
+		PoolPaused,
+		/// The pool has been delisted, its status can no longer change
+		PoolDelisted,
+		/// The pool's liquidity can only be force returned once it has been delisted
+		PoolNotDelisted,
+		/// A swap would have lowered the pool's constant product
+		ConstantProductDecreased,
+		/// The tokens are frozen and cannot be transferred
+		BalanceFrozen,
+		/// The transfer would take the account below the asset's minimum balance
+		WouldKillAccount,
+		/// The owner has not approved the sender to spend enough of the asset
+		InsufficientAllowance,
+		/// The account has not staked enough LP tokens in the pool
+		NotEnoughStakedLpTokens,
+		/// The account has no liquidity mining rewards to claim in the pool
+		NoFarmingRewards,
+		/// The rewards account does not hold enough to pay out the rewards
+		NotEnoughFarmingRewardFunds,
+		/// Rewards can only be compounded into a pool of the reward asset
+		RewardAssetNotInPool,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T>
+	where
+		<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+	{
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			Self::execute_dca_schedules(n)
+				.saturating_add(Self::expire_swap_commitments(n))
+				.saturating_add(Self::batch_auctions_clearing_weight())
+		}
+
+		fn on_finalize(_n: T::BlockNumber) {
+			Self::clear_batch_auctions();
+
+			// This version of try-runtime has no per-block state check, so every block it
+			// executes checks the pool invariants once it is done with the pools
+			#[cfg(feature = "try-runtime")]
+			Self::do_try_state().expect("Pool invariants violated");
+		}
+
+		fn on_idle(_n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let mut consumed_weight = Self::match_limit_orders(remaining_weight);
+			consumed_weight = consumed_weight.saturating_add(Self::execute_conditional_orders(
+				remaining_weight.saturating_sub(consumed_weight),
+			));
+			consumed_weight.saturating_add(Self::process_pool_teardowns(
+				remaining_weight.saturating_sub(consumed_weight),
+			))
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			Self::do_try_state()
+		}
 	}
 
 	#[pallet::call]
@@ -109,6 +1165,7 @@ pub mod pallet {
 		<T::Assets as Inspect<T::AccountId>>::AssetId: Codec,
 	{
         /// Create a new DEX pool for a given asset pair
+        /// With `keep_alive` set, the deposit must leave the creator's asset accounts alive
 		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1,1))]
 		pub fn create_pool(
 			origin: OriginFor<T>,
@@ -116,35 +1173,46 @@ pub mod pallet {
 			asset_b: AssetIdOf<T>,
 			asset_a_amount: BalanceOf<T>,
 			asset_b_amount: BalanceOf<T>,
+			keep_alive: bool,
 		) -> DispatchResult {
-			// check if message is signed
-			let sender = ensure_signed(origin)?;
+			// check if message is sent by an origin allowed to create pools
+			let sender = T::CreatePoolOrigin::ensure_origin(origin)?;
 
 			// Check the user is able to make the required deposit
 			Self::check_deposit_is_valid(
 				&sender,
 				(asset_a, asset_b),
 				(asset_a_amount, asset_b_amount),
+				keep_alive,
 			)?;
+			Self::check_pool_assets_allowed((asset_a, asset_b))?;
 
 			// Create the new liquidity pool
-			Self::create_new_pool(&sender, (asset_a, asset_b), (asset_a_amount, asset_b_amount))?;
+			Self::create_new_pool(
+				&sender,
+				(asset_a, asset_b),
+				(asset_a_amount, asset_b_amount),
+				keep_alive,
+			)?;
 
 			Ok(())
 		}
 
         /// Provide further liquidity to an already existing pool
+        /// With `keep_alive` set, the deposit must leave the provider's asset accounts alive
 		#[pallet::weight(5_000 + T::DbWeight::get().reads_writes(1,1))]
 		pub fn provide_liquidity(
 			origin: OriginFor<T>,
 			asset_a: AssetIdOf<T>,
 			asset_b: AssetIdOf<T>,
 			asset_a_amount: BalanceOf<T>,
+			keep_alive: bool,
 		) -> DispatchResult {
 			// check if message is signed
 			let sender = ensure_signed(origin)?;
 
-			// Get pool data
+			// Get pool data, after the virtual trades of long-term orders
+			Self::settle_long_term_orders((asset_a, asset_b))?;
 			let pool_liquidity = Self::get_pool_liquidity((asset_a, asset_b))?;
 			let asset_b_amount = Self::derive_second_asset_amount(pool_liquidity, asset_a_amount)?;
 
@@ -153,14 +1221,18 @@ pub mod pallet {
 				&sender,
 				(asset_a, asset_b),
 				(asset_a_amount, asset_b_amount),
+				keep_alive,
 			)?;
+			Self::check_liquidity_provision_allowed((asset_a, asset_b))?;
 
 			// Handle the deposit to the liquidity pool
 			Self::process_liquidity_pool_deposit(
+				&sender,
 				&sender,
 				(asset_a, asset_b),
 				(asset_a_amount, asset_b_amount),
 				pool_liquidity.0,
+				keep_alive,
 			)?;
 
 			Ok(())
@@ -168,12 +1240,25 @@ pub mod pallet {
 
         /// Swap asset_a for another asset_b
         /// The pool must already exist and have liquidity available
-		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1,1))]
+        /// The swap is split between the pool and resting limit orders, whichever is cheaper
+        /// A swap moving the price beyond the pool's per-block limit is reverted and trips the
+        /// pool's circuit breaker, halting its swaps for the rest of the block. The call itself
+        /// succeeds so that the trip is kept, it is reported by a `CircuitBreakerTripped` event
+        /// With `keep_alive` set, the swap must leave the sender's asset_a account alive
+        /// The asset_b received is sent to `recipient`
+		#[pallet::weight(
+			(10_000 + T::DbWeight::get().reads_writes(3, 2)).saturating_add(
+				(T::MaxOrdersPerPool::get() as Weight)
+					.saturating_mul(10_000 + T::DbWeight::get().reads_writes(6, 6))
+			)
+		)]
 		pub fn swap(
 			origin: OriginFor<T>,
 			asset_a: AssetIdOf<T>,
 			asset_b: AssetIdOf<T>,
 			asset_a_amount: BalanceOf<T>,
+			keep_alive: bool,
+			recipient: T::AccountId,
 		) -> DispatchResult {
 			// check if message is signed
 			let sender = ensure_signed(origin)?;
@@ -183,21 +1268,33 @@ pub mod pallet {
 				&sender,
 				(asset_a, asset_b),
 				(asset_a_amount, 0u32.into()),
+				keep_alive,
 			)?;
+			Self::check_direct_swaps_allowed((asset_a, asset_b))?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
 
-			// Handle the swap
-			Self::process_swap(&sender, (asset_a, asset_b), asset_a_amount)?;
+			// Handle the swap, a swap tripping the pool's circuit breaker is reverted
+			Self::process_swap_with_circuit_breaker(
+				&sender,
+				&recipient,
+				(asset_a, asset_b),
+				asset_a_amount,
+				0u32.into(),
+				keep_alive,
+			)?;
 
 			Ok(())
 		}
 
         /// Redeem LP tokens in exchange for their underlying liquidity in the pool
+        /// The redeemed liquidity is sent to `recipient`
 		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1,1))]
 		pub fn redeem_lp_tokens(
 			origin: OriginFor<T>,
 			asset_a: AssetIdOf<T>,
 			asset_b: AssetIdOf<T>,
 			lp_token_amount: BalanceOf<T>,
+			recipient: T::AccountId,
 		) -> DispatchResult {
 			// check if message is signed
 			let sender = ensure_signed(origin)?;
@@ -217,12 +1314,669 @@ pub mod pallet {
 			// Redeem the users LP tokens
 			Self::handle_lp_token_redemption(
 				&sender,
+				&recipient,
 				pool_id,
 				lp_token_id,
 				lp_token_amount,
 				(asset_a, asset_b),
 			)?;
 
+			Ok(())
+		}
+
+        /// Borrow `amount` of asset_a from the asset_a/asset_b pool for the duration of `call`.
+        /// The amount plus the flash loan fee is taken back from the borrower once `call`
+        /// completes, and everything is reverted if the pool is left worse off.
+		#[pallet::weight({
+			let dispatch_info = call.get_dispatch_info();
+			dispatch_info
+				.weight
+				.saturating_add(10_000)
+				.saturating_add(T::DbWeight::get().reads_writes(2, 2))
+		})]
+		pub fn flash_loan(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			amount: BalanceOf<T>,
+			call: Box<<T as Config>::Call>,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the loan can be made
+			Self::check_flash_loan_is_valid((asset_a, asset_b), amount, &call)?;
+
+			// Lend, dispatch and get repaid
+			Self::process_flash_loan(&sender, (asset_a, asset_b), amount, *call)?;
+
+			Ok(())
+		}
+
+        /// Place an order to swap `amount_in` of asset_a for asset_b once the pool pays at least
+        /// `limit_price` of asset_b per asset_a. The amount is held by the pallet until the order
+        /// is filled or cancelled.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 4))]
+		pub fn place_limit_order(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			amount_in: BalanceOf<T>,
+			limit_price: FixedU128,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the user is able to place the order
+			Self::check_deposit_is_valid(
+				&sender,
+				(asset_a, asset_b),
+				(amount_in, 0u32.into()),
+				false,
+			)?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
+
+			// Escrow the funds and record the order
+			Self::create_limit_order(&sender, (asset_a, asset_b), amount_in, limit_price)?;
+
+			Ok(())
+		}
+
+        /// Cancel an open limit order and return its escrowed funds
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn cancel_limit_order(origin: OriginFor<T>, order_id: OrderId) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Remove the order and refund the user
+			Self::remove_limit_order(&sender, order_id)?;
+
+			Ok(())
+		}
+
+        /// Turn batch auction mode on or off for the asset_a/asset_b pool. In batch auction mode
+        /// swaps are submitted through `submit_batch_swap` instead of `swap`.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_batch_auction_mode(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			enabled: bool,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let pool_id = Self::get_pool_id((asset_a, asset_b));
+			ensure!(Pools::<T>::contains_key(&pool_id), Error::<T>::PoolNotFound);
+			if enabled {
+				BatchAuctionPools::<T>::insert(&pool_id, true);
+			} else {
+				BatchAuctionPools::<T>::remove(&pool_id);
+			}
+			Self::deposit_event(Event::BatchAuctionModeSet { pool_id, enabled });
+
+			Ok(())
+		}
+
+        /// Set whether the asset list allows or denies pool creation for the assets on it
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_asset_list_mode(origin: OriginFor<T>, mode: AssetListMode) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ActiveAssetListMode::<T>::put(mode);
+			Self::deposit_event(Event::AssetListModeSet { mode });
+
+			Ok(())
+		}
+
+        /// Add an asset to or remove it from the asset list. Existing pools are not affected.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_asset_listed(
+			origin: OriginFor<T>,
+			asset_id: AssetIdOf<T>,
+			listed: bool,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			if listed {
+				AssetList::<T>::insert(asset_id, true);
+			} else {
+				AssetList::<T>::remove(asset_id);
+			}
+			Self::deposit_event(Event::AssetListUpdated { asset_id, listed });
+
+			Ok(())
+		}
+
+        /// Queue the asset_a/asset_b pool for teardown once it no longer holds liquidity. Pools
+        /// are queued automatically when their last LP tokens are redeemed, unless something else
+        /// still depends on them at that point. LP tokens held by the pool account are locked
+        /// and do not keep it alive. The teardown runs in steps when blocks have weight to spare:
+        /// limit orders, conditional orders and DCA schedules trading on the pool are cancelled
+        /// and refunded, and farming rewards are paid out, before the pool itself is destroyed.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(6, 1))]
+		pub fn destroy_pool(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Self::schedule_pool_teardown((asset_a, asset_b))?;
+
+			Ok(())
+		}
+
+        /// Set the trading status of the asset_a/asset_b pool. Delisting is final.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_pool_status(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			status: PoolStatus,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Self::set_pool_status_for((asset_a, asset_b), status)?;
+
+			Ok(())
+		}
+
+        /// Return the liquidity of the given LP holders of the delisted asset_a/asset_b pool,
+        /// redeeming all of their LP tokens on their behalf
+		#[pallet::weight(
+			10_000 + (holders.len() as Weight)
+				.saturating_mul(10_000 + T::DbWeight::get().reads_writes(7, 6))
+		)]
+		pub fn force_return_liquidity(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			holders: BoundedVec<T::AccountId, T::MaxForcedRedemptions>,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Self::force_return_liquidity_to((asset_a, asset_b), &holders)?;
+
+			Ok(())
+		}
+
+        /// Set the limits on how far swaps can move the asset_a/asset_b pool's price,
+        /// `None` removes them
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_price_limits(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			limits: Option<PriceLimits>,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let pool_id = Self::get_pool_id((asset_a, asset_b));
+			ensure!(Pools::<T>::contains_key(&pool_id), Error::<T>::PoolNotFound);
+			PoolPriceLimits::<T>::set(&pool_id, limits);
+			Self::deposit_event(Event::PriceLimitsSet { pool_id, limits });
+
+			Ok(())
+		}
+
+        /// Submit a swap of `amount_in` of asset_a for asset_b to the pool's batch auction.
+        /// All swaps submitted to the pool in this block are cleared at the same price at the
+        /// end of the block, swaps which would return less than `min_amount_out` are refunded.
+        /// Clearing the batch is weighed in `on_initialize`.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 4))]
+		pub fn submit_batch_swap(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			amount_in: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the user is able to make the swap
+			Self::check_deposit_is_valid(
+				&sender,
+				(asset_a, asset_b),
+				(amount_in, 0u32.into()),
+				false,
+			)?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
+
+			// Escrow the input until the end of the block
+			Self::collect_batch_swap(&sender, (asset_a, asset_b), amount_in, min_amount_out)?;
+
+			Ok(())
+		}
+
+        /// Sell `amount_in` of asset_a for asset_b in equal parts over the next `duration`
+        /// blocks. The parts are traded virtually against the pool and settled whenever the pool
+        /// is next used. Any remainder of `amount_in` not divisible by `duration` is not sold.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 6))]
+		pub fn place_long_term_order(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			amount_in: BalanceOf<T>,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the user is able to place the order
+			Self::check_deposit_is_valid(
+				&sender,
+				(asset_a, asset_b),
+				(amount_in, 0u32.into()),
+				false,
+			)?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
+
+			// Escrow the funds and start selling
+			Self::create_long_term_order(&sender, (asset_a, asset_b), amount_in, duration)?;
+
+			Ok(())
+		}
+
+        /// Cancel a long-term order, returning its unsold input and its proceeds so far
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 6))]
+		pub fn cancel_long_term_order(origin: OriginFor<T>, order_id: OrderId) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Settle the order and refund the user
+			Self::remove_long_term_order(&sender, order_id)?;
+
+			Ok(())
+		}
+
+        /// Place an order executing `action` once the pool price, read from `price_source`,
+        /// meets `trigger`. The action's input is held by the pallet until the order is executed
+        /// or cancelled.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 5))]
+		pub fn place_conditional_order(
+			origin: OriginFor<T>,
+			action: ConditionalAction<AssetIdOf<T>, BalanceOf<T>>,
+			trigger: PriceTrigger,
+			price_source: PriceSource,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the user holds the action's input
+			Self::check_conditional_order_is_valid(&sender, &action)?;
+
+			// Escrow the input and record the order
+			Self::create_conditional_order(&sender, action, trigger, price_source)?;
+
+			Ok(())
+		}
+
+        /// Cancel an open conditional order and return its escrowed input
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 4))]
+		pub fn cancel_conditional_order(origin: OriginFor<T>, order_id: OrderId) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Remove the order and refund the user
+			Self::remove_conditional_order(&sender, order_id)?;
+
+			Ok(())
+		}
+
+        /// Swap `amount_per_period` of asset_a for asset_b every `period` blocks, `count` times.
+        /// The total amount is held by the pallet up front. A period whose return falls short of
+        /// the pool's marginal price by more than `max_slippage` is skipped, and its amount
+        /// returned once the schedule completes.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 5))]
+		pub fn schedule_dca(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			amount_per_period: BalanceOf<T>,
+			period: T::BlockNumber,
+			count: u32,
+			max_slippage: Permill,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the user is able to fund every period
+			let total_amount = amount_per_period
+				.checked_mul(&count.into())
+				.ok_or(Error::<T>::MathOverflow)?;
+			Self::check_deposit_is_valid(
+				&sender,
+				(asset_a, asset_b),
+				(total_amount, 0u32.into()),
+				false,
+			)?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
+
+			// Escrow the funds and queue the first period
+			Self::create_dca_schedule(
+				&sender,
+				(asset_a, asset_b),
+				amount_per_period,
+				period,
+				count,
+				max_slippage,
+			)?;
+
+			Ok(())
+		}
+
+        /// Cancel a DCA schedule and return the funds still held for it
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 4))]
+		pub fn cancel_dca(origin: OriginFor<T>, schedule_id: ScheduleId) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Remove the schedule and refund the user
+			Self::remove_dca_schedule(&sender, schedule_id)?;
+
+			Ok(())
+		}
+
+        /// Commit to a swap by the hash of its parameters, see `swap_commitment_hash`. The swap
+        /// is executed when revealed through `reveal_swap` within the reveal window, otherwise
+        /// the commitment's deposit is forfeited.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn commit_swap(origin: OriginFor<T>, commitment: T::Hash) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Reserve the deposit and record the commitment
+			Self::create_swap_commitment(&sender, commitment)?;
+
+			Ok(())
+		}
+
+        /// Reveal the parameters of a committed swap and execute it. Fails if the swap would
+        /// return less than `min_amount_out`. A swap tripping the pool's circuit breaker is
+        /// reverted, the commitment is released all the same.
+		#[pallet::weight(
+			(20_000 + T::DbWeight::get().reads_writes(8, 8)).saturating_add(
+				(T::MaxOrdersPerPool::get() as Weight)
+					.saturating_mul(10_000 + T::DbWeight::get().reads_writes(6, 6))
+			)
+		)]
+		pub fn reveal_swap(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			asset_a_amount: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+			salt: [u8; 32],
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Release the deposit of the matching commitment
+			let commitment = Self::swap_commitment_hash(
+				&sender,
+				(asset_a, asset_b),
+				asset_a_amount,
+				min_amount_out,
+				&salt,
+			);
+			Self::remove_swap_commitment(&sender, commitment)?;
+
+			// Check the user is able to make the swap
+			Self::check_deposit_is_valid(
+				&sender,
+				(asset_a, asset_b),
+				(asset_a_amount, 0u32.into()),
+				false,
+			)?;
+			Self::check_direct_swaps_allowed((asset_a, asset_b))?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
+
+			// Handle the swap, a swap tripping the pool's circuit breaker is reverted
+			let amount_out = match Self::process_swap_with_circuit_breaker(
+				&sender,
+				&sender,
+				(asset_a, asset_b),
+				asset_a_amount,
+				min_amount_out,
+				false,
+			)? {
+				Some(amount_out) => amount_out,
+				None => return Ok(()),
+			};
+
+			Self::deposit_event(Event::SwapRevealed {
+				commitment,
+				owner: sender,
+				asset_pair: (asset_a, asset_b),
+				amount_in: asset_a_amount,
+				amount_out,
+			});
+
+			Ok(())
+		}
+
+        /// Swap asset_a of `owner` for asset_b, spending the approval `owner` gave the sender
+        /// for asset_a. The asset_b received is sent to `recipient`. A swap tripping the pool's
+        /// circuit breaker is reverted, leaving the approval unspent.
+		#[pallet::weight(
+			(10_000 + T::DbWeight::get().reads_writes(5, 4)).saturating_add(
+				(T::MaxOrdersPerPool::get() as Weight)
+					.saturating_mul(10_000 + T::DbWeight::get().reads_writes(6, 6))
+			)
+		)]
+		pub fn swap_from(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			asset_a_amount: BalanceOf<T>,
+			keep_alive: bool,
+			recipient: T::AccountId,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Check the owner is able to make the swap, and has approved it
+			Self::check_deposit_is_valid(
+				&owner,
+				(asset_a, asset_b),
+				(asset_a_amount, 0u32.into()),
+				keep_alive,
+			)?;
+			Self::check_allowance(&owner, &sender, asset_a, asset_a_amount)?;
+			Self::check_direct_swaps_allowed((asset_a, asset_b))?;
+			Self::check_swaps_allowed((asset_a, asset_b))?;
+
+			// Handle the swap on the owner's behalf
+			Self::process_swap_from(
+				&owner,
+				&sender,
+				&recipient,
+				(asset_a, asset_b),
+				asset_a_amount,
+				keep_alive,
+			)?;
+
+			Ok(())
+		}
+
+        /// Provide liquidity from `owner` to an already existing pool, spending the approvals
+        /// `owner` gave the sender for both assets. The LP tokens are minted to `owner`.
+		#[pallet::weight(5_000 + T::DbWeight::get().reads_writes(3, 5))]
+		pub fn add_liquidity_from(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			asset_a_amount: BalanceOf<T>,
+			keep_alive: bool,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Get pool data, after the virtual trades of long-term orders
+			Self::settle_long_term_orders((asset_a, asset_b))?;
+			let pool_liquidity = Self::get_pool_liquidity((asset_a, asset_b))?;
+			let asset_b_amount = Self::derive_second_asset_amount(pool_liquidity, asset_a_amount)?;
+
+			// Check the owner is able to make the required deposit, and has approved it
+			Self::check_deposit_is_valid(
+				&owner,
+				(asset_a, asset_b),
+				(asset_a_amount, asset_b_amount),
+				keep_alive,
+			)?;
+			Self::check_allowance(&owner, &sender, asset_a, asset_a_amount)?;
+			Self::check_allowance(&owner, &sender, asset_b, asset_b_amount)?;
+			Self::check_liquidity_provision_allowed((asset_a, asset_b))?;
+
+			// Handle the deposit on the owner's behalf
+			Self::process_liquidity_pool_deposit_from(
+				&owner,
+				&sender,
+				(asset_a, asset_b),
+				(asset_a_amount, asset_b_amount),
+				pool_liquidity.0,
+				keep_alive,
+			)?;
+
+			Ok(())
+		}
+
+        /// Execute a list of swaps and liquidity operations in a single call.
+        /// An atomic batch fails as a whole with its first failing operation. A best-effort
+        /// batch skips failing operations, reverting only their own changes.
+		#[pallet::weight(Pallet::<T>::batch_weight(operations))]
+		pub fn batch(
+			origin: OriginFor<T>,
+			operations: BoundedVec<DexOperation<AssetIdOf<T>, BalanceOf<T>>, T::MaxBatchOperations>,
+			atomic: bool,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			// Execute the operations in order
+			Self::execute_batch(&sender, operations.into_inner(), atomic)?;
+
+			Ok(())
+		}
+
+        /// Set the rewards emitted in every block to the stakers of the asset_a/asset_b pool's
+        /// LP tokens. Zero stops the pool's rewards.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 1))]
+		pub fn set_farming_reward_rate(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			reward_per_block: BalanceOf<T>,
+		) -> DispatchResult {
+			// check if message is sent by the admin
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Self::check_farming_pool_exists((asset_a, asset_b))?;
+
+			Self::set_farming_reward_rate_for((asset_a, asset_b), reward_per_block);
+
+			Ok(())
+		}
+
+        /// Add rewards to the account liquidity mining rewards are paid from
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 2))]
+		pub fn fund_farming_rewards(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			Self::check_can_withdraw(&sender, T::FarmingRewardAsset::get(), amount, false)?;
+
+			Self::process_farming_rewards_funding(&sender, amount)?;
+
+			Ok(())
+		}
+
+        /// Stake LP tokens of the asset_a/asset_b pool to earn liquidity mining rewards
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 4))]
+		pub fn stake_lp_tokens(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			lp_token_amount: BalanceOf<T>,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			Self::check_lp_stake_is_valid(&sender, (asset_a, asset_b), lp_token_amount)?;
+
+			Self::process_lp_token_stake(&sender, (asset_a, asset_b), lp_token_amount)?;
+
+			Ok(())
+		}
+
+        /// Return staked LP tokens of the asset_a/asset_b pool. Their rewards so far can still
+        /// be claimed.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 4))]
+		pub fn unstake_lp_tokens(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			lp_token_amount: BalanceOf<T>,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			Self::check_lp_unstake_is_valid(&sender, (asset_a, asset_b), lp_token_amount)?;
+
+			Self::process_lp_token_unstake(&sender, (asset_a, asset_b), lp_token_amount)?;
+
+			Ok(())
+		}
+
+        /// Pay out the liquidity mining rewards earned in the asset_a/asset_b pool
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 4))]
+		pub fn claim_farming_rewards(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			Self::process_farming_rewards_claim(&sender, (asset_a, asset_b))?;
+
+			Ok(())
+		}
+
+        /// Deposit the liquidity mining rewards earned in the asset_a/asset_b pool into the
+        /// pool, swapping half of them for at least `min_amount_out` of its other asset, and
+        /// stake the LP tokens received. Only pools of the reward asset can compound their
+        /// rewards.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(14, 12))]
+		pub fn compound_farming_rewards(
+			origin: OriginFor<T>,
+			asset_a: AssetIdOf<T>,
+			asset_b: AssetIdOf<T>,
+			min_amount_out: BalanceOf<T>,
+			min_lp_tokens: BalanceOf<T>,
+		) -> DispatchResult {
+			// check if message is signed
+			let sender = ensure_signed(origin)?;
+
+			Self::check_can_compound_farming_rewards((asset_a, asset_b))?;
+
+			Self::process_farming_rewards_compound(
+				&sender,
+				(asset_a, asset_b),
+				min_amount_out,
+				min_lp_tokens,
+			)?;
+
 			Ok(())
 		}
 	}
@@ -1,18 +1,20 @@
 use crate as pallet_dex;
 use frame_support::{
 	parameter_types,
-	traits::{ConstU128, ConstU16, ConstU32, ConstU64},
+	traits::{ConstU128, ConstU16, ConstU32, ConstU64, Contains},
 	PalletId,
 };
 use frame_system as system;
 use pallet_assets;
 use pallet_balances;
+use pallet_timestamp;
 use sp_core::H256;
 use sp_runtime::{
 	testing::Header,
 	traits::{BlakeTwo256, IdentityLookup},
+	Permill,
 };
-use system::EnsureRoot;
+use system::{EnsureRoot, EnsureSigned};
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -30,6 +32,8 @@ frame_support::construct_runtime!(
 		DexModule: pallet_dex::{Pallet, Call, Storage, Event<T>},
 		Assets: pallet_assets,
 		Balances: pallet_balances,
+		Timestamp: pallet_timestamp,
+		PriceConsumer: pallet_price_consumer::{Pallet, Call, Storage},
 	}
 );
 
@@ -103,18 +107,123 @@ parameter_types! {
 	pub const StringLimit: u32 = 50;
 }
 
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = ConstU64<1>;
+	type WeightInfo = ();
+}
+
 parameter_types! {
 	pub const PoolPalletId: PalletId = PalletId(*b"the/pool");
+	pub const FlashLoanFee: Permill = Permill::from_percent(1);
+	pub const MakerFee: Permill = Permill::from_parts(1_000);
+	pub const TakerFee: Permill = Permill::from_parts(2_000);
+	pub const CommitmentDeposit: u128 = 100;
+	pub const PoolCreationDeposit: u128 = 1_000;
+	pub const FarmingRewardAsset: u32 = 1;
+}
+
+pub struct FlashLoanCallFilter;
+impl Contains<Call> for FlashLoanCallFilter {
+	fn contains(call: &Call) -> bool {
+		matches!(call, Call::Assets(_) | Call::DexModule(pallet_dex::Call::swap { .. }))
+	}
 }
 
 impl pallet_dex::Config for Test {
 	type Event = Event;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type CreatePoolOrigin = EnsureSigned<AccountId>;
 	type Assets = Assets;
 	type Balances = Balances;
 	type PalletId = PoolPalletId;
+	type UnixTime = Timestamp;
+	type MaxPriceObservations = ConstU32<8>;
+	type Call = Call;
+	type FlashLoanCallFilter = FlashLoanCallFilter;
+	type FlashLoanFee = FlashLoanFee;
+	type MaxOrdersPerPool = ConstU32<4>;
+	type MakerFee = MakerFee;
+	type TakerFee = TakerFee;
+	type MaxDcaExecutionsPerBlock = ConstU32<4>;
+	type MaxConditionalExecutionsPerBlock = ConstU32<2>;
+	type MaxBatchSwapsPerPool = ConstU32<4>;
+	type CommitmentDeposit = CommitmentDeposit;
+	type MinRevealDelay = ConstU64<1>;
+	type RevealWindow = ConstU64<5>;
+	type MaxCommitmentExpiriesPerBlock = ConstU32<2>;
+	type MaxForcedRedemptions = ConstU32<4>;
+	type PoolCreationDeposit = PoolCreationDeposit;
+	type MaxBatchOperations = ConstU32<4>;
+	type MaxStatsDays = ConstU32<3>;
+	type FarmingRewardAsset = FarmingRewardAsset;
+}
+
+/// Minimal pallet consuming DEX prices through `DexPriceProvider`,
+/// as a lending pallet would when valuing collateral
+#[frame_support::pallet]
+pub mod pallet_price_consumer {
+	use crate::DexPriceProvider;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		type Oracle: DexPriceProvider<u32, u128>;
+	}
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	/// Last recorded collateral value, denominated in the quote asset
+	#[pallet::storage]
+	pub type CollateralValue<T> = StorageValue<_, u128, OptionQuery>;
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The oracle could not provide a price
+		PriceUnavailable,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		#[pallet::weight(10_000)]
+		pub fn record_collateral_value(
+			origin: OriginFor<T>,
+			collateral_asset: u32,
+			quote_asset: u32,
+			amount: u128,
+			window: u64,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let value = T::Oracle::twap_value_of(collateral_asset, quote_asset, amount, window)
+				.ok_or(Error::<T>::PriceUnavailable)?;
+			CollateralValue::<T>::put(value);
+			Ok(())
+		}
+	}
+}
+
+impl pallet_price_consumer::Config for Test {
+	type Oracle = DexModule;
+}
+
+/// Test externalities checking the pool invariants once a test is done with them. The
+/// `assert_ok_with_invariants!` of `test_utils` checks them after every call in between.
+pub struct TestExternalities(sp_io::TestExternalities);
+
+impl TestExternalities {
+	pub fn execute_with<R>(&mut self, execute: impl FnOnce() -> R) -> R {
+		self.0.execute_with(|| {
+			let result = execute();
+			DexModule::do_try_state().unwrap();
+			result
+		})
+	}
 }
 
 // Build genesis storage according to the mock runtime.
-pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+pub fn new_test_ext() -> TestExternalities {
+	TestExternalities(system::GenesisConfig::default().build_storage::<Test>().unwrap().into())
 }
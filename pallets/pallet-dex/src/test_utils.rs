@@ -1,9 +1,27 @@
 use crate::{dex_math::*, mock::*};
 use frame_support::{
-	assert_ok,
-	traits::{tokens::fungibles::Mutate, Currency},
+	dispatch::DispatchResult,
+	traits::{tokens::fungibles::Mutate, Currency, Hooks},
+	weights::Weight,
 };
 
+/// `frame_support::assert_ok!`, checking the pool invariants after the call succeeded
+macro_rules! assert_ok_with_invariants {
+	( $( $args:tt )* ) => {{
+		frame_support::assert_ok!($( $args )*);
+		$crate::mock::DexModule::do_try_state().unwrap();
+	}};
+}
+pub(crate) use assert_ok_with_invariants;
+
+pub const USER: AccountId = 1u32;
+pub const USER_2: AccountId = 2u32;
+pub const ASSET_A: u32 = 1u32;
+pub const ASSET_B: u32 = 2u32;
+pub const ASSET_A_AMOUNT: u128 = 1_000_000;
+pub const ASSET_B_AMOUNT: u128 = 1_000_000;
+pub const MINTED_AMOUNT: u128 = 1_000_000_000;
+
 pub fn create_and_give_user_asset(user: AccountId, asset: u32, amount: u128) {
 	let origin = Origin::signed(user);
 	Balances::make_free_balance_be(&user, amount);
@@ -85,15 +103,45 @@ pub fn create_liquidity_pool(
 
 	let origin = Origin::signed(user);
 
-	assert_ok!(DexModule::create_pool(
+	assert_ok_with_invariants!(DexModule::create_pool(
 		origin,
 		asset_pair.0,
 		asset_pair.1,
 		asset_amounts.0,
 		asset_amounts.0,
+		false,
 	),);
 }
 
+/// Create `USER`'s ASSET_A/ASSET_B pool through the extrinsic, from assets `USER` already holds
+pub fn create_pool() -> DispatchResult {
+	DexModule::create_pool(
+		Origin::signed(USER),
+		ASSET_A,
+		ASSET_B,
+		ASSET_A_AMOUNT,
+		ASSET_B_AMOUNT,
+		false,
+	)
+}
+
+/// Start at block 1 with `USER`'s ASSET_A/ASSET_B pool, and give each of `traders` `amount` of
+/// each of `assets`
+pub fn setup_pool_with_traders(traders: &[AccountId], assets: &[u32], amount: u128) {
+	System::set_block_number(1);
+	create_liquidity_pool(
+		USER,
+		(ASSET_A, ASSET_B),
+		(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+		MINTED_AMOUNT,
+	);
+	for trader in traders {
+		for asset in assets {
+			give_user_asset(*trader, *asset, amount);
+		}
+	}
+}
+
 pub fn check_user_swap_executed(
 	user: AccountId,
 	asset_pair: (u32, u32),
@@ -135,3 +183,12 @@ pub fn check_half_of_lp_tokens_redeemed(user: AccountId, asset_pair: (u32, u32),
 	check_users_balance(pool_id, asset_pair.1, lp_tokens_amount);
 	check_users_balance(pool_id, lp_token_id, 0);
 }
+
+/// Run the block's idle tasks without a weight limit, completing the queued pool teardowns
+pub fn tear_down_pools() {
+	DexModule::on_idle(System::block_number(), Weight::MAX);
+}
+
+pub fn set_time_in_secs(secs: u64) {
+	Timestamp::set_timestamp(secs * 1_000);
+}
@@ -1,13 +1,5 @@
 use crate::{dex_math::*, mock::*, test_utils::*, Error};
-use frame_support::{assert_noop, assert_ok, traits::Currency};
-
-const USER: AccountId = 1u32;
-const USER_2: AccountId = 2u32;
-const ASSET_A: u32 = 1u32;
-const ASSET_B: u32 = 2u32;
-const ASSET_A_AMOUNT: u128 = 1_000_000;
-const ASSET_B_AMOUNT: u128 = 1_000_000;
-const MINTED_AMOUNT: u128 = 1_000_000_000;
+use frame_support::{assert_err, assert_noop, assert_ok, traits::Currency};
 
 #[test]
 fn can_transfer_assets() {
@@ -15,13 +7,14 @@ fn can_transfer_assets() {
 		create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
 		Balances::make_free_balance_be(&USER_2, ExistentialDeposit::get());
 		let origin = Origin::signed(USER);
-		assert_ok!(Assets::transfer(origin, ASSET_A, USER_2, ASSET_A_AMOUNT));
+		assert_ok_with_invariants!(Assets::transfer(origin, ASSET_A, USER_2, ASSET_A_AMOUNT));
 	});
 }
 
 #[cfg(test)]
 mod dex_math_tests {
 	use super::*;
+	use sp_runtime::{FixedPointNumber, FixedU128, Permill};
 
 	#[test]
 	fn test_get_lp_tokens_for_new_pool() {
@@ -67,6 +60,113 @@ mod dex_math_tests {
 		});
 	}
 
+	#[test]
+	fn test_get_swap_return_and_fee() {
+		new_test_ext().execute_with(|| {
+			let (swap_return, fee) =
+				get_swap_return_and_fee::<u128, Test>(50u128, (50u128, 100u128)).unwrap();
+			assert_eq!((swap_return, fee), (45, 5));
+		});
+	}
+
+	#[test]
+	fn test_get_swap_input() {
+		new_test_ext().execute_with(|| {
+			let expected_input = get_swap_input::<u128, Test>(45u128, (50u128, 100u128)).unwrap();
+			assert_eq!(expected_input, 49);
+			let actual_return =
+				get_swap_return::<u128, Test>(expected_input, (50u128, 100u128)).unwrap();
+			assert_eq!(actual_return, 45);
+
+			let expected_input = get_swap_input::<u128, Test>(15u128, (100u128, 50u128)).unwrap();
+			let actual_return =
+				get_swap_return::<u128, Test>(expected_input, (100u128, 50u128)).unwrap();
+			assert_eq!(actual_return, 15);
+			let actual_return =
+				get_swap_return::<u128, Test>(expected_input - 1, (100u128, 50u128)).unwrap();
+			assert_eq!(actual_return, 14);
+
+			assert!(get_swap_input::<u128, Test>(100u128, (50u128, 100u128)).is_err());
+		});
+	}
+
+	#[test]
+	fn test_get_marginal_swap_price() {
+		new_test_ext().execute_with(|| {
+			let price = get_marginal_swap_price((100u128, 90u128)).unwrap();
+			assert_eq!(price, FixedU128::saturating_from_rational(100_000u128, 81_000u128));
+			assert_eq!(get_marginal_swap_price((100u128, 0u128)), None);
+		});
+	}
+
+	#[test]
+	fn test_get_swap_input_to_price() {
+		new_test_ext().execute_with(|| {
+			// (1_000_000 + x)^2 = 2 * 1_000_000 * 1_000_000 * 0.9
+			let price = FixedU128::saturating_from_integer(2u32);
+			let input = get_swap_input_to_price(price, (1_000_000u128, 1_000_000u128));
+			assert_eq!(input, 341_640);
+
+			// Already above the price
+			let price = FixedU128::saturating_from_rational(1u32, 2u32);
+			let input = get_swap_input_to_price(price, (1_000_000u128, 1_000_000u128));
+			assert_eq!(input, 0);
+		});
+	}
+
+	#[test]
+	fn test_get_order_fill() {
+		new_test_ext().execute_with(|| {
+			let price = FixedU128::saturating_from_rational(3u32, 2u32);
+			assert_eq!(get_order_fill(300u128, 1_000u128, price), Some((200, 300)));
+			assert_eq!(get_order_fill(3_000u128, 1_000u128, price), Some((1_000, 1_500)));
+			assert_eq!(get_order_fill(301u128, 1_000u128, price), Some((200, 300)));
+			assert_eq!(get_order_fill(300u128, 1_000u128, FixedU128::from_inner(0)), None);
+		});
+	}
+
+	#[test]
+	fn test_get_amount_at_reserve_ratio() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(get_amount_at_reserve_ratio(1_000u128, (1_000_000, 2_000_000)), 2_000);
+			assert_eq!(get_amount_at_reserve_ratio(1_000u128, (3_000_000, 1_000_000)), 333);
+			assert_eq!(get_amount_at_reserve_ratio(1_000u128, (0, 1_000_000)), 0);
+		});
+	}
+
+	#[test]
+	fn test_get_batch_pool_input() {
+		new_test_ext().execute_with(|| {
+			let liquidity = (1_000_000u128, 1_000_000u128);
+
+			// Without opposing flow everything goes through the pool
+			assert_eq!(get_batch_pool_input::<u128, Test>(1_000, 0, liquidity).unwrap(), 1_000);
+
+			// Both sides end up trading at the same price
+			let pool_input = get_batch_pool_input::<u128, Test>(10_000, 4_000, liquidity).unwrap();
+			let swap_return = get_swap_return::<u128, Test>(pool_input, liquidity).unwrap();
+			let a_side_price = FixedU128::saturating_from_rational(4_000 + swap_return, 10_000u128);
+			let b_side_price = FixedU128::saturating_from_rational(4_000u128, 10_000 - pool_input);
+			assert!(a_side_price >= b_side_price);
+			assert!(
+				a_side_price - b_side_price < FixedU128::saturating_from_rational(1u32, 1_000u32)
+			);
+		});
+	}
+
+	#[test]
+	fn test_get_min_swap_return() {
+		new_test_ext().execute_with(|| {
+			let liquidity = (1_000_000u128, 2_000_000u128);
+			assert_eq!(get_min_swap_return(1_000u128, liquidity, Permill::zero()), Some(1_800));
+			assert_eq!(
+				get_min_swap_return(1_000u128, liquidity, Permill::from_percent(10)),
+				Some(1_620)
+			);
+			assert_eq!(get_min_swap_return(1_000u128, (0, 0), Permill::zero()), None);
+		});
+	}
+
 	#[test]
 	fn test_get_redeemed_token_balance() {
 		new_test_ext().execute_with(|| {
@@ -80,6 +180,32 @@ mod dex_math_tests {
 			assert_eq!(expected_return, (0, 0));
 		});
 	}
+
+	#[test]
+	fn test_get_liquidity_per_lp_token() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				get_liquidity_per_lp_token((100u128, 400u128), 100u128),
+				Some(FixedU128::saturating_from_integer(2u32))
+			);
+			assert_eq!(get_liquidity_per_lp_token((100u128, 400u128), 0u128), None);
+		});
+	}
+
+	#[test]
+	fn test_get_impermanent_loss() {
+		new_test_ext().execute_with(|| {
+			// A fourfold price increase costs a fifth of the held value
+			assert_eq!(
+				get_impermanent_loss((100u128, 100u128), (50, 200), (50, 200)),
+				Permill::from_percent(20)
+			);
+			assert_eq!(
+				get_impermanent_loss((100u128, 100u128), (100, 100), (100, 100)),
+				Permill::zero()
+			);
+		});
+	}
 }
 
 #[cfg(test)]
@@ -91,7 +217,14 @@ mod create_pool_tests {
 		new_test_ext().execute_with(|| {
 			let origin = Origin::signed(USER);
 			assert_noop!(
-				DexModule::create_pool(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				DexModule::create_pool(
+					origin,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT,
+					false
+				),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -104,7 +237,14 @@ mod create_pool_tests {
 
 			let origin = Origin::signed(USER);
 			assert_noop!(
-				DexModule::create_pool(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				DexModule::create_pool(
+					origin,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT,
+					false
+				),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -117,7 +257,14 @@ mod create_pool_tests {
 
 			let origin = Origin::signed(USER);
 			assert_noop!(
-				DexModule::create_pool(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				DexModule::create_pool(
+					origin,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT,
+					false
+				),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -130,7 +277,14 @@ mod create_pool_tests {
 
 			let origin = Origin::signed(USER);
 			assert_noop!(
-				DexModule::create_pool(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				DexModule::create_pool(
+					origin,
+					ASSET_A,
+					ASSET_A,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT,
+					false
+				),
 				Error::<Test>::ProvidedInvalidAssetIds
 			);
 		});
@@ -192,7 +346,7 @@ mod provide_liquidity_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-				DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT,),
+				DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, false),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -212,7 +366,7 @@ mod provide_liquidity_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-				DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT,),
+				DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, false),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -232,7 +386,7 @@ mod provide_liquidity_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-				DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT,),
+				DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, false),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -252,7 +406,7 @@ mod provide_liquidity_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-				DexModule::provide_liquidity(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT,),
+				DexModule::provide_liquidity(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT, false),
 				Error::<Test>::ProvidedInvalidAssetIds
 			);
 		});
@@ -271,7 +425,13 @@ mod provide_liquidity_tests {
 
 			let origin = Origin::signed(USER_2);
 
-			assert_ok!(DexModule::provide_liquidity(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT,),);
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				origin,
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				false
+			),);
 
 			check_liquidity_taken(
 				USER_2,
@@ -309,7 +469,7 @@ mod swap_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-                DexModule::swap(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT),
+                DexModule::swap(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, false, USER_2),
 				Error::<Test>::NotEnoughTokensForTransaction
 			);
 		});
@@ -328,7 +488,7 @@ mod swap_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-                DexModule::swap(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT),
+                DexModule::swap(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT, false, USER_2),
 				Error::<Test>::ProvidedInvalidAssetIds
 			);
 		});
@@ -347,7 +507,14 @@ mod swap_tests {
 
 			let origin = Origin::signed(USER_2);
 
-			assert_ok!(DexModule::swap(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT),);
+			assert_ok_with_invariants!(DexModule::swap(
+				origin,
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				false,
+				USER_2
+			),);
 
 			check_user_swap_executed(
 				USER_2,
@@ -377,7 +544,7 @@ mod redeem_lp_tokens_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-				DexModule::redeem_lp_tokens(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT),
+				DexModule::redeem_lp_tokens(origin, ASSET_A, ASSET_A, ASSET_A_AMOUNT, USER_2),
 				Error::<Test>::ProvidedInvalidAssetIds,
 			);
 		});
@@ -396,7 +563,7 @@ mod redeem_lp_tokens_tests {
 			let origin = Origin::signed(USER_2);
 
 			assert_noop!(
-				DexModule::redeem_lp_tokens(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT),
+				DexModule::redeem_lp_tokens(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT, USER_2),
 				Error::<Test>::NotEnoughLPTokens,
 			);
 		});
@@ -415,7 +582,13 @@ mod redeem_lp_tokens_tests {
 
 			let origin = Origin::signed(USER);
 
-			assert_ok!(DexModule::redeem_lp_tokens(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT));
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				origin,
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				USER
+			));
 
 			check_all_lp_tokens_redeemed(USER, (ASSET_A, ASSET_B), ASSET_A_AMOUNT);
 		});
@@ -434,9 +607,3845 @@ mod redeem_lp_tokens_tests {
 
 			let origin = Origin::signed(USER);
 
-			assert_ok!(DexModule::redeem_lp_tokens(origin, ASSET_A, ASSET_B, ASSET_A_AMOUNT / 2));
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				origin,
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT / 2,
+				USER
+			));
 
 			check_half_of_lp_tokens_redeemed(USER, (ASSET_A, ASSET_B), ASSET_A_AMOUNT / 2);
 		});
 	}
 }
+
+#[cfg(test)]
+mod price_oracle_tests {
+	use super::*;
+	use sp_runtime::{FixedPointNumber, FixedU128};
+
+	#[test]
+	fn test_spot_price() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+
+			assert_eq!(
+				DexModule::get_spot_price((ASSET_A, ASSET_B)),
+				Some(FixedU128::saturating_from_integer(1u32))
+			);
+		});
+	}
+
+	#[test]
+	fn test_twap_without_enough_history() {
+		new_test_ext().execute_with(|| {
+			set_time_in_secs(100);
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			set_time_in_secs(150);
+
+			assert_eq!(DexModule::get_twap((ASSET_A, ASSET_B), 100), None);
+			assert_eq!(
+				DexModule::get_twap((ASSET_A, ASSET_B), 50),
+				Some(FixedU128::saturating_from_integer(1u32))
+			);
+		});
+	}
+
+	#[test]
+	fn test_twap_after_swap() {
+		new_test_ext().execute_with(|| {
+			set_time_in_secs(1);
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			set_time_in_secs(101);
+			let origin = Origin::signed(USER_2);
+			assert_ok_with_invariants!(DexModule::swap(
+				origin,
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				false,
+				USER_2
+			));
+
+			// Pool now holds 2_000_000 of asset A and 550_000 of asset B
+			let price_after_swap = FixedU128::saturating_from_rational(550_000u128, 2_000_000u128);
+			assert_eq!(DexModule::get_spot_price((ASSET_A, ASSET_B)), Some(price_after_swap));
+
+			set_time_in_secs(201);
+			assert_eq!(DexModule::get_twap((ASSET_A, ASSET_B), 100), Some(price_after_swap));
+			assert_eq!(
+				DexModule::get_twap((ASSET_A, ASSET_B), 200),
+				Some(FixedU128::saturating_from_rational(6_375u128, 10_000u128))
+			);
+			// Window start falls between observations
+			assert_eq!(
+				DexModule::get_twap((ASSET_A, ASSET_B), 150),
+				Some(FixedU128::from_inner(516_666_666_666_666_666))
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod price_provider_tests {
+	use super::*;
+	use crate::DexPriceProvider;
+	use sp_runtime::{FixedPointNumber, FixedU128};
+
+	#[test]
+	fn test_value_of() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				false,
+				USER_2
+			));
+
+			// Pool now holds 2_000_000 of asset A and 550_000 of asset B
+			assert_eq!(DexModule::value_of(ASSET_A, ASSET_B, 1_000), Some(275));
+			assert_eq!(DexModule::value_of(ASSET_A, 3u32, 1_000), None);
+		});
+	}
+
+	#[test]
+	fn test_spot_price_settles_long_term_orders() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_long_term_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				10
+			));
+
+			// Priced at the reserves a swap would execute at, like quotes are
+			System::set_block_number(6);
+			let virtual_return =
+				get_swap_return::<u128, Test>(5_000, (ASSET_A_AMOUNT, ASSET_B_AMOUNT)).unwrap();
+			let settled_reserves = (ASSET_A_AMOUNT + 5_000, ASSET_B_AMOUNT - virtual_return);
+			let settled_price =
+				FixedU128::checked_from_rational(settled_reserves.1, settled_reserves.0).unwrap();
+			assert_eq!(DexModule::spot_price(ASSET_A, ASSET_B), Some(settled_price));
+			assert_eq!(
+				DexModule::value_of(ASSET_A, ASSET_B, 1_000_000),
+				settled_price.checked_mul_int(1_000_000u128)
+			);
+			assert_ne!(
+				DexModule::spot_price(ASSET_A, ASSET_B),
+				DexModule::get_spot_price((ASSET_A, ASSET_B))
+			);
+		});
+	}
+
+	#[test]
+	fn test_consumer_pallet_reads_twap() {
+		new_test_ext().execute_with(|| {
+			set_time_in_secs(1);
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			set_time_in_secs(101);
+
+			assert_noop!(
+				PriceConsumer::record_collateral_value(
+					Origin::signed(USER),
+					ASSET_A,
+					ASSET_B,
+					1_000,
+					200
+				),
+				pallet_price_consumer::Error::<Test>::PriceUnavailable
+			);
+
+			assert_ok_with_invariants!(PriceConsumer::record_collateral_value(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				100
+			));
+			assert_eq!(pallet_price_consumer::CollateralValue::<Test>::get(), Some(1_000));
+		});
+	}
+}
+
+#[cfg(test)]
+mod handler_tests {
+	use super::*;
+	use crate::{LiquidityHandler, SwapHandler};
+
+	#[test]
+	fn test_swap_handler() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			let expected_return =
+				get_swap_return::<u128, Test>(ASSET_A_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			assert_eq!(
+				<DexModule as SwapHandler<_, _, _>>::quote_swap(ASSET_A, ASSET_B, ASSET_A_AMOUNT),
+				Some(expected_return)
+			);
+
+			assert_noop!(
+				<DexModule as SwapHandler<_, _, _>>::swap(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					expected_return + 1
+				),
+				Error::<Test>::SlippageExceeded
+			);
+
+			assert_eq!(
+				<DexModule as SwapHandler<_, _, _>>::swap(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					expected_return
+				),
+				Ok(expected_return)
+			);
+			check_user_swap_executed(
+				USER_2,
+				(ASSET_A, ASSET_B),
+				ASSET_A_AMOUNT,
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+		});
+	}
+
+	#[test]
+	fn test_swap_handler_respects_batch_auction_mode() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::set_batch_auction_mode(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				true
+			));
+
+			assert_noop!(
+				<DexModule as SwapHandler<_, _, _>>::swap(&USER_2, ASSET_A, ASSET_B, 1_000, 0),
+				Error::<Test>::PoolInBatchAuctionMode
+			);
+		});
+	}
+
+	#[test]
+	fn test_quote_swap_settles_long_term_orders() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_long_term_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				10
+			));
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			// Five blocks of the order have been sold by the time a swap would execute
+			System::set_block_number(6);
+			let virtual_return =
+				get_swap_return::<u128, Test>(5_000, (ASSET_A_AMOUNT, ASSET_B_AMOUNT)).unwrap();
+			let expected_return = get_swap_return::<u128, Test>(
+				1_000,
+				(ASSET_B_AMOUNT - virtual_return, ASSET_A_AMOUNT + 5_000),
+			)
+			.unwrap();
+			assert_eq!(
+				<DexModule as SwapHandler<_, _, _>>::quote_swap(ASSET_B, ASSET_A, 1_000),
+				Some(expected_return)
+			);
+
+			// Quoting does not store the settlement
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT);
+			assert_eq!(DexModule::long_term_order_pools(&pool_id).unwrap().last_settled_block, 1);
+		});
+	}
+
+	#[test]
+	fn test_liquidity_handler() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_two_assets(USER_2, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+
+			assert_noop!(
+				DexModule::add_liquidity(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT - 1,
+					0
+				),
+				Error::<Test>::SlippageExceeded
+			);
+			assert_noop!(
+				DexModule::add_liquidity(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT,
+					ASSET_A_AMOUNT + 1
+				),
+				Error::<Test>::SlippageExceeded
+			);
+
+			assert_eq!(
+				DexModule::add_liquidity(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					ASSET_B_AMOUNT,
+					ASSET_A_AMOUNT
+				),
+				Ok((ASSET_B_AMOUNT, ASSET_A_AMOUNT))
+			);
+
+			assert_noop!(
+				DexModule::remove_liquidity(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					(ASSET_A_AMOUNT + 1, 0)
+				),
+				Error::<Test>::SlippageExceeded
+			);
+			assert_eq!(
+				DexModule::remove_liquidity(
+					&USER_2,
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT,
+					(ASSET_A_AMOUNT, ASSET_B_AMOUNT)
+				),
+				Ok((ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+			);
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+			check_users_balance(USER_2, ASSET_B, MINTED_AMOUNT);
+		});
+	}
+}
+
+#[cfg(test)]
+mod flash_loan_tests {
+	use super::*;
+
+	const LOAN_AMOUNT: u128 = 100_000;
+	const LOAN_FEE: u128 = 1_000;
+
+	fn transfer_call(amount: u128) -> Box<Call> {
+		Box::new(Call::Assets(pallet_assets::Call::transfer {
+			id: ASSET_A,
+			target: USER,
+			amount,
+		}))
+	}
+
+	#[test]
+	fn test_flash_loan_call_not_allowed() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			let call = Box::new(Call::Balances(pallet_balances::Call::transfer {
+				dest: USER,
+				value: 1,
+			}));
+
+			assert_noop!(
+				DexModule::flash_loan(Origin::signed(USER_2), ASSET_A, ASSET_B, LOAN_AMOUNT, call),
+				Error::<Test>::CallNotAllowed
+			);
+		});
+	}
+
+	#[test]
+	fn test_flash_loan_more_than_pool_liquidity() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			assert_noop!(
+				DexModule::flash_loan(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					ASSET_A_AMOUNT + 1,
+					transfer_call(1)
+				),
+				Error::<Test>::NotEnoughLiquidity
+			);
+		});
+	}
+
+	#[test]
+	fn test_flash_loan_not_repaid() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			// Only enough to cover part of the fee
+			give_user_asset(USER_2, ASSET_A, LOAN_FEE / 2);
+
+			assert_noop!(
+				DexModule::flash_loan(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					LOAN_AMOUNT,
+					transfer_call(1)
+				),
+				Error::<Test>::FlashLoanNotRepaid
+			);
+		});
+	}
+
+	#[test]
+	fn test_flash_loan() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::flash_loan(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				LOAN_AMOUNT,
+				transfer_call(LOAN_AMOUNT)
+			));
+
+			// The borrowed funds were spent, so the repayment came from the borrower's own funds
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - LOAN_AMOUNT - LOAN_FEE);
+			check_users_balance(USER, ASSET_A, MINTED_AMOUNT - ASSET_A_AMOUNT + LOAN_AMOUNT);
+
+			// The fee went to the pool
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + LOAN_FEE);
+			check_users_balance(pool_id, ASSET_B, ASSET_B_AMOUNT);
+		});
+	}
+}
+
+#[cfg(test)]
+mod limit_order_tests {
+	use super::*;
+	use crate::{LimitOrders, PoolLimitOrders, SwapHandler};
+	use frame_support::{traits::Hooks, weights::Weight};
+	use sp_runtime::{FixedPointNumber, FixedU128};
+
+	const ORDER_AMOUNT: u128 = 1_000;
+
+	fn run_matcher() {
+		DexModule::on_idle(System::block_number(), Weight::MAX);
+	}
+
+	#[test]
+	fn test_place_limit_order_escrows_funds() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_integer(2u32)
+			));
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - ORDER_AMOUNT);
+			check_users_balance(DexModule::account_id(), ASSET_A, ORDER_AMOUNT);
+			assert!(LimitOrders::<Test>::contains_key(0));
+		});
+	}
+
+	#[test]
+	fn test_place_limit_order_without_tokens() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+
+			assert_noop!(
+				DexModule::place_limit_order(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					ORDER_AMOUNT,
+					FixedU128::saturating_from_integer(1u32)
+				),
+				Error::<Test>::NotEnoughTokensForTransaction
+			);
+		});
+	}
+
+	#[test]
+	fn test_too_many_limit_orders() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			for _ in 0..4 {
+				assert_ok_with_invariants!(DexModule::place_limit_order(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					ORDER_AMOUNT,
+					FixedU128::saturating_from_integer(2u32)
+				));
+			}
+
+			assert_noop!(
+				DexModule::place_limit_order(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					ORDER_AMOUNT,
+					FixedU128::saturating_from_integer(2u32)
+				),
+				Error::<Test>::TooManyOrders
+			);
+		});
+	}
+
+	#[test]
+	fn test_cancel_limit_order() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_integer(2u32)
+			));
+
+			assert_noop!(
+				DexModule::cancel_limit_order(Origin::signed(USER), 0),
+				Error::<Test>::NotOrderOwner
+			);
+			assert_ok_with_invariants!(DexModule::cancel_limit_order(Origin::signed(USER_2), 0));
+			assert_noop!(
+				DexModule::cancel_limit_order(Origin::signed(USER_2), 0),
+				Error::<Test>::OrderNotFound
+			);
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert!(!PoolLimitOrders::<Test>::contains_key(pool_id, ASSET_A));
+		});
+	}
+
+	#[test]
+	fn test_limit_order_filled_when_price_crosses_limit() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_integer(2u32)
+			));
+
+			// The pool only pays ~0.9 B per A
+			run_matcher();
+			assert!(LimitOrders::<Test>::contains_key(0));
+			check_users_balance(USER_2, ASSET_B, 0);
+
+			// Buying A pushes its price above the limit
+			assert_ok_with_invariants!(<DexModule as SwapHandler<_, _, _>>::swap(
+				&USER,
+				ASSET_B,
+				ASSET_A,
+				ASSET_B_AMOUNT,
+				0
+			));
+			let pool_liquidity = DexModule::get_pool_liquidity((ASSET_A, ASSET_B)).unwrap();
+			let expected_return =
+				get_swap_return::<u128, Test>(ORDER_AMOUNT, pool_liquidity).unwrap();
+
+			run_matcher();
+			assert!(!LimitOrders::<Test>::contains_key(0));
+			check_users_balance(USER_2, ASSET_B, expected_return);
+			check_users_balance(DexModule::account_id(), ASSET_A, 0);
+		});
+	}
+
+	#[test]
+	fn test_limit_order_matching_is_bounded_by_weight() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_rational(1u32, 2u32)
+			));
+
+			DexModule::on_idle(System::block_number(), 0);
+			assert!(LimitOrders::<Test>::contains_key(0));
+
+			run_matcher();
+			assert!(!LimitOrders::<Test>::contains_key(0));
+		});
+	}
+
+	#[test]
+	fn test_limit_order_matching_resumes_after_last_order_book() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_two_assets(USER_2, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+
+			// The order selling A cannot be filled, the one selling B can
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_integer(2u32)
+			));
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_B,
+				ASSET_A,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_rational(1u32, 2u32)
+			));
+
+			// Each block only visits a single order book, the second continues after the first
+			let weight = DexModule::limit_order_fill_weight();
+			DexModule::on_idle(System::block_number(), weight);
+			DexModule::on_idle(System::block_number(), weight);
+			assert!(LimitOrders::<Test>::contains_key(0));
+			assert!(!LimitOrders::<Test>::contains_key(1));
+		});
+	}
+
+	#[test]
+	fn test_limit_orders_rest_on_batch_auction_pools() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ORDER_AMOUNT,
+				FixedU128::saturating_from_rational(1u32, 2u32)
+			));
+			assert_ok_with_invariants!(DexModule::set_batch_auction_mode(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				true
+			));
+
+			run_matcher();
+			assert!(LimitOrders::<Test>::contains_key(0));
+
+			assert_ok_with_invariants!(DexModule::set_batch_auction_mode(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				false
+			));
+			run_matcher();
+			assert!(!LimitOrders::<Test>::contains_key(0));
+		});
+	}
+}
+
+#[cfg(test)]
+mod order_routing_tests {
+	use super::*;
+	use crate::{LimitOrders, PoolLimitOrders};
+	use sp_runtime::{FixedPointNumber, FixedU128};
+
+	const TAKER: AccountId = 3u32;
+	const ORDER_AMOUNT: u128 = 1_000;
+
+	fn place_sell_order(limit_price: u32) {
+		give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+		assert_ok_with_invariants!(DexModule::place_limit_order(
+			Origin::signed(USER_2),
+			ASSET_A,
+			ASSET_B,
+			ORDER_AMOUNT,
+			FixedU128::saturating_from_integer(limit_price)
+		));
+	}
+
+	#[test]
+	fn test_swap_fills_cheaper_limit_order_before_pool() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			// The pool charges ~1.11 B per A, the order only 1 B per A
+			place_sell_order(1);
+			give_user_asset(TAKER, ASSET_B, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(TAKER),
+				ASSET_B,
+				ASSET_A,
+				10_000,
+				false,
+				TAKER
+			));
+
+			// The maker pays a 0.1% fee, the taker a 0.2% fee, both to the pool
+			check_users_balance(USER_2, ASSET_B, 999);
+			let pool_return =
+				get_swap_return::<u128, Test>(9_000, (ASSET_B_AMOUNT + 1, ASSET_A_AMOUNT + 2))
+					.unwrap();
+			check_users_balance(TAKER, ASSET_A, 998 + pool_return);
+			check_users_balance(TAKER, ASSET_B, MINTED_AMOUNT - 10_000);
+
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + 2 - pool_return);
+			check_users_balance(pool_id, ASSET_B, ASSET_B_AMOUNT + 1 + 9_000);
+
+			assert!(!LimitOrders::<Test>::contains_key(0));
+			assert!(!PoolLimitOrders::<Test>::contains_key(&pool_id, ASSET_A));
+		});
+	}
+
+	#[test]
+	fn test_swap_partially_fills_limit_order() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			place_sell_order(1);
+			give_user_asset(TAKER, ASSET_B, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(TAKER),
+				ASSET_B,
+				ASSET_A,
+				500,
+				false,
+				TAKER
+			));
+
+			check_users_balance(USER_2, ASSET_B, 500);
+			check_users_balance(TAKER, ASSET_A, 499);
+
+			let order = DexModule::limit_orders(0).unwrap();
+			assert_eq!(order.amount_in, ORDER_AMOUNT - 500);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert_eq!(DexModule::pool_limit_orders(&pool_id, ASSET_A).len(), 1);
+		});
+	}
+
+	#[test]
+	fn test_swap_uses_pool_when_cheaper_than_orders() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			place_sell_order(2);
+			give_user_asset(TAKER, ASSET_B, MINTED_AMOUNT);
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(TAKER),
+				ASSET_B,
+				ASSET_A,
+				1_000,
+				false,
+				TAKER
+			));
+
+			let expected_return =
+				get_swap_return::<u128, Test>(1_000, (ASSET_B_AMOUNT, ASSET_A_AMOUNT)).unwrap();
+			check_users_balance(TAKER, ASSET_A, expected_return);
+			check_users_balance(USER_2, ASSET_B, 0);
+			assert_eq!(DexModule::limit_orders(0).unwrap().amount_in, ORDER_AMOUNT);
+		});
+	}
+}
+
+#[cfg(test)]
+mod dca_tests {
+	use super::*;
+	use crate::{DcaExecutionQueue, DcaSchedules, Event as DexEvent, PriceLimits, PriceReference};
+	use frame_support::traits::Hooks;
+	use sp_runtime::Permill;
+
+	const AMOUNT_PER_PERIOD: u128 = 1_000;
+	const USER_2_BALANCE: u128 = 10_000;
+
+	fn run_to_block(n: u64) {
+		while System::block_number() < n {
+			System::set_block_number(System::block_number() + 1);
+			DexModule::on_initialize(System::block_number());
+		}
+	}
+
+	fn setup_pool_and_schedule(amount_per_period: u128, period: u64, count: u32, slippage: u32) {
+		setup_pool_with_traders(
+			&[USER_2],
+			&[ASSET_B],
+			USER_2_BALANCE.max(amount_per_period * count as u128),
+		);
+
+		assert_ok_with_invariants!(DexModule::schedule_dca(
+			Origin::signed(USER_2),
+			ASSET_B,
+			ASSET_A,
+			amount_per_period,
+			period,
+			count,
+			Permill::from_percent(slippage)
+		));
+	}
+
+	#[test]
+	fn test_schedule_dca_escrows_total_amount() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_schedule(AMOUNT_PER_PERIOD, 2, 5, 1);
+
+			check_users_balance(USER_2, ASSET_B, USER_2_BALANCE - 5 * AMOUNT_PER_PERIOD);
+			check_users_balance(DexModule::account_id(), ASSET_B, 5 * AMOUNT_PER_PERIOD);
+			let schedule = DexModule::dca_schedules(0).unwrap();
+			assert_eq!(schedule.next_execution, 3);
+			assert_eq!(DexModule::dca_execution_queue(3).into_inner(), vec![0]);
+		});
+	}
+
+	#[test]
+	fn test_schedule_dca_rejects_invalid_parameters() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			give_user_asset(USER_2, ASSET_B, USER_2_BALANCE);
+
+			assert_noop!(
+				DexModule::schedule_dca(
+					Origin::signed(USER_2),
+					ASSET_B,
+					ASSET_A,
+					AMOUNT_PER_PERIOD,
+					0,
+					5,
+					Permill::from_percent(1)
+				),
+				Error::<Test>::InvalidScheduleParameters
+			);
+			assert_noop!(
+				DexModule::schedule_dca(
+					Origin::signed(USER_2),
+					ASSET_B,
+					ASSET_A,
+					AMOUNT_PER_PERIOD,
+					2,
+					11,
+					Permill::from_percent(1)
+				),
+				Error::<Test>::NotEnoughTokensForTransaction
+			);
+		});
+	}
+
+	#[test]
+	fn test_dca_executes_each_period() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_schedule(AMOUNT_PER_PERIOD, 2, 5, 1);
+
+			run_to_block(2);
+			check_users_balance(USER_2, ASSET_A, 0);
+
+			run_to_block(3);
+			let expected_return = get_swap_return::<u128, Test>(
+				AMOUNT_PER_PERIOD,
+				(ASSET_B_AMOUNT, ASSET_A_AMOUNT),
+			)
+			.unwrap();
+			check_users_balance(USER_2, ASSET_A, expected_return);
+			let schedule = DexModule::dca_schedules(0).unwrap();
+			assert_eq!(schedule.remaining_periods, 4);
+			assert_eq!(schedule.next_execution, 5);
+			assert_eq!(schedule.escrowed_amount, 4 * AMOUNT_PER_PERIOD);
+
+			run_to_block(11);
+			assert!(!DcaSchedules::<Test>::contains_key(0));
+			check_users_balance(USER_2, ASSET_B, USER_2_BALANCE - 5 * AMOUNT_PER_PERIOD);
+			check_users_balance(DexModule::account_id(), ASSET_B, 0);
+			assert!(Assets::balance(ASSET_A, &USER_2) > 4 * expected_return);
+		});
+	}
+
+	#[test]
+	fn test_dca_skips_period_on_slippage() {
+		new_test_ext().execute_with(|| {
+			// A tenth of the pool moves the price far more than 5%
+			let amount_per_period = ASSET_B_AMOUNT / 10;
+			setup_pool_and_schedule(amount_per_period, 1, 2, 5);
+
+			run_to_block(2);
+			check_users_balance(USER_2, ASSET_A, 0);
+			assert_eq!(DexModule::dca_schedules(0).unwrap().escrowed_amount, 2 * amount_per_period);
+
+			// The skipped periods are refunded once the schedule completes
+			run_to_block(3);
+			assert!(!DcaSchedules::<Test>::contains_key(0));
+			check_users_balance(USER_2, ASSET_A, 0);
+			check_users_balance(USER_2, ASSET_B, 2 * amount_per_period);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_B, ASSET_B_AMOUNT);
+		});
+	}
+
+	#[test]
+	fn test_dca_period_tripping_circuit_breaker_is_skipped() {
+		new_test_ext().execute_with(|| {
+			let amount_per_period = ASSET_B_AMOUNT / 10;
+			setup_pool_and_schedule(amount_per_period, 1, 2, 50);
+			assert_ok_with_invariants!(DexModule::set_price_limits(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				Some(PriceLimits {
+					max_price_impact: None,
+					max_price_movement: Some(Permill::from_percent(3)),
+					reference: PriceReference::OpeningPrice,
+				})
+			));
+
+			// The swap is reverted, but the pool stays halted for the block
+			run_to_block(2);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let price = DexModule::get_limit_price((ASSET_A, ASSET_B)).unwrap();
+			System::assert_has_event(
+				DexEvent::<Test>::DcaExecutionSkipped { schedule_id: 0, owner: USER_2 }.into(),
+			);
+			System::assert_has_event(
+				DexEvent::<Test>::CircuitBreakerTripped { pool_id, price }.into(),
+			);
+			assert_noop!(
+				DexModule::check_circuit_breaker((ASSET_B, ASSET_A)),
+				Error::<Test>::CircuitBreakerTripped
+			);
+			check_users_balance(USER_2, ASSET_A, 0);
+			check_users_balance(pool_id, ASSET_B, ASSET_B_AMOUNT);
+			assert_eq!(DexModule::dca_schedules(0).unwrap().escrowed_amount, 2 * amount_per_period);
+		});
+	}
+
+	#[test]
+	fn test_cancel_dca_refunds_remaining_amount() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_schedule(AMOUNT_PER_PERIOD, 2, 5, 1);
+			run_to_block(3);
+
+			assert_noop!(
+				DexModule::cancel_dca(Origin::signed(USER), 0),
+				Error::<Test>::NotScheduleOwner
+			);
+			assert_ok_with_invariants!(DexModule::cancel_dca(Origin::signed(USER_2), 0));
+
+			check_users_balance(USER_2, ASSET_B, USER_2_BALANCE - AMOUNT_PER_PERIOD);
+			assert!(!DcaSchedules::<Test>::contains_key(0));
+			assert!(!DcaExecutionQueue::<Test>::contains_key(5));
+			assert_noop!(
+				DexModule::cancel_dca(Origin::signed(USER_2), 0),
+				Error::<Test>::ScheduleNotFound
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod conditional_order_tests {
+	use super::*;
+	use crate::{ConditionalAction, ConditionalOrders, PriceSource, PriceTrigger, SwapHandler};
+	use frame_support::{traits::Hooks, weights::Weight};
+	use sp_runtime::{FixedPointNumber, FixedU128};
+
+	const ORDER_AMOUNT: u128 = 10_000;
+
+	fn run_hook() {
+		DexModule::on_idle(System::block_number(), Weight::MAX);
+	}
+
+	fn sell_a_order(
+		trigger: PriceTrigger,
+		price_source: PriceSource,
+	) -> ConditionalAction<u32, u128> {
+		let action = ConditionalAction::Swap {
+			asset_in: ASSET_A,
+			asset_out: ASSET_B,
+			amount_in: ORDER_AMOUNT,
+			min_amount_out: 0,
+		};
+		assert_ok_with_invariants!(DexModule::place_conditional_order(
+			Origin::signed(USER_2),
+			action.clone(),
+			trigger,
+			price_source
+		));
+		action
+	}
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A], MINTED_AMOUNT);
+	}
+
+	fn swap(asset_in: u32, asset_out: u32, amount_in: u128) {
+		assert_ok_with_invariants!(<DexModule as SwapHandler<_, _, _>>::swap(
+			&USER,
+			asset_in,
+			asset_out,
+			amount_in,
+			0
+		));
+	}
+
+	#[test]
+	fn test_stop_loss_executes_when_spot_price_falls() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let action = sell_a_order(
+				PriceTrigger::Below(FixedU128::saturating_from_rational(9u32, 10u32)),
+				PriceSource::Spot,
+			);
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - ORDER_AMOUNT);
+			check_users_balance(DexModule::account_id(), ASSET_A, ORDER_AMOUNT);
+
+			run_hook();
+			assert_eq!(DexModule::conditional_orders(0).unwrap().action, action);
+
+			// Selling A drops its price below the stop
+			swap(ASSET_A, ASSET_B, ASSET_A_AMOUNT / 5);
+			let pool_liquidity = DexModule::get_pool_liquidity((ASSET_A, ASSET_B)).unwrap();
+			let expected_return =
+				get_swap_return::<u128, Test>(ORDER_AMOUNT, pool_liquidity).unwrap();
+
+			run_hook();
+			assert!(!ConditionalOrders::<Test>::contains_key(0));
+			check_users_balance(USER_2, ASSET_B, expected_return);
+			check_users_balance(DexModule::account_id(), ASSET_A, 0);
+		});
+	}
+
+	#[test]
+	fn test_take_profit_follows_twap() {
+		new_test_ext().execute_with(|| {
+			set_time_in_secs(1);
+			setup_pool();
+			sell_a_order(
+				PriceTrigger::Above(FixedU128::saturating_from_rational(3u32, 2u32)),
+				PriceSource::Twap { window: 10 },
+			);
+
+			// Buying A pushes its spot price above 2
+			set_time_in_secs(10);
+			swap(ASSET_B, ASSET_A, ASSET_B_AMOUNT / 2);
+			assert!(
+				DexModule::get_spot_price((ASSET_A, ASSET_B)).unwrap() >
+					FixedU128::saturating_from_integer(2u32)
+			);
+
+			// The average over the last 10 seconds is still mostly the old price
+			set_time_in_secs(11);
+			run_hook();
+			assert!(ConditionalOrders::<Test>::contains_key(0));
+
+			set_time_in_secs(20);
+			run_hook();
+			assert!(!ConditionalOrders::<Test>::contains_key(0));
+			assert!(Assets::balance(ASSET_B, &USER_2) > 2 * ORDER_AMOUNT * 9 / 10);
+		});
+	}
+
+	#[test]
+	fn test_lp_redeemed_when_price_leaves_range() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+			let lp_token_amount = 100_000;
+
+			assert_ok_with_invariants!(DexModule::place_conditional_order(
+				Origin::signed(USER),
+				ConditionalAction::RedeemLiquidity {
+					asset_a: ASSET_A,
+					asset_b: ASSET_B,
+					lp_token_amount,
+					min_amounts: (0, 0),
+				},
+				PriceTrigger::OutsideRange {
+					lower: FixedU128::saturating_from_rational(1u32, 2u32),
+					upper: FixedU128::saturating_from_integer(2u32),
+				},
+				PriceSource::Spot
+			));
+			check_users_balance(DexModule::account_id(), lp_token_id, lp_token_amount);
+
+			run_hook();
+			assert!(ConditionalOrders::<Test>::contains_key(0));
+
+			assert_ok_with_invariants!(<DexModule as SwapHandler<_, _, _>>::swap(
+				&USER_2,
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				0
+			));
+			let pool_liquidity = DexModule::get_pool_liquidity((ASSET_A, ASSET_B)).unwrap();
+			let total_lp_token_supply = Assets::total_supply(lp_token_id);
+			let expected_amounts =
+				get_redeemed_token_balance(lp_token_amount, total_lp_token_supply, pool_liquidity)
+					.unwrap();
+			let balances_before =
+				(Assets::balance(ASSET_A, &USER), Assets::balance(ASSET_B, &USER));
+
+			run_hook();
+			assert!(!ConditionalOrders::<Test>::contains_key(0));
+			check_users_balance(USER, ASSET_A, balances_before.0 + expected_amounts.0);
+			check_users_balance(USER, ASSET_B, balances_before.1 + expected_amounts.1);
+			check_users_balance(DexModule::account_id(), lp_token_id, 0);
+
+			// The redeemed LP tokens are taken off the owner's position
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.lp_tokens, ASSET_A_AMOUNT - lp_token_amount);
+		});
+	}
+
+	#[test]
+	fn test_executions_are_capped_per_block() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let stop = PriceTrigger::Below(FixedU128::saturating_from_rational(9u32, 10u32));
+			for _ in 0..3 {
+				sell_a_order(stop, PriceSource::Spot);
+			}
+			swap(ASSET_A, ASSET_B, ASSET_A_AMOUNT / 5);
+
+			// MaxConditionalExecutionsPerBlock is 2
+			run_hook();
+			assert_eq!(ConditionalOrders::<Test>::iter().count(), 1);
+
+			run_hook();
+			assert_eq!(ConditionalOrders::<Test>::iter().count(), 0);
+		});
+	}
+
+	#[test]
+	fn test_cancel_conditional_order_refunds_input() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			sell_a_order(PriceTrigger::Below(FixedU128::from_inner(1)), PriceSource::Spot);
+
+			assert_noop!(
+				DexModule::cancel_conditional_order(Origin::signed(USER), 0),
+				Error::<Test>::NotOrderOwner
+			);
+			assert_ok_with_invariants!(DexModule::cancel_conditional_order(
+				Origin::signed(USER_2),
+				0
+			));
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert!(!ConditionalOrders::<Test>::contains_key(0));
+		});
+	}
+
+	#[test]
+	fn test_place_conditional_order_rejects_invalid_trigger() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_noop!(
+				DexModule::place_conditional_order(
+					Origin::signed(USER_2),
+					ConditionalAction::Swap {
+						asset_in: ASSET_A,
+						asset_out: ASSET_B,
+						amount_in: ORDER_AMOUNT,
+						min_amount_out: 0,
+					},
+					PriceTrigger::OutsideRange {
+						lower: FixedU128::saturating_from_integer(2u32),
+						upper: FixedU128::saturating_from_integer(1u32),
+					},
+					PriceSource::Spot
+				),
+				Error::<Test>::InvalidOrderParameters
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod long_term_order_tests {
+	use super::*;
+	use crate::{LongTermOrderExpiries, LongTermOrderPools, LongTermOrders};
+
+	const USER_3: AccountId = 3u32;
+	const ORDER_AMOUNT: u128 = 10_000;
+	const DURATION: u64 = 10;
+
+	fn setup_pool_and_order() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A], MINTED_AMOUNT);
+		assert_ok_with_invariants!(DexModule::place_long_term_order(
+			Origin::signed(USER_2),
+			ASSET_A,
+			ASSET_B,
+			ORDER_AMOUNT,
+			DURATION
+		));
+	}
+
+	#[test]
+	fn test_place_long_term_order_escrows_funds() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_order();
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - ORDER_AMOUNT);
+			check_users_balance(DexModule::account_id(), ASSET_A, ORDER_AMOUNT);
+			let order = DexModule::long_term_orders(0).unwrap();
+			assert_eq!(order.sell_rate, ORDER_AMOUNT / DURATION as u128);
+			assert_eq!(order.end_block, 1 + DURATION);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert_eq!(DexModule::long_term_order_expiries(&pool_id).into_inner(), vec![(11, 0)]);
+		});
+	}
+
+	#[test]
+	fn test_long_term_order_settled_when_pool_is_used() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_order();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			// Nothing happens until the pool is used
+			System::set_block_number(6);
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT);
+
+			// Five blocks have been sold by the time the swap executes
+			give_user_asset(USER_3, ASSET_B, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_3),
+				ASSET_B,
+				ASSET_A,
+				1_000,
+				false,
+				USER_3
+			));
+			let virtual_return =
+				get_swap_return::<u128, Test>(ORDER_AMOUNT / 2, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			let swap_return = get_swap_return::<u128, Test>(
+				1_000,
+				(ASSET_B_AMOUNT - virtual_return, ASSET_A_AMOUNT + ORDER_AMOUNT / 2),
+			)
+			.unwrap();
+			check_users_balance(USER_3, ASSET_A, swap_return);
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + ORDER_AMOUNT / 2 - swap_return);
+			assert_eq!(DexModule::long_term_order_pools(&pool_id).unwrap().last_settled_block, 6);
+		});
+	}
+
+	#[test]
+	fn test_long_term_order_completes_and_pays_out() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_order();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			System::set_block_number(20);
+			assert_ok_with_invariants!(DexModule::settle_long_term_orders((ASSET_A, ASSET_B)));
+
+			let expected_return =
+				get_swap_return::<u128, Test>(ORDER_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			check_users_balance(USER_2, ASSET_B, expected_return);
+			check_users_balance(DexModule::account_id(), ASSET_A, 0);
+			assert!(!LongTermOrders::<Test>::contains_key(0));
+			assert!(!LongTermOrderPools::<Test>::contains_key(&pool_id));
+			assert!(!LongTermOrderExpiries::<Test>::contains_key(&pool_id));
+		});
+	}
+
+	#[test]
+	fn test_opposing_long_term_orders_are_matched_without_moving_the_pool() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_order();
+			give_user_asset(USER_3, ASSET_B, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_long_term_order(
+				Origin::signed(USER_3),
+				ASSET_B,
+				ASSET_A,
+				ORDER_AMOUNT,
+				DURATION
+			));
+
+			System::set_block_number(20);
+			assert_ok_with_invariants!(DexModule::settle_long_term_orders((ASSET_A, ASSET_B)));
+
+			check_users_balance(USER_2, ASSET_B, ORDER_AMOUNT);
+			check_users_balance(USER_3, ASSET_A, ORDER_AMOUNT);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT);
+			check_users_balance(pool_id, ASSET_B, ASSET_B_AMOUNT);
+		});
+	}
+
+	#[test]
+	fn test_cancel_long_term_order_refunds_unsold_amount() {
+		new_test_ext().execute_with(|| {
+			setup_pool_and_order();
+
+			System::set_block_number(5);
+			assert_noop!(
+				DexModule::cancel_long_term_order(Origin::signed(USER), 0),
+				Error::<Test>::NotOrderOwner
+			);
+			assert_ok_with_invariants!(DexModule::cancel_long_term_order(
+				Origin::signed(USER_2),
+				0
+			));
+
+			// Four of the ten blocks were sold
+			let sold_amount = 4 * ORDER_AMOUNT / DURATION as u128;
+			let expected_return =
+				get_swap_return::<u128, Test>(sold_amount, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - sold_amount);
+			check_users_balance(USER_2, ASSET_B, expected_return);
+			assert!(!LongTermOrders::<Test>::contains_key(0));
+		});
+	}
+}
+
+#[cfg(test)]
+mod batch_auction_tests {
+	use super::*;
+	use crate::{Event as DexEvent, PendingBatchSwaps};
+	use frame_support::traits::Hooks;
+	use sp_runtime::DispatchError;
+
+	const USER_3: AccountId = 3u32;
+
+	fn setup_batch_pool() {
+		setup_pool_with_traders(&[USER_2, USER_3], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+		assert_ok_with_invariants!(DexModule::set_batch_auction_mode(
+			Origin::root(),
+			ASSET_A,
+			ASSET_B,
+			true
+		));
+	}
+
+	fn clear_block() {
+		DexModule::on_finalize(System::block_number());
+	}
+
+	#[test]
+	fn test_batch_auction_mode_is_admin_only() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			assert_noop!(
+				DexModule::set_batch_auction_mode(Origin::signed(USER), ASSET_A, ASSET_B, true),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(
+				DexModule::submit_batch_swap(Origin::signed(USER), ASSET_A, ASSET_B, 1_000, 0),
+				Error::<Test>::PoolNotInBatchAuctionMode
+			);
+		});
+	}
+
+	#[test]
+	fn test_batch_auction_mode_requires_pool() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				DexModule::set_batch_auction_mode(Origin::root(), ASSET_A, ASSET_B, true),
+				Error::<Test>::PoolNotFound
+			);
+		});
+	}
+
+	#[test]
+	fn test_swap_rejected_in_batch_auction_mode() {
+		new_test_ext().execute_with(|| {
+			setup_batch_pool();
+			assert_noop!(
+				DexModule::swap(Origin::signed(USER_2), ASSET_A, ASSET_B, 1_000, false, USER_2),
+				Error::<Test>::PoolInBatchAuctionMode
+			);
+		});
+	}
+
+	#[test]
+	fn test_one_sided_batch_shares_pool_return() {
+		new_test_ext().execute_with(|| {
+			setup_batch_pool();
+			assert_ok_with_invariants!(DexModule::submit_batch_swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				0
+			));
+			assert_ok_with_invariants!(DexModule::submit_batch_swap(
+				Origin::signed(USER_3),
+				ASSET_A,
+				ASSET_B,
+				3_000,
+				0
+			));
+			check_users_balance(DexModule::account_id(), ASSET_A, 4_000);
+
+			clear_block();
+
+			let total_return =
+				get_swap_return::<u128, Test>(4_000, (ASSET_A_AMOUNT, ASSET_B_AMOUNT)).unwrap();
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - 1_000);
+			check_users_balance(USER_2, ASSET_B, MINTED_AMOUNT + total_return / 4);
+			check_users_balance(USER_3, ASSET_B, MINTED_AMOUNT + total_return * 3 / 4);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + 4_000);
+			assert!(!PendingBatchSwaps::<Test>::contains_key(&pool_id));
+		});
+	}
+
+	#[test]
+	fn test_opposing_swaps_matched_at_one_price() {
+		new_test_ext().execute_with(|| {
+			setup_batch_pool();
+			assert_ok_with_invariants!(DexModule::submit_batch_swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				0
+			));
+			assert_ok_with_invariants!(DexModule::submit_batch_swap(
+				Origin::signed(USER_3),
+				ASSET_B,
+				ASSET_A,
+				4_000,
+				0
+			));
+
+			clear_block();
+
+			// Only the excess of asset A is swapped through the pool
+			let pool_input = get_batch_pool_input::<u128, Test>(
+				10_000,
+				4_000,
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+			)
+			.unwrap();
+			let swap_return =
+				get_swap_return::<u128, Test>(pool_input, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + pool_input);
+			check_users_balance(pool_id, ASSET_B, ASSET_B_AMOUNT - swap_return);
+			check_users_balance(USER_2, ASSET_B, MINTED_AMOUNT + 4_000 + swap_return);
+			check_users_balance(USER_3, ASSET_A, MINTED_AMOUNT + 10_000 - pool_input);
+		});
+	}
+
+	#[test]
+	fn test_swap_below_minimum_is_refunded() {
+		new_test_ext().execute_with(|| {
+			setup_batch_pool();
+			assert_ok_with_invariants!(DexModule::submit_batch_swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				0
+			));
+			// The swap fee alone keeps the return below 1_000
+			assert_ok_with_invariants!(DexModule::submit_batch_swap(
+				Origin::signed(USER_3),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				1_000
+			));
+
+			clear_block();
+
+			let swap_return =
+				get_swap_return::<u128, Test>(1_000, (ASSET_A_AMOUNT, ASSET_B_AMOUNT)).unwrap();
+			check_users_balance(USER_2, ASSET_B, MINTED_AMOUNT + swap_return);
+			check_users_balance(USER_3, ASSET_A, MINTED_AMOUNT);
+			check_users_balance(USER_3, ASSET_B, MINTED_AMOUNT);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			System::assert_has_event(
+				DexEvent::<Test>::BatchSwapRefunded { pool_id, who: USER_3, amount_in: 1_000 }
+					.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_batch_clearing_weight_reserved_on_initialize() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			let weight = DexModule::on_initialize(System::block_number());
+
+			assert_ok_with_invariants!(DexModule::set_batch_auction_mode(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				true
+			));
+			assert_eq!(
+				DexModule::on_initialize(System::block_number()),
+				weight + DexModule::batch_auction_clearing_weight()
+			);
+
+			assert_ok_with_invariants!(DexModule::set_batch_auction_mode(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				false
+			));
+			assert_eq!(DexModule::on_initialize(System::block_number()), weight);
+		});
+	}
+}
+
+#[cfg(test)]
+mod commit_reveal_tests {
+	use super::*;
+	use crate::{CommitmentExpiries, SwapCommitments};
+	use frame_support::{
+		dispatch::DispatchResult,
+		traits::{Hooks, ReservableCurrency},
+	};
+	use sp_core::H256;
+
+	const USER_3: AccountId = 3u32;
+	const SWAP_AMOUNT: u128 = 10_000;
+	const SALT: [u8; 32] = [7u8; 32];
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2, USER_3], &[ASSET_A], MINTED_AMOUNT);
+	}
+
+	fn commit(user: AccountId, amount: u128, min_amount_out: u128) -> H256 {
+		let commitment = DexModule::swap_commitment_hash(
+			&user,
+			(ASSET_A, ASSET_B),
+			amount,
+			min_amount_out,
+			&SALT,
+		);
+		assert_ok_with_invariants!(DexModule::commit_swap(Origin::signed(user), commitment));
+		commitment
+	}
+
+	fn reveal(user: AccountId, amount: u128, min_amount_out: u128) -> DispatchResult {
+		DexModule::reveal_swap(Origin::signed(user), ASSET_A, ASSET_B, amount, min_amount_out, SALT)
+	}
+
+	#[test]
+	fn test_revealed_swap_is_executed() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let commitment = commit(USER_2, SWAP_AMOUNT, 0);
+			assert_eq!(Balances::reserved_balance(&USER_2), CommitmentDeposit::get());
+
+			System::set_block_number(2);
+			assert_ok_with_invariants!(reveal(USER_2, SWAP_AMOUNT, 0));
+
+			let swap_return =
+				get_swap_return::<u128, Test>(SWAP_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - SWAP_AMOUNT);
+			check_users_balance(USER_2, ASSET_B, swap_return);
+			assert_eq!(Balances::reserved_balance(&USER_2), 0);
+			assert!(!SwapCommitments::<Test>::contains_key(commitment));
+			assert!(!CommitmentExpiries::<Test>::contains_key(7));
+		});
+	}
+
+	#[test]
+	fn test_reveal_rejected_in_commitment_block() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			commit(USER_2, SWAP_AMOUNT, 0);
+			assert_noop!(reveal(USER_2, SWAP_AMOUNT, 0), Error::<Test>::RevealTooEarly);
+		});
+	}
+
+	#[test]
+	fn test_reveal_must_match_commitment() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let commitment = commit(USER_2, SWAP_AMOUNT, 0);
+			assert_noop!(
+				DexModule::commit_swap(Origin::signed(USER_2), commitment),
+				Error::<Test>::DuplicateCommitment
+			);
+
+			System::set_block_number(2);
+			assert_noop!(reveal(USER_2, SWAP_AMOUNT + 1, 0), Error::<Test>::CommitmentNotFound);
+			assert_noop!(reveal(USER_3, SWAP_AMOUNT, 0), Error::<Test>::CommitmentNotFound);
+			assert_noop!(
+				reveal(USER_2, SWAP_AMOUNT, ASSET_B_AMOUNT),
+				Error::<Test>::SlippageExceeded
+			);
+		});
+	}
+
+	#[test]
+	fn test_swaps_execute_in_reveal_order() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			commit(USER_2, SWAP_AMOUNT, 0);
+			commit(USER_3, SWAP_AMOUNT, 0);
+
+			// The later commitment is revealed first and gets the better price
+			System::set_block_number(2);
+			assert_ok_with_invariants!(reveal(USER_3, SWAP_AMOUNT, 0));
+			assert_ok_with_invariants!(reveal(USER_2, SWAP_AMOUNT, 0));
+
+			let first_return =
+				get_swap_return::<u128, Test>(SWAP_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			let second_return = get_swap_return::<u128, Test>(
+				SWAP_AMOUNT,
+				(ASSET_A_AMOUNT + SWAP_AMOUNT, ASSET_B_AMOUNT - first_return),
+			)
+			.unwrap();
+			check_users_balance(USER_3, ASSET_B, first_return);
+			check_users_balance(USER_2, ASSET_B, second_return);
+			assert!(second_return < first_return);
+		});
+	}
+
+	#[test]
+	fn test_unrevealed_commitment_forfeits_deposit() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let commitment = commit(USER_2, SWAP_AMOUNT, 0);
+			let issuance = Balances::total_issuance();
+
+			// The reveal window closes after block 6
+			System::set_block_number(7);
+			assert_noop!(reveal(USER_2, SWAP_AMOUNT, 0), Error::<Test>::RevealTooLate);
+
+			DexModule::on_initialize(7);
+			assert!(!SwapCommitments::<Test>::contains_key(commitment));
+			assert!(!CommitmentExpiries::<Test>::contains_key(7));
+			assert_eq!(Balances::reserved_balance(&USER_2), 0);
+			assert_eq!(Balances::free_balance(&USER_2), MINTED_AMOUNT - CommitmentDeposit::get());
+			assert_eq!(Balances::total_issuance(), issuance - CommitmentDeposit::get());
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_noop!(reveal(USER_2, SWAP_AMOUNT, 0), Error::<Test>::CommitmentNotFound);
+		});
+	}
+}
+
+#[cfg(test)]
+mod price_limit_tests {
+	use super::*;
+	use crate::{Event as DexEvent, PriceLimits, PriceReference};
+	use sp_runtime::{traits::Dispatchable, DispatchError, Permill};
+
+	fn setup_pool_with_limits(max_price_impact: Option<u32>, max_price_movement: Option<u32>) {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A], MINTED_AMOUNT);
+		assert_ok_with_invariants!(DexModule::set_price_limits(
+			Origin::root(),
+			ASSET_A,
+			ASSET_B,
+			Some(PriceLimits {
+				max_price_impact: max_price_impact.map(Permill::from_percent),
+				max_price_movement: max_price_movement.map(Permill::from_percent),
+				reference: PriceReference::OpeningPrice,
+			})
+		));
+	}
+
+	#[test]
+	fn test_price_limits_are_admin_only() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				DexModule::set_price_limits(Origin::signed(USER), ASSET_A, ASSET_B, None),
+				DispatchError::BadOrigin
+			);
+		});
+	}
+
+	#[test]
+	fn test_price_limits_require_pool() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				DexModule::set_price_limits(Origin::root(), ASSET_A, ASSET_B, None),
+				Error::<Test>::PoolNotFound
+			);
+		});
+	}
+
+	#[test]
+	fn test_swap_exceeding_price_impact_is_rejected() {
+		new_test_ext().execute_with(|| {
+			setup_pool_with_limits(Some(1), None);
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				false,
+				USER_2
+			));
+			assert_noop!(
+				DexModule::swap(Origin::signed(USER_2), ASSET_A, ASSET_B, 20_000, false, USER_2),
+				Error::<Test>::PriceImpactTooHigh
+			);
+
+			// Removing the limits allows the swap
+			assert_ok_with_invariants!(DexModule::set_price_limits(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				None
+			));
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				20_000,
+				false,
+				USER_2
+			));
+		});
+	}
+
+	#[test]
+	fn test_circuit_breaker_halts_pool_for_the_block() {
+		new_test_ext().execute_with(|| {
+			setup_pool_with_limits(None, Some(3));
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			// Each swap moves the price by about 2%, the second would leave the 3% band
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				false,
+				USER_2
+			));
+			let price = DexModule::get_limit_price((ASSET_A, ASSET_B)).unwrap();
+
+			// Dispatched like an extrinsic, the swap is reverted but the trip is kept
+			let swap = Call::DexModule(crate::Call::swap {
+				asset_a: ASSET_A,
+				asset_b: ASSET_B,
+				asset_a_amount: 10_000,
+				keep_alive: false,
+				recipient: USER_2,
+			});
+			assert_ok_with_invariants!(swap.dispatch(Origin::signed(USER_2)));
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - 10_000);
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + 10_000);
+			System::assert_has_event(
+				DexEvent::<Test>::CircuitBreakerTripped { pool_id, price }.into(),
+			);
+			assert_noop!(
+				DexModule::swap(Origin::signed(USER_2), ASSET_A, ASSET_B, 100, false, USER_2),
+				Error::<Test>::CircuitBreakerTripped
+			);
+
+			// The next block opens at the current price
+			System::set_block_number(2);
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				false,
+				USER_2
+			));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT + 20_000);
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_status_tests {
+	use super::*;
+	use crate::{Event as DexEvent, PoolStatus};
+	use sp_runtime::{DispatchError, FixedU128};
+
+	const LIQUIDITY_AMOUNT: u128 = 500_000;
+
+	fn setup_pool_with_status(status: PoolStatus) {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+		assert_ok_with_invariants!(DexModule::provide_liquidity(
+			Origin::signed(USER_2),
+			ASSET_A,
+			ASSET_B,
+			LIQUIDITY_AMOUNT,
+			false
+		));
+		assert_ok_with_invariants!(DexModule::set_pool_status(
+			Origin::root(),
+			ASSET_A,
+			ASSET_B,
+			status
+		));
+	}
+
+	fn lp_token_balance(user: AccountId) -> u128 {
+		let lp_token_id = DexModule::get_lp_token_id(&DexModule::get_pool_id((ASSET_A, ASSET_B)));
+		Assets::balance(lp_token_id, &user)
+	}
+
+	#[test]
+	fn test_pool_status_is_admin_only() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				DexModule::set_pool_status(
+					Origin::signed(USER),
+					ASSET_A,
+					ASSET_B,
+					PoolStatus::Paused
+				),
+				DispatchError::BadOrigin
+			);
+		});
+	}
+
+	#[test]
+	fn test_swaps_paused_pool_accepts_liquidity_only() {
+		new_test_ext().execute_with(|| {
+			setup_pool_with_status(PoolStatus::SwapsPaused);
+
+			assert_noop!(
+				DexModule::swap(Origin::signed(USER_2), ASSET_A, ASSET_B, 1_000, false, USER_2),
+				Error::<Test>::PoolSwapsPaused
+			);
+			assert_noop!(
+				DexModule::place_limit_order(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					1_000,
+					FixedU128::from_inner(500_000_000_000_000_000)
+				),
+				Error::<Test>::PoolSwapsPaused
+			);
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				false
+			));
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				USER_2
+			));
+		});
+	}
+
+	#[test]
+	fn test_paused_pool_allows_redemptions_only() {
+		new_test_ext().execute_with(|| {
+			setup_pool_with_status(PoolStatus::Paused);
+
+			assert_noop!(
+				DexModule::swap(Origin::signed(USER_2), ASSET_A, ASSET_B, 1_000, false, USER_2),
+				Error::<Test>::PoolSwapsPaused
+			);
+			assert_noop!(
+				DexModule::provide_liquidity(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					1_000,
+					false
+				),
+				Error::<Test>::PoolPaused
+			);
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				USER_2
+			));
+
+			// Trading resumes once the pool is active again
+			assert_ok_with_invariants!(DexModule::set_pool_status(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				PoolStatus::Active
+			));
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				false,
+				USER_2
+			));
+		});
+	}
+
+	#[test]
+	fn test_delisted_pool_returns_liquidity_to_holders() {
+		new_test_ext().execute_with(|| {
+			setup_pool_with_status(PoolStatus::Active);
+			assert_noop!(
+				DexModule::force_return_liquidity(
+					Origin::root(),
+					ASSET_A,
+					ASSET_B,
+					vec![USER, USER_2].try_into().unwrap()
+				),
+				Error::<Test>::PoolNotDelisted
+			);
+
+			assert_ok_with_invariants!(DexModule::set_pool_status(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				PoolStatus::Delisted
+			));
+			assert_noop!(
+				DexModule::set_pool_status(Origin::root(), ASSET_A, ASSET_B, PoolStatus::Active),
+				Error::<Test>::PoolDelisted
+			);
+
+			let user_2_lp_tokens = lp_token_balance(USER_2);
+			assert_ok_with_invariants!(DexModule::force_return_liquidity(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				vec![USER, USER_2].try_into().unwrap()
+			));
+
+			// Each holder got their share, the pool holds nothing anymore
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, 0);
+			check_users_balance(pool_id, ASSET_B, 0);
+			assert_eq!(lp_token_balance(USER), 0);
+			assert_eq!(lp_token_balance(USER_2), 0);
+			check_users_balance(USER, ASSET_A, MINTED_AMOUNT);
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+			System::assert_has_event(
+				DexEvent::<Test>::LiquidityForceReturned {
+					pool_id,
+					who: USER_2,
+					lp_token_amount: user_2_lp_tokens,
+					redeemed_token_amounts: (LIQUIDITY_AMOUNT, LIQUIDITY_AMOUNT),
+				}
+				.into(),
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_creation_policy_tests {
+	use super::*;
+	use crate::{AssetListMode, PoolCreationDeposits};
+	use frame_support::traits::ReservableCurrency;
+	use sp_runtime::DispatchError;
+
+	#[test]
+	fn test_create_pool_reserves_deposit() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			let reserved = Balances::reserved_balance(&USER);
+			assert_ok_with_invariants!(create_pool());
+
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert_eq!(Balances::reserved_balance(&USER), reserved + PoolCreationDeposit::get());
+			assert_eq!(
+				PoolCreationDeposits::<Test>::get(&pool_id),
+				Some((USER, PoolCreationDeposit::get()))
+			);
+		});
+	}
+
+	#[test]
+	fn test_asset_list_is_admin_only() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				DexModule::set_asset_listed(Origin::signed(USER), ASSET_A, true),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(
+				DexModule::set_asset_list_mode(Origin::signed(USER), AssetListMode::AllowList),
+				DispatchError::BadOrigin
+			);
+		});
+	}
+
+	#[test]
+	fn test_denied_asset_cannot_be_pooled() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::set_asset_listed(Origin::root(), ASSET_B, true));
+			assert_noop!(create_pool(), Error::<Test>::AssetNotAllowed);
+
+			assert_ok_with_invariants!(DexModule::set_asset_listed(Origin::root(), ASSET_B, false));
+			assert_ok_with_invariants!(create_pool());
+		});
+	}
+
+	#[test]
+	fn test_allow_list_requires_both_assets() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::set_asset_list_mode(
+				Origin::root(),
+				AssetListMode::AllowList
+			));
+			assert_ok_with_invariants!(DexModule::set_asset_listed(Origin::root(), ASSET_A, true));
+			assert_noop!(create_pool(), Error::<Test>::AssetNotAllowed);
+
+			assert_ok_with_invariants!(DexModule::set_asset_listed(Origin::root(), ASSET_B, true));
+			assert_ok_with_invariants!(create_pool());
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_destruction_tests {
+	use super::*;
+	use crate::{
+		ConditionalAction, Event as DexEvent, PoolCreationDeposits, PoolStatus, PoolStatuses,
+		PoolTeardowns, Pools, PriceObservations, PriceSource, PriceTrigger,
+	};
+	use frame_support::traits::ReservableCurrency;
+	use sp_runtime::{DispatchError, FixedPointNumber, FixedU128, Permill};
+
+	const LP_TOKEN_SUPPLY: u128 = 1_000_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[], &[], 0);
+	}
+
+	fn redeem_all() {
+		assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+			Origin::signed(USER),
+			ASSET_A,
+			ASSET_B,
+			LP_TOKEN_SUPPLY,
+			USER
+		));
+	}
+
+	#[test]
+	fn test_pool_destroyed_when_last_lp_tokens_redeemed() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+			assert_ok_with_invariants!(DexModule::set_pool_status(
+				Origin::root(),
+				ASSET_A,
+				ASSET_B,
+				PoolStatus::SwapsPaused
+			));
+			let reserved = Balances::reserved_balance(&USER);
+
+			redeem_all();
+			System::assert_has_event(
+				DexEvent::<Test>::PoolTeardownScheduled { pool_id, asset_pair: (ASSET_A, ASSET_B) }
+					.into(),
+			);
+			assert!(pallet_assets::Asset::<Test>::contains_key(lp_token_id));
+
+			tear_down_pools();
+			assert!(!PoolTeardowns::<Test>::contains_key(&pool_id));
+			System::assert_has_event(
+				DexEvent::<Test>::PoolDestroyed { pool_id, asset_pair: (ASSET_A, ASSET_B) }.into(),
+			);
+			assert!(!pallet_assets::Asset::<Test>::contains_key(lp_token_id));
+			assert!(!System::account_exists(&pool_id));
+			assert_eq!(Balances::reserved_balance(&USER), reserved - PoolCreationDeposit::get());
+			assert!(!PoolStatuses::<Test>::contains_key(&pool_id));
+			assert!(!PriceObservations::<Test>::contains_key(&pool_id));
+			check_users_balance(USER, ASSET_A, MINTED_AMOUNT);
+			check_users_balance(USER, ASSET_B, MINTED_AMOUNT);
+		});
+	}
+
+	#[test]
+	fn test_destroyed_pool_can_be_created_again() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			redeem_all();
+			tear_down_pools();
+
+			assert_ok_with_invariants!(DexModule::create_pool(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				ASSET_B_AMOUNT,
+				false
+			));
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			check_users_balance(pool_id, ASSET_A, ASSET_A_AMOUNT);
+			check_users_balance(USER, DexModule::get_lp_token_id(&pool_id), LP_TOKEN_SUPPLY);
+		});
+	}
+
+	#[test]
+	fn test_destroy_pool_requires_empty_pool() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				DexModule::destroy_pool(Origin::root(), ASSET_A, ASSET_B),
+				Error::<Test>::PoolNotFound
+			);
+
+			setup_pool();
+			assert_noop!(
+				DexModule::destroy_pool(Origin::signed(USER), ASSET_A, ASSET_B),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(
+				DexModule::destroy_pool(Origin::root(), ASSET_A, ASSET_B),
+				Error::<Test>::PoolNotEmpty
+			);
+		});
+	}
+
+	#[test]
+	fn test_open_long_term_orders_keep_pool_alive() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_long_term_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				10
+			));
+
+			redeem_all();
+			tear_down_pools();
+
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+			assert!(!PoolTeardowns::<Test>::contains_key(&pool_id));
+			assert!(pallet_assets::Asset::<Test>::contains_key(lp_token_id));
+			assert_noop!(
+				DexModule::destroy_pool(Origin::root(), ASSET_A, ASSET_B),
+				Error::<Test>::PoolNotEmpty
+			);
+
+			// Once the order is cancelled nothing depends on the pool anymore
+			assert_ok_with_invariants!(DexModule::cancel_long_term_order(
+				Origin::signed(USER_2),
+				0
+			));
+			assert_ok_with_invariants!(DexModule::destroy_pool(Origin::root(), ASSET_A, ASSET_B));
+			tear_down_pools();
+			assert!(!pallet_assets::Asset::<Test>::contains_key(lp_token_id));
+		});
+	}
+
+	#[test]
+	fn test_open_orders_refunded_on_teardown() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				FixedU128::saturating_from_integer(2u32)
+			));
+			assert_ok_with_invariants!(DexModule::schedule_dca(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				1_000,
+				10,
+				5,
+				Permill::from_percent(50)
+			));
+
+			redeem_all();
+			tear_down_pools();
+
+			assert!(DexModule::limit_orders(0).is_none());
+			assert!(DexModule::dca_schedules(0).is_none());
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+			System::assert_has_event(
+				DexEvent::<Test>::LimitOrderCancelled { order_id: 0, owner: USER_2 }.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_teardown_is_spread_over_blocks() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			give_user_asset(USER_2, ASSET_A, MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::place_limit_order(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				FixedU128::saturating_from_integer(2u32)
+			));
+			assert_ok_with_invariants!(DexModule::place_conditional_order(
+				Origin::signed(USER_2),
+				ConditionalAction::Swap {
+					asset_in: ASSET_A,
+					asset_out: ASSET_B,
+					amount_in: 10_000,
+					min_amount_out: 0,
+				},
+				PriceTrigger::Below(FixedU128::saturating_from_rational(1u32, 2u32)),
+				PriceSource::Spot
+			));
+			redeem_all();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			// Only a single order is released while room is left for destroying the pool
+			let weight = DexModule::pool_teardown_weight();
+			DexModule::process_pool_teardowns(weight);
+			assert!(DexModule::limit_orders(0).is_none());
+			assert!(DexModule::conditional_orders(1).is_some());
+
+			DexModule::process_pool_teardowns(weight);
+			assert!(DexModule::conditional_orders(1).is_none());
+			assert!(Pools::<Test>::contains_key(&pool_id));
+
+			tear_down_pools();
+			assert!(!Pools::<Test>::contains_key(&pool_id));
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT);
+		});
+	}
+
+	#[test]
+	fn test_locked_lp_tokens_do_not_keep_pool_alive() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+			assert_ok_with_invariants!(Assets::transfer(
+				Origin::signed(USER),
+				lp_token_id,
+				pool_id,
+				1_000
+			));
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				LP_TOKEN_SUPPLY - 1_000,
+				USER
+			));
+			tear_down_pools();
+
+			assert!(!pallet_assets::Asset::<Test>::contains_key(lp_token_id));
+			assert!(!System::account_exists(&pool_id));
+			// The reserves backing the locked LP tokens go to the creator
+			check_users_balance(USER, ASSET_A, MINTED_AMOUNT);
+			check_users_balance(USER, ASSET_B, MINTED_AMOUNT);
+		});
+	}
+
+	#[test]
+	fn test_pool_account_reaped_without_creation_deposit() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			PoolCreationDeposits::<Test>::remove(&pool_id);
+			let issuance = Balances::total_issuance();
+
+			redeem_all();
+			tear_down_pools();
+
+			assert!(!System::account_exists(&pool_id));
+			assert_eq!(Balances::total_issuance(), issuance - ExistentialDeposit::get());
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_account_funding_tests {
+	use super::*;
+
+	#[test]
+	fn test_creator_pays_pool_existential_deposit() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			let issuance = Balances::total_issuance();
+			let free_balance = Balances::free_balance(&USER);
+
+			assert_ok_with_invariants!(create_pool());
+
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert_eq!(Balances::free_balance(&pool_id), ExistentialDeposit::get());
+			assert_eq!(
+				Balances::free_balance(&USER),
+				free_balance - ExistentialDeposit::get() - PoolCreationDeposit::get()
+			);
+		});
+	}
+
+	#[test]
+	fn test_funded_pool_account_is_not_overwritten() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			Balances::make_free_balance_be(&USER_2, MINTED_AMOUNT);
+			assert_ok_with_invariants!(Balances::transfer(Origin::signed(USER_2), pool_id, 500));
+			let free_balance = Balances::free_balance(&USER);
+
+			assert_ok_with_invariants!(create_pool());
+
+			assert_eq!(Balances::free_balance(&pool_id), 500);
+			assert_eq!(Balances::free_balance(&USER), free_balance - PoolCreationDeposit::get());
+		});
+	}
+
+	#[test]
+	fn test_existential_deposit_returned_on_teardown() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			let issuance = Balances::total_issuance();
+			let free_balance = Balances::free_balance(&USER);
+			assert_ok_with_invariants!(create_pool());
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				USER
+			));
+			tear_down_pools();
+
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert_eq!(Balances::free_balance(&USER), free_balance);
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_invariant_tests {
+	use super::*;
+	use crate::Pools;
+	use frame_support::traits::tokens::fungibles::Mutate;
+
+	#[test]
+	fn test_pool_registered_with_sorted_asset_pair() {
+		new_test_ext().execute_with(|| {
+			create_and_give_user_two_assets(USER, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::create_pool(
+				Origin::signed(USER),
+				ASSET_B,
+				ASSET_A,
+				ASSET_B_AMOUNT,
+				ASSET_A_AMOUNT,
+				false
+			));
+
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert_eq!(DexModule::pools(&pool_id), Some((ASSET_A, ASSET_B)));
+		});
+	}
+
+	#[test]
+	fn test_invariants_hold_after_every_operation() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			assert_ok_with_invariants!(DexModule::do_try_state());
+
+			give_user_two_assets(USER_2, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT / 2,
+				false
+			));
+			assert_ok_with_invariants!(DexModule::do_try_state());
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				10_000,
+				false,
+				USER_2
+			));
+			assert_ok_with_invariants!(DexModule::do_try_state());
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_B,
+				ASSET_A,
+				25_000,
+				false,
+				USER_2
+			));
+			assert_ok_with_invariants!(DexModule::do_try_state());
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT / 2,
+				USER
+			));
+			assert_ok_with_invariants!(DexModule::do_try_state());
+		});
+	}
+
+	#[test]
+	fn test_swaps_never_decrease_constant_product() {
+		new_test_ext().execute_with(|| {
+			setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+			let constant_product = || {
+				let reserves = DexModule::get_pool_liquidity((ASSET_A, ASSET_B)).unwrap();
+				reserves.0 * reserves.1
+			};
+
+			let mut last_constant_product = constant_product();
+			let swaps = [
+				((ASSET_A, ASSET_B), 10_000),
+				((ASSET_B, ASSET_A), 25_000),
+				((ASSET_A, ASSET_B), 1_000),
+			];
+			for (asset_pair, amount) in swaps {
+				assert_ok_with_invariants!(DexModule::swap(
+					Origin::signed(USER_2),
+					asset_pair.0,
+					asset_pair.1,
+					amount,
+					false,
+					USER_2
+				));
+				assert!(constant_product() >= last_constant_product);
+				last_constant_product = constant_product();
+			}
+		});
+	}
+
+	#[test]
+	fn test_destroyed_pool_is_unregistered() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT,
+				USER
+			));
+			tear_down_pools();
+
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert!(!Pools::<Test>::contains_key(&pool_id));
+			assert_ok_with_invariants!(DexModule::do_try_state());
+		});
+	}
+
+	#[test]
+	fn test_try_state_detects_unbacked_lp_tokens() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+
+			// Tampering with the pool breaks the invariants assert_ok_with_invariants! asserts
+			assert_ok!(Assets::mint_into(lp_token_id, &USER, 1));
+			assert_eq!(
+				DexModule::do_try_state(),
+				Err("LP supply not backed by the pool's reserves")
+			);
+
+			assert_ok!(Assets::burn_from(lp_token_id, &USER, 1));
+		});
+	}
+
+	#[test]
+	fn test_try_state_detects_drained_reserves() {
+		new_test_ext().execute_with(|| {
+			create_liquidity_pool(
+				USER,
+				(ASSET_A, ASSET_B),
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				MINTED_AMOUNT,
+			);
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			assert_ok!(Assets::transfer(Origin::signed(pool_id), ASSET_A, USER, ASSET_A_AMOUNT));
+			assert_eq!(DexModule::do_try_state(), Err("LP tokens outstanding on an empty pool"));
+
+			assert_ok_with_invariants!(Assets::transfer(
+				Origin::signed(USER),
+				ASSET_A,
+				USER,
+				ASSET_A_AMOUNT
+			));
+		});
+	}
+}
+
+#[cfg(test)]
+mod withdrawal_check_tests {
+	use super::*;
+
+	const USER_BALANCE: u128 = 10_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], USER_BALANCE);
+	}
+
+	#[test]
+	fn test_swap_of_frozen_tokens_fails() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			assert_ok_with_invariants!(Assets::freeze(Origin::signed(USER), ASSET_A, USER_2));
+
+			assert_noop!(
+				DexModule::swap(Origin::signed(USER_2), ASSET_A, ASSET_B, 1_000, false, USER_2),
+				Error::<Test>::BalanceFrozen
+			);
+		});
+	}
+
+	#[test]
+	fn test_keep_alive_swap_cannot_empty_account() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_noop!(
+				DexModule::swap(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					USER_BALANCE,
+					true,
+					USER_2
+				),
+				Error::<Test>::WouldKillAccount
+			);
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				USER_BALANCE - 1,
+				true,
+				USER_2
+			));
+		});
+	}
+
+	#[test]
+	fn test_keep_alive_applies_to_the_swap_transfer() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			// Even without the extrinsic's checks the transfer keeps the account alive
+			assert!(DexModule::process_swap(
+				&USER_2,
+				&USER_2,
+				(ASSET_A, ASSET_B),
+				USER_BALANCE,
+				true
+			)
+			.is_err());
+			check_users_balance(USER_2, ASSET_A, USER_BALANCE);
+		});
+	}
+
+	#[test]
+	fn test_swap_can_empty_account_without_keep_alive() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				USER_BALANCE,
+				false,
+				USER_2
+			));
+			check_users_balance(USER_2, ASSET_A, 0);
+		});
+	}
+
+	#[test]
+	fn test_keep_alive_deposit_cannot_empty_account() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_noop!(
+				DexModule::provide_liquidity(
+					Origin::signed(USER_2),
+					ASSET_A,
+					ASSET_B,
+					USER_BALANCE,
+					true
+				),
+				Error::<Test>::WouldKillAccount
+			);
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				USER_BALANCE,
+				false
+			));
+		});
+	}
+
+	#[test]
+	fn test_redemption_of_frozen_lp_tokens_fails() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+			assert_ok_with_invariants!(Assets::freeze(
+				Origin::signed(DexModule::account_id()),
+				lp_token_id,
+				USER
+			));
+
+			assert_noop!(
+				DexModule::redeem_lp_tokens(Origin::signed(USER), ASSET_A, ASSET_B, 1_000, USER),
+				Error::<Test>::BalanceFrozen
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod delegated_trading_tests {
+	use super::*;
+
+	const USER_3: AccountId = 3u32;
+	const SWAP_AMOUNT: u128 = 10_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+		Balances::make_free_balance_be(&USER_3, MINTED_AMOUNT);
+	}
+
+	fn lp_token_id() -> u32 {
+		DexModule::get_lp_token_id(&DexModule::get_pool_id((ASSET_A, ASSET_B)))
+	}
+
+	#[test]
+	fn test_swap_sends_return_to_recipient() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				SWAP_AMOUNT,
+				false,
+				USER_3
+			));
+
+			let expected_return =
+				get_swap_return::<u128, Test>(SWAP_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - SWAP_AMOUNT);
+			check_users_balance(USER_2, ASSET_B, MINTED_AMOUNT);
+			check_users_balance(USER_3, ASSET_B, expected_return);
+		});
+	}
+
+	#[test]
+	fn test_redemption_sends_liquidity_to_recipient() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let lp_token_id = lp_token_id();
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				ASSET_A_AMOUNT / 2,
+				USER_3
+			));
+
+			check_users_balance(USER, lp_token_id, ASSET_A_AMOUNT / 2);
+			check_users_balance(USER, ASSET_A, MINTED_AMOUNT - ASSET_A_AMOUNT);
+			check_users_balance(USER_3, ASSET_A, ASSET_A_AMOUNT / 2);
+			check_users_balance(USER_3, ASSET_B, ASSET_B_AMOUNT / 2);
+		});
+	}
+
+	#[test]
+	fn test_swap_from_spends_approval() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			assert_ok_with_invariants!(Assets::approve_transfer(
+				Origin::signed(USER_2),
+				ASSET_A,
+				USER_3,
+				SWAP_AMOUNT
+			));
+
+			assert_ok_with_invariants!(DexModule::swap_from(
+				Origin::signed(USER_3),
+				USER_2,
+				ASSET_A,
+				ASSET_B,
+				SWAP_AMOUNT,
+				false,
+				USER_3
+			));
+
+			let expected_return =
+				get_swap_return::<u128, Test>(SWAP_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - SWAP_AMOUNT);
+			check_users_balance(USER_3, ASSET_B, expected_return);
+			check_users_balance(DexModule::account_id(), ASSET_A, 0);
+			// The swap is made by the owner
+			assert!(System::events().iter().any(|record| matches!(
+				record.event,
+				Event::DexModule(crate::Event::TokensSwapped { who: USER_2, recipient: USER_3, .. })
+			)));
+
+			// The approval has been used up
+			assert_noop!(
+				DexModule::swap_from(
+					Origin::signed(USER_3),
+					USER_2,
+					ASSET_A,
+					ASSET_B,
+					1,
+					false,
+					USER_3
+				),
+				Error::<Test>::InsufficientAllowance
+			);
+		});
+	}
+
+	#[test]
+	fn test_swap_from_requires_approval() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_noop!(
+				DexModule::swap_from(
+					Origin::signed(USER_3),
+					USER_2,
+					ASSET_A,
+					ASSET_B,
+					SWAP_AMOUNT,
+					false,
+					USER_3
+				),
+				Error::<Test>::InsufficientAllowance
+			);
+		});
+	}
+
+	#[test]
+	fn test_add_liquidity_from_mints_lp_tokens_to_owner() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			for asset in [ASSET_A, ASSET_B] {
+				assert_ok_with_invariants!(Assets::approve_transfer(
+					Origin::signed(USER_2),
+					asset,
+					USER_3,
+					SWAP_AMOUNT
+				));
+			}
+
+			assert_ok_with_invariants!(DexModule::add_liquidity_from(
+				Origin::signed(USER_3),
+				USER_2,
+				ASSET_A,
+				ASSET_B,
+				SWAP_AMOUNT,
+				false
+			));
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - SWAP_AMOUNT);
+			check_users_balance(USER_2, ASSET_B, MINTED_AMOUNT - SWAP_AMOUNT);
+			check_lp_tokens_sent_to_provider(
+				USER_2,
+				(ASSET_A, ASSET_B),
+				SWAP_AMOUNT,
+				ASSET_A_AMOUNT,
+				ASSET_A_AMOUNT,
+			);
+			let lp_token_id = lp_token_id();
+			check_users_balance(USER_3, lp_token_id, 0);
+		});
+	}
+
+	#[test]
+	fn test_add_liquidity_from_requires_approval_of_both_assets() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			assert_ok_with_invariants!(Assets::approve_transfer(
+				Origin::signed(USER_2),
+				ASSET_A,
+				USER_3,
+				SWAP_AMOUNT
+			));
+
+			assert_noop!(
+				DexModule::add_liquidity_from(
+					Origin::signed(USER_3),
+					USER_2,
+					ASSET_A,
+					ASSET_B,
+					SWAP_AMOUNT,
+					false
+				),
+				Error::<Test>::InsufficientAllowance
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod batch_operation_tests {
+	use super::*;
+	use crate::{DexOperation, Event as DexEvent};
+
+	const SWAP_AMOUNT: u128 = 10_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+	}
+
+	fn lp_token_balance(user: AccountId) -> u128 {
+		let lp_token_id = DexModule::get_lp_token_id(&DexModule::get_pool_id((ASSET_A, ASSET_B)));
+		Assets::balance(lp_token_id, &user)
+	}
+
+	fn swap(min_amount_out: u128) -> DexOperation<u32, u128> {
+		DexOperation::Swap {
+			asset_in: ASSET_A,
+			asset_out: ASSET_B,
+			amount_in: SWAP_AMOUNT,
+			min_amount_out,
+		}
+	}
+
+	fn add_liquidity() -> DexOperation<u32, u128> {
+		DexOperation::AddLiquidity {
+			asset_a: ASSET_A,
+			asset_b: ASSET_B,
+			amount_a: SWAP_AMOUNT,
+			max_amount_b: MINTED_AMOUNT,
+			min_lp_tokens: 0,
+		}
+	}
+
+	#[test]
+	fn test_batch_executes_operations_in_order() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let swap_return =
+				get_swap_return::<u128, Test>(SWAP_AMOUNT, (ASSET_A_AMOUNT, ASSET_B_AMOUNT))
+					.unwrap();
+
+			assert_ok_with_invariants!(DexModule::batch(
+				Origin::signed(USER_2),
+				vec![swap(0), add_liquidity()].try_into().unwrap(),
+				true
+			));
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - 2 * SWAP_AMOUNT);
+			let lp_tokens = lp_token_balance(USER_2);
+			assert!(lp_tokens > 0);
+			System::assert_has_event(
+				DexEvent::<Test>::BatchExecuted { who: USER_2, operations: 2, failed: vec![] }
+					.into(),
+			);
+
+			// The liquidity can be taken out again in a later batch
+			let remove_liquidity = DexOperation::RemoveLiquidity {
+				asset_a: ASSET_A,
+				asset_b: ASSET_B,
+				lp_token_amount: lp_tokens,
+				min_amounts: (0, 0),
+			};
+			assert_ok_with_invariants!(DexModule::batch(
+				Origin::signed(USER_2),
+				vec![remove_liquidity].try_into().unwrap(),
+				true
+			));
+			assert_eq!(lp_token_balance(USER_2), 0);
+			assert!(Assets::balance(ASSET_B, &USER_2) > MINTED_AMOUNT + swap_return - SWAP_AMOUNT);
+		});
+	}
+
+	#[test]
+	fn test_atomic_batch_fails_as_a_whole() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_noop!(
+				DexModule::batch(
+					Origin::signed(USER_2),
+					vec![swap(0), swap(MINTED_AMOUNT)].try_into().unwrap(),
+					true
+				),
+				Error::<Test>::SlippageExceeded
+			);
+		});
+	}
+
+	#[test]
+	fn test_best_effort_batch_skips_failing_operations() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_ok_with_invariants!(DexModule::batch(
+				Origin::signed(USER_2),
+				vec![swap(0), swap(MINTED_AMOUNT), add_liquidity()].try_into().unwrap(),
+				false
+			));
+
+			check_users_balance(USER_2, ASSET_A, MINTED_AMOUNT - 2 * SWAP_AMOUNT);
+			assert!(lp_token_balance(USER_2) > 0);
+			System::assert_has_event(
+				DexEvent::<Test>::BatchExecuted { who: USER_2, operations: 3, failed: vec![1] }
+					.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_batch_weight_adds_up_operations() {
+		new_test_ext().execute_with(|| {
+			let swap_weight = DexModule::batch_weight(&[swap(0)]);
+			let add_weight = DexModule::batch_weight(&[add_liquidity()]);
+			let empty_weight = DexModule::batch_weight(&[]);
+
+			assert_eq!(
+				DexModule::batch_weight(&[swap(0), add_liquidity()]),
+				swap_weight + add_weight - empty_weight
+			);
+		});
+	}
+
+	#[test]
+	fn test_batch_weight_covers_operation_code_paths() {
+		new_test_ext().execute_with(|| {
+			let empty_weight = DexModule::batch_weight(&[]);
+
+			assert!(
+				DexModule::batch_weight(&[swap(0)]) - empty_weight >=
+					DexModule::routed_swap_weight()
+			);
+			assert!(
+				DexModule::batch_weight(&[add_liquidity()]) - empty_weight >=
+					DexModule::long_term_order_settlement_weight() +
+						DexModule::liquidity_position_update_weight()
+			);
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_event_tests {
+	use super::*;
+	use crate::Event as DexEvent;
+
+	const USER_3: AccountId = 3u32;
+	const SWAP_AMOUNT: u128 = 10_000;
+	const LIQUIDITY_AMOUNT: u128 = 100_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+		Balances::make_free_balance_be(&USER_3, MINTED_AMOUNT);
+	}
+
+	#[test]
+	fn test_pool_creation_event() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			System::assert_has_event(
+				DexEvent::<Test>::NewPoolCreated {
+					pool_id,
+					creator: USER,
+					asset_pair: (ASSET_A, ASSET_B),
+					asset_amounts: (ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+					lp_token_id: DexModule::get_lp_token_id(&pool_id),
+					earned_lp_tokens: 1_000_000,
+					reserves: (ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+				}
+				.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_liquidity_provision_event() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				LIQUIDITY_AMOUNT,
+				false
+			));
+
+			System::assert_has_event(
+				DexEvent::<Test>::LiquidityProvided {
+					pool_id,
+					who: USER_2,
+					asset_pair: (ASSET_A, ASSET_B),
+					asset_amounts: (LIQUIDITY_AMOUNT, LIQUIDITY_AMOUNT),
+					lp_token_id: DexModule::get_lp_token_id(&pool_id),
+					earned_lp_tokens: 100_000,
+					reserves: (
+						ASSET_A_AMOUNT + LIQUIDITY_AMOUNT,
+						ASSET_B_AMOUNT + LIQUIDITY_AMOUNT
+					),
+				}
+				.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_swap_event() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let (swap_return, fee) = get_swap_return_and_fee::<u128, Test>(
+				SWAP_AMOUNT,
+				(ASSET_A_AMOUNT, ASSET_B_AMOUNT),
+			)
+			.unwrap();
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				SWAP_AMOUNT,
+				false,
+				USER_3
+			));
+
+			assert!(fee > 0);
+			System::assert_has_event(
+				DexEvent::<Test>::TokensSwapped {
+					pool_id,
+					who: USER_2,
+					recipient: USER_3,
+					asset_in: ASSET_A,
+					asset_out: ASSET_B,
+					amount_in: SWAP_AMOUNT,
+					amount_out: swap_return,
+					fee,
+					reserves: (ASSET_A_AMOUNT + SWAP_AMOUNT, ASSET_B_AMOUNT - swap_return),
+				}
+				.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_liquidity_redemption_event() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				LIQUIDITY_AMOUNT,
+				USER_3
+			));
+
+			System::assert_has_event(
+				DexEvent::<Test>::LiquidityRedeemed {
+					pool_id,
+					who: USER,
+					recipient: USER_3,
+					asset_pair: (ASSET_A, ASSET_B),
+					lp_token_id: DexModule::get_lp_token_id(&pool_id),
+					lp_token_amount: LIQUIDITY_AMOUNT,
+					redeemed_token_amounts: (LIQUIDITY_AMOUNT, LIQUIDITY_AMOUNT),
+					reserves: (
+						ASSET_A_AMOUNT - LIQUIDITY_AMOUNT,
+						ASSET_B_AMOUNT - LIQUIDITY_AMOUNT
+					),
+				}
+				.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_events_track_pool_reserves() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_ok_with_invariants!(DexModule::swap(
+				Origin::signed(USER_2),
+				ASSET_B,
+				ASSET_A,
+				SWAP_AMOUNT,
+				false,
+				USER_2
+			));
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				LIQUIDITY_AMOUNT,
+				false
+			));
+
+			// The reserves of the last event are those of the pool
+			let reserves = System::events()
+				.into_iter()
+				.filter_map(|record| match record.event {
+					Event::DexModule(DexEvent::<Test>::LiquidityProvided { reserves, .. }) =>
+						Some(reserves),
+					_ => None,
+				})
+				.last();
+			assert_eq!(reserves, Some(DexModule::get_pool_liquidity((ASSET_A, ASSET_B)).unwrap()));
+		});
+	}
+}
+
+#[cfg(test)]
+mod pool_stats_tests {
+	use super::*;
+	use crate::DailyPoolStats;
+
+	const SWAP_AMOUNT: u128 = 10_000;
+	const DAY: u64 = 86_400;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+	}
+
+	/// Swap and return the fee the pool kept
+	fn swap(asset_pair: (u32, u32)) -> u128 {
+		let pool_liquidity = DexModule::get_pool_liquidity(asset_pair).unwrap();
+		let (_, fee) = get_swap_return_and_fee::<u128, Test>(SWAP_AMOUNT, pool_liquidity).unwrap();
+		assert_ok_with_invariants!(DexModule::swap(
+			Origin::signed(USER_2),
+			asset_pair.0,
+			asset_pair.1,
+			SWAP_AMOUNT,
+			false,
+			USER_2
+		));
+		fee
+	}
+
+	#[test]
+	fn test_swaps_update_pool_stats() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			System::set_block_number(2);
+			let fee_b = swap((ASSET_A, ASSET_B));
+			System::set_block_number(5);
+			let fee_a = swap((ASSET_B, ASSET_A));
+
+			let stats = DexModule::get_pool_stats((ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(stats.volumes, (SWAP_AMOUNT, SWAP_AMOUNT));
+			assert_eq!(stats.fees, (fee_a, fee_b));
+			assert_eq!(stats.trade_count, 2);
+			assert_eq!(stats.last_trade_block, 5);
+
+			// Amounts follow the order of the requested assets
+			let reversed = DexModule::get_pool_stats((ASSET_B, ASSET_A)).unwrap();
+			assert_eq!(reversed.fees, (fee_b, fee_a));
+		});
+	}
+
+	#[test]
+	fn test_no_stats_for_missing_pool() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(DexModule::get_pool_stats((ASSET_A, ASSET_B)), None);
+			assert!(DexModule::get_daily_pool_stats((ASSET_A, ASSET_B)).is_empty());
+		});
+	}
+
+	#[test]
+	fn test_daily_stats_are_bucketed_by_day() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			set_time_in_secs(DAY);
+			let first_fee = swap((ASSET_A, ASSET_B));
+			set_time_in_secs(2 * DAY - 1);
+			let second_fee = swap((ASSET_A, ASSET_B));
+			set_time_in_secs(2 * DAY);
+			let third_fee = swap((ASSET_A, ASSET_B));
+
+			assert_eq!(
+				DexModule::get_daily_pool_stats((ASSET_A, ASSET_B)),
+				vec![
+					DailyPoolStats {
+						day: 1,
+						volumes: (2 * SWAP_AMOUNT, 0),
+						fees: (0, first_fee + second_fee),
+						trade_count: 2,
+					},
+					DailyPoolStats {
+						day: 2,
+						volumes: (SWAP_AMOUNT, 0),
+						fees: (0, third_fee),
+						trade_count: 1,
+					},
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn test_daily_stats_keep_the_most_recent_days() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			// The mock keeps three days of statistics
+			for day in 1..=4 {
+				set_time_in_secs(day * DAY);
+				swap((ASSET_A, ASSET_B));
+			}
+
+			let days: Vec<u64> = DexModule::get_daily_pool_stats((ASSET_A, ASSET_B))
+				.into_iter()
+				.map(|stats| stats.day)
+				.collect();
+			assert_eq!(days, vec![2, 3, 4]);
+			assert_eq!(DexModule::get_pool_stats((ASSET_A, ASSET_B)).unwrap().trade_count, 4);
+		});
+	}
+}
+
+#[cfg(test)]
+mod liquidity_position_tests {
+	use super::*;
+	use crate::LiquidityPositions;
+	use sp_runtime::Permill;
+
+	const LP_TOKEN_SUPPLY: u128 = 1_000_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[USER_2], &[ASSET_A, ASSET_B], MINTED_AMOUNT);
+	}
+
+	fn swap(asset_pair: (u32, u32), amount: u128) {
+		assert_ok_with_invariants!(DexModule::swap(
+			Origin::signed(USER_2),
+			asset_pair.0,
+			asset_pair.1,
+			amount,
+			false,
+			USER_2
+		));
+	}
+
+	#[test]
+	fn test_pool_creation_opens_position() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.lp_tokens, LP_TOKEN_SUPPLY);
+			assert_eq!(position.value, (ASSET_A_AMOUNT, ASSET_B_AMOUNT));
+			assert_eq!(position.net_deposits, (ASSET_A_AMOUNT, ASSET_B_AMOUNT));
+			assert_eq!(position.fees_earned, (0, 0));
+			assert_eq!(position.impermanent_loss, Permill::zero());
+		});
+	}
+
+	#[test]
+	fn test_provided_liquidity_is_tracked() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			System::set_block_number(3);
+
+			assert_ok_with_invariants!(DexModule::provide_liquidity(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				100_000,
+				false
+			));
+
+			let position = DexModule::get_liquidity_position(&USER_2, (ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.lp_tokens, 100_000);
+			assert_eq!(position.net_deposits, (100_000, 100_000));
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			assert_eq!(
+				LiquidityPositions::<Test>::get(&pool_id, USER_2).unwrap().last_deposit_block,
+				3
+			);
+		});
+	}
+
+	#[test]
+	fn test_swap_fees_are_earned() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			// A round trip leaves the price about where it was, with both fees in the pool
+			swap((ASSET_A, ASSET_B), 100_000);
+			let asset_b_received = Assets::balance(ASSET_B, &USER_2) - MINTED_AMOUNT;
+			swap((ASSET_B, ASSET_A), asset_b_received);
+
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			let reserves = DexModule::get_pool_liquidity((ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.value, reserves);
+			assert_eq!(position.net_deposits, (ASSET_A_AMOUNT, ASSET_B_AMOUNT));
+			assert!(position.fees_earned.0 > 0 && position.fees_earned.1 > 0);
+		});
+	}
+
+	#[test]
+	fn test_price_moves_cause_impermanent_loss() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			swap((ASSET_A, ASSET_B), ASSET_A_AMOUNT);
+
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			assert!(position.impermanent_loss > Permill::from_percent(1));
+
+			// Amounts follow the order of the requested assets
+			let reversed = DexModule::get_liquidity_position(&USER, (ASSET_B, ASSET_A)).unwrap();
+			assert_eq!(reversed.value, (position.value.1, position.value.0));
+			assert_eq!(reversed.fees_earned, (position.fees_earned.1, position.fees_earned.0));
+		});
+	}
+
+	#[test]
+	fn test_redemption_reduces_position() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				LP_TOKEN_SUPPLY / 4,
+				USER
+			));
+
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.lp_tokens, LP_TOKEN_SUPPLY * 3 / 4);
+			assert_eq!(position.net_deposits, (ASSET_A_AMOUNT * 3 / 4, ASSET_B_AMOUNT * 3 / 4));
+		});
+	}
+
+	#[test]
+	fn test_transferred_lp_tokens_take_their_deposits() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+
+			assert_ok_with_invariants!(Assets::transfer(
+				Origin::signed(USER),
+				lp_token_id,
+				USER_2,
+				LP_TOKEN_SUPPLY / 2
+			));
+
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.lp_tokens, LP_TOKEN_SUPPLY / 2);
+			assert_eq!(position.net_deposits, (ASSET_A_AMOUNT / 2, ASSET_B_AMOUNT / 2));
+			// Tokens received by transfer have no cost basis
+			assert_eq!(DexModule::get_liquidity_position(&USER_2, (ASSET_A, ASSET_B)), None);
+		});
+	}
+
+	#[test]
+	fn test_full_redemption_closes_position() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			assert_ok_with_invariants!(DexModule::redeem_lp_tokens(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				LP_TOKEN_SUPPLY,
+				USER
+			));
+
+			assert!(!LiquidityPositions::<Test>::contains_key(&pool_id, USER));
+			assert_eq!(DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)), None);
+		});
+	}
+}
+
+#[cfg(test)]
+mod farming_tests {
+	use super::*;
+	use crate::Event as DexEvent;
+	use sp_runtime::DispatchError;
+
+	const LP_TOKEN_SUPPLY: u128 = 1_000_000;
+	const REWARD_FUNDS: u128 = 1_000_000;
+
+	fn setup_pool() {
+		setup_pool_with_traders(&[], &[], 0);
+		assert_ok_with_invariants!(DexModule::fund_farming_rewards(
+			Origin::signed(USER),
+			REWARD_FUNDS
+		));
+	}
+
+	fn setup_second_staker() {
+		give_user_two_assets(USER_2, (ASSET_A, ASSET_B), MINTED_AMOUNT);
+		assert_ok_with_invariants!(DexModule::provide_liquidity(
+			Origin::signed(USER_2),
+			ASSET_A,
+			ASSET_B,
+			100_000,
+			false
+		));
+	}
+
+	fn set_reward_rate(reward_per_block: u128) {
+		assert_ok_with_invariants!(DexModule::set_farming_reward_rate(
+			Origin::root(),
+			ASSET_A,
+			ASSET_B,
+			reward_per_block
+		));
+	}
+
+	fn stake(user: AccountId, amount: u128) {
+		assert_ok_with_invariants!(DexModule::stake_lp_tokens(
+			Origin::signed(user),
+			ASSET_A,
+			ASSET_B,
+			amount
+		));
+	}
+
+	/// Claim the user's rewards and return the amount paid out
+	fn claim(user: AccountId) -> u128 {
+		let balance = Assets::balance(ASSET_A, &user);
+		assert_ok_with_invariants!(DexModule::claim_farming_rewards(
+			Origin::signed(user),
+			ASSET_A,
+			ASSET_B
+		));
+		Assets::balance(ASSET_A, &user) - balance
+	}
+
+	#[test]
+	fn test_reward_rate_set_by_admin() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+
+			assert_noop!(
+				DexModule::set_farming_reward_rate(Origin::signed(USER), ASSET_A, ASSET_B, 100),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(
+				DexModule::set_farming_reward_rate(Origin::root(), ASSET_A, 3, 100),
+				Error::<Test>::PoolNotFound
+			);
+
+			set_reward_rate(100);
+
+			assert_eq!(DexModule::farming_pool(&pool_id).reward_per_block, 100);
+			System::assert_has_event(
+				DexEvent::<Test>::FarmingRewardRateSet { pool_id, reward_per_block: 100 }.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_rewards_accrue_per_block() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			set_reward_rate(100);
+			stake(USER, LP_TOKEN_SUPPLY);
+
+			System::set_block_number(11);
+
+			assert_eq!(claim(USER), 1_000);
+			System::assert_has_event(
+				DexEvent::<Test>::FarmingRewardsClaimed { pool_id, who: USER, amount: 1_000 }
+					.into(),
+			);
+			assert_noop!(
+				DexModule::claim_farming_rewards(Origin::signed(USER), ASSET_A, ASSET_B),
+				Error::<Test>::NoFarmingRewards
+			);
+		});
+	}
+
+	#[test]
+	fn test_rewards_shared_by_stake() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			setup_second_staker();
+			set_reward_rate(100);
+			stake(USER, 300_000);
+			stake(USER_2, 100_000);
+
+			System::set_block_number(11);
+			assert_ok_with_invariants!(DexModule::unstake_lp_tokens(
+				Origin::signed(USER_2),
+				ASSET_A,
+				ASSET_B,
+				100_000
+			));
+
+			// The unstaked tokens keep the rewards they earned, but earn no more
+			System::set_block_number(21);
+			assert_eq!(claim(USER), 750 + 1_000);
+			assert_eq!(claim(USER_2), 250);
+		});
+	}
+
+	#[test]
+	fn test_reward_rate_changes_apply_from_then_on() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			set_reward_rate(100);
+			stake(USER, LP_TOKEN_SUPPLY);
+
+			System::set_block_number(11);
+			set_reward_rate(200);
+			System::set_block_number(21);
+
+			assert_eq!(claim(USER), 1_000 + 2_000);
+		});
+	}
+
+	#[test]
+	fn test_staking_moves_lp_tokens_into_escrow() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			let lp_token_id = DexModule::get_lp_token_id(&pool_id);
+
+			stake(USER, LP_TOKEN_SUPPLY / 2);
+
+			check_users_balance(USER, lp_token_id, LP_TOKEN_SUPPLY / 2);
+			check_users_balance(DexModule::account_id(), lp_token_id, LP_TOKEN_SUPPLY / 2);
+			assert_eq!(DexModule::farming_pool(&pool_id).total_staked, LP_TOKEN_SUPPLY / 2);
+			System::assert_has_event(
+				DexEvent::<Test>::LpTokensStaked {
+					pool_id,
+					who: USER,
+					amount: LP_TOKEN_SUPPLY / 2,
+				}
+				.into(),
+			);
+
+			// Staked LP tokens are still part of the liquidity position
+			let position = DexModule::get_liquidity_position(&USER, (ASSET_A, ASSET_B)).unwrap();
+			assert_eq!(position.lp_tokens, LP_TOKEN_SUPPLY);
+
+			assert_noop!(
+				DexModule::stake_lp_tokens(Origin::signed(USER), ASSET_A, ASSET_B, LP_TOKEN_SUPPLY),
+				Error::<Test>::NotEnoughLPTokens
+			);
+			assert_noop!(
+				DexModule::unstake_lp_tokens(
+					Origin::signed(USER),
+					ASSET_A,
+					ASSET_B,
+					LP_TOKEN_SUPPLY
+				),
+				Error::<Test>::NotEnoughStakedLpTokens
+			);
+		});
+	}
+
+	#[test]
+	fn test_claim_fails_without_reward_funds() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			set_reward_rate(REWARD_FUNDS);
+			stake(USER, LP_TOKEN_SUPPLY);
+
+			System::set_block_number(3);
+
+			assert_noop!(
+				DexModule::claim_farming_rewards(Origin::signed(USER), ASSET_A, ASSET_B),
+				Error::<Test>::NotEnoughFarmingRewardFunds
+			);
+		});
+	}
+
+	#[test]
+	fn test_compounding_stakes_rewards_as_liquidity() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			let pool_id = DexModule::get_pool_id((ASSET_A, ASSET_B));
+			set_reward_rate(1_000);
+			stake(USER, LP_TOKEN_SUPPLY);
+
+			System::set_block_number(11);
+			assert_ok_with_invariants!(DexModule::compound_farming_rewards(
+				Origin::signed(USER),
+				ASSET_A,
+				ASSET_B,
+				0,
+				0
+			));
+
+			let lp_tokens = DexModule::farming_stake(&pool_id, USER).staked - LP_TOKEN_SUPPLY;
+			assert!(lp_tokens > 0);
+			System::assert_has_event(
+				DexEvent::<Test>::FarmingRewardsCompounded {
+					pool_id,
+					who: USER,
+					rewards: 10_000,
+					lp_tokens,
+				}
+				.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_compounding_swap_respects_min_amount_out() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			set_reward_rate(1_000);
+			stake(USER, LP_TOKEN_SUPPLY);
+
+			// The swap fee keeps the return of half the rewards below 5_000
+			System::set_block_number(11);
+			assert_noop!(
+				DexModule::compound_farming_rewards(
+					Origin::signed(USER),
+					ASSET_A,
+					ASSET_B,
+					5_000,
+					0
+				),
+				Error::<Test>::SlippageExceeded
+			);
+		});
+	}
+
+	#[test]
+	fn test_compounding_needs_reward_asset_in_pool() {
+		new_test_ext().execute_with(|| {
+			setup_pool();
+			create_liquidity_pool(USER, (3, 4), (ASSET_A_AMOUNT, ASSET_B_AMOUNT), MINTED_AMOUNT);
+
+			assert_noop!(
+				DexModule::compound_farming_rewards(Origin::signed(USER), 3, 4, 0, 0),
+				Error::<Test>::RewardAssetNotInPool
+			);
+		});
+	}
+}
@@ -0,0 +1,250 @@
+use super::*;
+use frame_support::storage::{with_transaction, TransactionOutcome};
+
+/// Read-only access to DEX prices for other pallets
+pub trait DexPriceProvider<AssetId, Balance> {
+	/// Price of `asset_a` denominated in `asset_b`, based on the pool reserves once its
+	/// long-term orders are settled
+	fn spot_price(asset_a: AssetId, asset_b: AssetId) -> Option<FixedU128>;
+
+	/// Time weighted average price of `asset_a` denominated in `asset_b`
+	/// over the last `window` seconds
+	fn twap(asset_a: AssetId, asset_b: AssetId, window: u64) -> Option<FixedU128>;
+
+	/// Value of `amount` of `asset_a` denominated in `asset_b` at the spot price
+	fn value_of(asset_a: AssetId, asset_b: AssetId, amount: Balance) -> Option<Balance>;
+
+	/// Value of `amount` of `asset_a` denominated in `asset_b` at the time weighted average
+	/// price over the last `window` seconds
+	fn twap_value_of(
+		asset_a: AssetId,
+		asset_b: AssetId,
+		amount: Balance,
+		window: u64,
+	) -> Option<Balance>;
+}
+
+impl<T: Config> DexPriceProvider<AssetIdOf<T>, BalanceOf<T>> for Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	fn spot_price(asset_a: AssetIdOf<T>, asset_b: AssetIdOf<T>) -> Option<FixedU128> {
+		Self::get_settled_spot_price((asset_a, asset_b))
+	}
+
+	fn twap(asset_a: AssetIdOf<T>, asset_b: AssetIdOf<T>, window: u64) -> Option<FixedU128> {
+		Self::get_twap((asset_a, asset_b), window)
+	}
+
+	fn value_of(
+		asset_a: AssetIdOf<T>,
+		asset_b: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> Option<BalanceOf<T>> {
+		let price = Self::get_settled_spot_price((asset_a, asset_b))?;
+		Self::convert_with_price(price, amount)
+	}
+
+	fn twap_value_of(
+		asset_a: AssetIdOf<T>,
+		asset_b: AssetIdOf<T>,
+		amount: BalanceOf<T>,
+		window: u64,
+	) -> Option<BalanceOf<T>> {
+		let price = Self::get_twap((asset_a, asset_b), window)?;
+		Self::convert_with_price(price, amount)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	fn convert_with_price(price: FixedU128, amount: BalanceOf<T>) -> Option<BalanceOf<T>> {
+		let value = price.checked_mul_int(amount.saturated_into::<u128>())?;
+		BalanceOf::<T>::try_from(value).ok()
+	}
+
+	/// Reserves of the pool after the virtual trades of its long-term orders, which are settled
+	/// without storing the settlement
+	fn get_settled_pool_liquidity(
+		asset_pair: (AssetIdOf<T>, AssetIdOf<T>),
+	) -> Option<(BalanceOf<T>, BalanceOf<T>)> {
+		with_transaction(|| {
+			let pool_liquidity = Self::settle_long_term_orders(asset_pair)
+				.and_then(|_| Self::get_pool_liquidity(asset_pair));
+			TransactionOutcome::Rollback(pool_liquidity)
+		})
+		.ok()
+	}
+
+	/// Spot price of asset_pair.0 in asset_pair.1, at the settled reserves quotes are based on
+	fn get_settled_spot_price(asset_pair: (AssetIdOf<T>, AssetIdOf<T>)) -> Option<FixedU128> {
+		let pool_liquidity = Self::get_settled_pool_liquidity(asset_pair)?;
+		FixedU128::checked_from_rational(
+			pool_liquidity.1.saturated_into::<u128>(),
+			pool_liquidity.0.saturated_into::<u128>(),
+		)
+	}
+}
+
+/// Swapping through the DEX from other pallets, without a signed extrinsic
+pub trait SwapHandler<AccountId, AssetId, Balance> {
+	/// Expected return for swapping `amount_in` of `asset_in` for `asset_out`, once the pool's
+	/// long-term orders are settled
+	fn quote_swap(asset_in: AssetId, asset_out: AssetId, amount_in: Balance) -> Option<Balance>;
+
+	/// Amount of `asset_in` required to receive at least `amount_out` of `asset_out`
+	fn quote_swap_input(
+		asset_in: AssetId,
+		asset_out: AssetId,
+		amount_out: Balance,
+	) -> Option<Balance>;
+
+	/// Swap `amount_in` of `asset_in` for at least `min_amount_out` of `asset_out`, routed and
+	/// limited like a `swap` extrinsic. Pools in batch auction mode do not take direct swaps.
+	/// A swap tripping the pool's circuit breaker fails, the trip is only kept if the caller
+	/// does not revert its own changes. Returns the amount of `asset_out` received.
+	fn swap(
+		who: &AccountId,
+		asset_in: AssetId,
+		asset_out: AssetId,
+		amount_in: Balance,
+		min_amount_out: Balance,
+	) -> Result<Balance, DispatchError>;
+}
+
+/// Managing DEX liquidity from other pallets, without a signed extrinsic
+pub trait LiquidityHandler<AccountId, AssetId, Balance> {
+	/// Deposit `amount_a` of `asset_a` and the matching amount of `asset_b`, which may not exceed
+	/// `max_amount_b`, for at least `min_lp_tokens`.
+	/// Returns the amount of `asset_b` deposited and the LP tokens minted.
+	fn add_liquidity(
+		who: &AccountId,
+		asset_a: AssetId,
+		asset_b: AssetId,
+		amount_a: Balance,
+		max_amount_b: Balance,
+		min_lp_tokens: Balance,
+	) -> Result<(Balance, Balance), DispatchError>;
+
+	/// Redeem `lp_token_amount` for at least `min_amounts` of the pool assets.
+	/// Returns the amounts of `asset_a` and `asset_b` received.
+	fn remove_liquidity(
+		who: &AccountId,
+		asset_a: AssetId,
+		asset_b: AssetId,
+		lp_token_amount: Balance,
+		min_amounts: (Balance, Balance),
+	) -> Result<(Balance, Balance), DispatchError>;
+}
+
+impl<T: Config> SwapHandler<T::AccountId, AssetIdOf<T>, BalanceOf<T>> for Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	fn quote_swap(
+		asset_in: AssetIdOf<T>,
+		asset_out: AssetIdOf<T>,
+		amount_in: BalanceOf<T>,
+	) -> Option<BalanceOf<T>> {
+		let pool_liquidity = Self::get_settled_pool_liquidity((asset_in, asset_out))?;
+		get_swap_return::<BalanceOf<T>, T>(amount_in, pool_liquidity).ok()
+	}
+
+	fn quote_swap_input(
+		asset_in: AssetIdOf<T>,
+		asset_out: AssetIdOf<T>,
+		amount_out: BalanceOf<T>,
+	) -> Option<BalanceOf<T>> {
+		let pool_liquidity = Self::get_settled_pool_liquidity((asset_in, asset_out))?;
+		get_swap_input::<BalanceOf<T>, T>(amount_out, pool_liquidity).ok()
+	}
+
+	fn swap(
+		who: &T::AccountId,
+		asset_in: AssetIdOf<T>,
+		asset_out: AssetIdOf<T>,
+		amount_in: BalanceOf<T>,
+		min_amount_out: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		Self::check_deposit_is_valid(who, (asset_in, asset_out), (amount_in, 0u32.into()), false)?;
+		Self::check_direct_swaps_allowed((asset_in, asset_out))?;
+		Self::check_swaps_allowed((asset_in, asset_out))?;
+
+		// The swap reverts itself, a trip of the circuit breaker is kept even though it fails
+		Self::process_swap_with_circuit_breaker(
+			who,
+			who,
+			(asset_in, asset_out),
+			amount_in,
+			min_amount_out,
+			false,
+		)?
+		.ok_or_else(|| Error::<T>::PriceMovementLimitExceeded.into())
+	}
+}
+
+impl<T: Config> LiquidityHandler<T::AccountId, AssetIdOf<T>, BalanceOf<T>> for Pallet<T>
+where
+	<T::Assets as Inspect<T::AccountId>>::AssetId: AtLeast32Bit,
+{
+	#[transactional]
+	fn add_liquidity(
+		who: &T::AccountId,
+		asset_a: AssetIdOf<T>,
+		asset_b: AssetIdOf<T>,
+		amount_a: BalanceOf<T>,
+		max_amount_b: BalanceOf<T>,
+		min_lp_tokens: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::settle_long_term_orders((asset_a, asset_b))?;
+		let pool_liquidity = Self::get_pool_liquidity((asset_a, asset_b))?;
+		let amount_b = Self::derive_second_asset_amount(pool_liquidity, amount_a)?;
+		ensure!(amount_b <= max_amount_b, Error::<T>::SlippageExceeded);
+
+		Self::check_deposit_is_valid(who, (asset_a, asset_b), (amount_a, amount_b), false)?;
+		Self::check_liquidity_provision_allowed((asset_a, asset_b))?;
+
+		let lp_tokens = Self::process_liquidity_pool_deposit(
+			who,
+			who,
+			(asset_a, asset_b),
+			(amount_a, amount_b),
+			pool_liquidity.0,
+			false,
+		)?;
+		ensure!(lp_tokens >= min_lp_tokens, Error::<T>::SlippageExceeded);
+
+		Ok((amount_b, lp_tokens))
+	}
+
+	#[transactional]
+	fn remove_liquidity(
+		who: &T::AccountId,
+		asset_a: AssetIdOf<T>,
+		asset_b: AssetIdOf<T>,
+		lp_token_amount: BalanceOf<T>,
+		min_amounts: (BalanceOf<T>, BalanceOf<T>),
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let pool_id = Self::get_pool_id((asset_a, asset_b));
+		let lp_token_id = Self::get_lp_token_id(&pool_id);
+
+		Self::check_lp_redemption_is_valid(who, lp_token_id, lp_token_amount, (asset_a, asset_b))?;
+
+		let redeemed_token_amounts = Self::handle_lp_token_redemption(
+			who,
+			who,
+			pool_id,
+			lp_token_id,
+			lp_token_amount,
+			(asset_a, asset_b),
+		)?;
+		ensure!(
+			redeemed_token_amounts.0 >= min_amounts.0 && redeemed_token_amounts.1 >= min_amounts.1,
+			Error::<T>::SlippageExceeded
+		);
+
+		Ok(redeemed_token_amounts)
+	}
+}
@@ -0,0 +1,81 @@
+//! Pay transaction fees in any asset listed on the DEX
+
+use crate::{AccountId, Assets, Balance, Call, DexModule, NativeAssetId, Runtime};
+use frame_support::traits::{
+	tokens::fungibles::{Balanced, CreditOf},
+	Get, OnUnbalanced,
+};
+use pallet_dex::SwapHandler;
+use sp_runtime::{
+	traits::{DispatchInfoOf, PostDispatchInfoOf, Zero},
+	transaction_validity::{InvalidTransaction, TransactionValidityError},
+};
+use sp_std::marker::PhantomData;
+
+/// Charges transaction fees in a pool-listed asset.
+///
+/// The fee asset is swapped through the DEX into `NativeAssetId`, the wrapped native currency
+/// DEX pools trade, and the fee is withdrawn from it. After dispatch, overestimated fees are
+/// refunded out of the withdrawn amount. The rest stays in the wrapped asset and is handed to
+/// `OU`, which burns it when it drops the credit. No native currency is ever issued for it, as
+/// the wrapped tokens are not backed by any.
+pub struct DexSwapFeeAdapter<OU>(PhantomData<OU>);
+
+impl<OU> pallet_asset_tx_payment::OnChargeAssetTransaction<Runtime> for DexSwapFeeAdapter<OU>
+where
+	OU: OnUnbalanced<CreditOf<AccountId, Assets>>,
+{
+	type Balance = Balance;
+	type AssetId = u32;
+	type LiquidityInfo = CreditOf<AccountId, Assets>;
+
+	fn withdraw_fee(
+		who: &AccountId,
+		_call: &Call,
+		_dispatch_info: &DispatchInfoOf<Call>,
+		asset_id: u32,
+		fee: Balance,
+		_tip: Balance,
+	) -> Result<Self::LiquidityInfo, TransactionValidityError> {
+		let native_asset = NativeAssetId::get();
+		if fee.is_zero() {
+			return Ok(CreditOf::<AccountId, Assets>::zero(native_asset))
+		}
+
+		if asset_id != native_asset {
+			let amount_in = <DexModule as SwapHandler<_, _, _>>::quote_swap_input(
+				asset_id,
+				native_asset,
+				fee,
+			)
+			.ok_or(InvalidTransaction::Payment)?;
+			<DexModule as SwapHandler<_, _, _>>::swap(who, asset_id, native_asset, amount_in, fee)
+				.map_err(|_| InvalidTransaction::Payment)?;
+		}
+
+		<Assets as Balanced<AccountId>>::withdraw(native_asset, who, fee)
+			.map_err(|_| InvalidTransaction::Payment.into())
+	}
+
+	fn correct_and_deposit_fee(
+		who: &AccountId,
+		_dispatch_info: &DispatchInfoOf<Call>,
+		_post_info: &PostDispatchInfoOf<Call>,
+		corrected_fee: Balance,
+		tip: Balance,
+		already_withdrawn: Self::LiquidityInfo,
+	) -> Result<(Balance, Balance), TransactionValidityError> {
+		let (fee, refund) = already_withdrawn.split(corrected_fee);
+
+		// A refund the account cannot receive, e.g. below the asset's minimum balance, is kept
+		let fee = match <Assets as Balanced<AccountId>>::resolve(who, refund) {
+			Ok(()) => fee,
+			Err(refund) => fee.merge(refund).map_err(|_| InvalidTransaction::Payment)?,
+		};
+
+		let fee_amount = fee.peek();
+		OU::on_unbalanced(fee);
+
+		Ok((fee_amount, tip))
+	}
+}
@@ -6,8 +6,12 @@
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
+mod asset_fee_payment;
+#[cfg(test)]
+mod tests;
+
 use frame_support::PalletId;
-use frame_system::EnsureRoot;
+use frame_system::{EnsureRoot, EnsureSigned};
 use pallet_grandpa::{
 	fg_primitives, AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList,
 };
@@ -29,7 +33,8 @@ use sp_version::RuntimeVersion;
 pub use frame_support::{
 	construct_runtime, parameter_types,
 	traits::{
-		ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, Randomness, StorageInfo,
+		ConstU128, ConstU32, ConstU64, ConstU8, Contains, KeyOwnerProofSystem, Randomness,
+		StorageInfo,
 	},
 	weights::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
@@ -43,7 +48,7 @@ pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::CurrencyAdapter;
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
-pub use sp_runtime::{Perbill, Permill};
+pub use sp_runtime::{FixedU128, Perbill, Permill};
 
 /// Import the template pallet.
 pub use pallet_dex;
@@ -263,6 +268,18 @@ impl pallet_transaction_payment::Config for Runtime {
 	type FeeMultiplierUpdate = ();
 }
 
+parameter_types! {
+	/// Asset representing the native currency inside DEX pools
+	pub const NativeAssetId: u32 = 0;
+}
+
+impl pallet_asset_tx_payment::Config for Runtime {
+	type Event = Event;
+	type Fungibles = Assets;
+	// Fees paid in assets are swapped into the wrapped native asset and burned
+	type OnChargeAssetTransaction = asset_fee_payment::DexSwapFeeAdapter<()>;
+}
+
 impl pallet_sudo::Config for Runtime {
 	type Event = Event;
 	type Call = Call;
@@ -295,14 +312,49 @@ impl pallet_assets::Config for Runtime {
 
 parameter_types! {
 	pub const PoolPalletId: PalletId = PalletId(*b"the/pool");
+	pub const FlashLoanFee: Permill = Permill::from_parts(900);
+	pub const MakerFee: Permill = Permill::from_parts(1_000);
+	pub const TakerFee: Permill = Permill::from_parts(2_000);
+	pub const CommitmentDeposit: Balance = 1_000_000_000;
+	pub const PoolCreationDeposit: Balance = 100_000_000_000_000;
+}
+
+/// Calls a borrower may make while holding a DEX flash loan
+pub struct FlashLoanCallFilter;
+impl Contains<Call> for FlashLoanCallFilter {
+	fn contains(call: &Call) -> bool {
+		matches!(call, Call::Assets(_) | Call::DexModule(pallet_dex::Call::swap { .. }))
+	}
 }
 
 /// Configure the pallet-dex in pallets/template.
 impl pallet_dex::Config for Runtime {
 	type Event = Event;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type CreatePoolOrigin = EnsureSigned<AccountId>;
 	type Assets = Assets;
 	type Balances = Balances;
 	type PalletId = PoolPalletId;
+	type UnixTime = Timestamp;
+	type MaxPriceObservations = ConstU32<64>;
+	type Call = Call;
+	type FlashLoanCallFilter = FlashLoanCallFilter;
+	type FlashLoanFee = FlashLoanFee;
+	type MaxOrdersPerPool = ConstU32<64>;
+	type MakerFee = MakerFee;
+	type TakerFee = TakerFee;
+	type MaxDcaExecutionsPerBlock = ConstU32<32>;
+	type MaxConditionalExecutionsPerBlock = ConstU32<16>;
+	type MaxBatchSwapsPerPool = ConstU32<64>;
+	type CommitmentDeposit = CommitmentDeposit;
+	type MinRevealDelay = ConstU32<1>;
+	type RevealWindow = ConstU32<{ 10 * MINUTES }>;
+	type MaxCommitmentExpiriesPerBlock = ConstU32<64>;
+	type MaxForcedRedemptions = ConstU32<64>;
+	type PoolCreationDeposit = PoolCreationDeposit;
+	type MaxBatchOperations = ConstU32<16>;
+	type MaxStatsDays = ConstU32<30>;
+	type FarmingRewardAsset = NativeAssetId;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -319,6 +371,7 @@ construct_runtime!(
 		Grandpa: pallet_grandpa,
 		Balances: pallet_balances,
 		TransactionPayment: pallet_transaction_payment,
+		AssetTxPayment: pallet_asset_tx_payment,
 		Sudo: pallet_sudo,
 		Assets: pallet_assets,
 		// Include the custom logic from the pallet-dex in the runtime.
@@ -341,7 +394,7 @@ pub type SignedExtra = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_asset_tx_payment::ChargeAssetTxPayment<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -502,6 +555,34 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_dex_runtime_api::DexApi<Block, AccountId, u32, Balance, BlockNumber> for Runtime {
+		fn get_twap(asset_a: u32, asset_b: u32, window: u64) -> Option<FixedU128> {
+			DexModule::get_twap((asset_a, asset_b), window)
+		}
+
+		fn get_pool_stats(
+			asset_a: u32,
+			asset_b: u32,
+		) -> Option<pallet_dex::PoolStats<Balance, BlockNumber>> {
+			DexModule::get_pool_stats((asset_a, asset_b))
+		}
+
+		fn get_daily_pool_stats(
+			asset_a: u32,
+			asset_b: u32,
+		) -> Vec<pallet_dex::DailyPoolStats<Balance>> {
+			DexModule::get_daily_pool_stats((asset_a, asset_b))
+		}
+
+		fn get_liquidity_position(
+			who: AccountId,
+			asset_a: u32,
+			asset_b: u32,
+		) -> Option<pallet_dex::LiquidityPositionReport<Balance>> {
+			DexModule::get_liquidity_position(&who, (asset_a, asset_b))
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
@@ -0,0 +1,108 @@
+use crate::*;
+use frame_support::{
+	assert_ok,
+	traits::Currency,
+	weights::{GetDispatchInfo, Pays, PostDispatchInfo},
+};
+use pallet_asset_tx_payment::ChargeAssetTxPayment;
+use pallet_dex::SwapHandler;
+use sp_runtime::traits::SignedExtension;
+
+const MAGIC: u32 = 1;
+const POOL_LIQUIDITY: Balance = 1_000_000_000_000_000;
+const USER_MAGIC_BALANCE: Balance = 1_000_000_000_000;
+
+fn alice() -> AccountId {
+	AccountId::new([1u8; 32])
+}
+
+fn bob() -> AccountId {
+	AccountId::new([2u8; 32])
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let native_asset = NativeAssetId::get();
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+	pallet_balances::GenesisConfig::<Runtime> { balances: vec![(alice(), 1 << 60)] }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+	pallet_assets::GenesisConfig::<Runtime> {
+		assets: vec![(native_asset, alice(), true, 1), (MAGIC, alice(), true, 1)],
+		metadata: vec![],
+		accounts: vec![
+			(native_asset, alice(), POOL_LIQUIDITY),
+			(MAGIC, alice(), POOL_LIQUIDITY),
+			(MAGIC, bob(), USER_MAGIC_BALANCE),
+		],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	// The chain's genesis pool of MAGIC and the wrapped native currency
+	pallet_dex::GenesisConfig::<Runtime> {
+		pools: vec![(alice(), MAGIC, native_asset, POOL_LIQUIDITY, POOL_LIQUIDITY)],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	storage.into()
+}
+
+#[test]
+fn fees_can_be_paid_in_pool_listed_assets() {
+	new_test_ext().execute_with(|| {
+		let native_asset = NativeAssetId::get();
+		let native_issuance = Balances::total_issuance();
+
+		let call = Call::System(frame_system::Call::remark { remark: vec![] });
+		let info = call.get_dispatch_info();
+		let len = 10;
+
+		let fee = TransactionPayment::compute_fee(len as u32, &info, 0);
+		let magic_spent =
+			<DexModule as SwapHandler<_, _, _>>::quote_swap_input(MAGIC, native_asset, fee)
+				.unwrap();
+		let native_received =
+			<DexModule as SwapHandler<_, _, _>>::quote_swap(MAGIC, native_asset, magic_spent)
+				.unwrap();
+
+		let pre = ChargeAssetTxPayment::<Runtime>::from(0, Some(MAGIC))
+			.pre_dispatch(&bob(), &call, &info, len)
+			.unwrap();
+
+		// Bob has no native currency, the fee was paid by swapping MAGIC
+		assert_eq!(Balances::free_balance(bob()), 0);
+		assert_eq!(Assets::balance(MAGIC, bob()), USER_MAGIC_BALANCE - magic_spent);
+		assert_eq!(Assets::balance(native_asset, bob()), native_received - fee);
+
+		// Overestimated weight is refunded
+		let post_info =
+			PostDispatchInfo { actual_weight: Some(info.weight / 2), pays_fee: Pays::Yes };
+		let corrected_fee =
+			TransactionPayment::compute_actual_fee(len as u32, &info, &post_info, 0);
+		assert!(corrected_fee < fee);
+
+		assert_ok!(ChargeAssetTxPayment::<Runtime>::post_dispatch(
+			Some(pre),
+			&info,
+			&post_info,
+			len,
+			&Ok(())
+		));
+		assert_eq!(Assets::balance(native_asset, bob()), native_received - corrected_fee);
+
+		// The fee was burned in the wrapped asset, no native currency was issued for it
+		assert_eq!(Assets::total_issuance(native_asset), POOL_LIQUIDITY - corrected_fee);
+		assert_eq!(Balances::total_issuance(), native_issuance);
+	});
+}
+
+#[test]
+fn fees_cannot_be_paid_in_assets_without_a_pool() {
+	new_test_ext().execute_with(|| {
+		let call = Call::System(frame_system::Call::remark { remark: vec![] });
+		let info = call.get_dispatch_info();
+
+		assert!(ChargeAssetTxPayment::<Runtime>::from(0, Some(2u32))
+			.pre_dispatch(&bob(), &call, &info, 10)
+			.is_err());
+	});
+}